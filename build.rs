@@ -0,0 +1,32 @@
+fn main() {
+    // regenerate the C header whenever the C library is built, into OUT_DIR so
+    // building never touches the source tree, set ARMLAB_RADIO_HEADER to a path
+    // to get a copy there (examples/c/Makefile does)
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-env-changed=ARMLAB_RADIO_HEADER");
+
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+            .expect("invalid cbindgen.toml");
+
+        let header = cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{}/src/ffi.rs", crate_dir))
+            .generate()
+            .expect("unable to generate C header");
+
+        header.write_to_file(format!("{}/armlab_radio.h", out_dir));
+
+        if let Ok(path) = std::env::var("ARMLAB_RADIO_HEADER") {
+            // the copy is rewritten if it is edited or deleted
+            println!("cargo:rerun-if-changed={}", path);
+            header.write_to_file(path);
+        }
+    }
+
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,4 @@
+//! builds `libarmlab_radio.a` / `libarmlab_radio.so`, the functions
+//! themselves are in `ArmlabRadio::ffi`
+
+pub use ::ArmlabRadio::ffi::*;
@@ -1,5 +1,6 @@
 use std::{time::Duration, thread};
 
+use ArmlabRadio::beacon::{Arrival, BeaconConfig, BeaconReceiver, BeaconTransmitter};
 use ArmlabRadio::radio_serial::{Radio, prompt_port};
 
 macro_rules! input {
@@ -21,44 +22,58 @@ macro_rules! input {
 }
 
 
-const DELAY: u64 = 1;
+/// time between polls of the Radio while recieving
+const POLL_DELAY: u64 = 1;
+
+/// sequence numbers per PER report
+const WINDOW: u32 = 100;
 
 fn tx(radio: &mut Radio) {
-    let mut i = 0;
+    let mut config = BeaconConfig::default();
+
+    if let Ok(n) = input!("interval ms (100)> ").parse::<u64>() {
+        config.interval = Duration::from_millis(n);
+    }
+
+    config.count = input!("count (forever)> ").parse::<u32>().ok();
+
+    let template = input!("template, {seq} and {time} are replaced (\"beacon | {seq}\")> ");
+    if !template.is_empty() {
+        config.template = template.into_bytes();
+    }
+
+    let mut beacon = match BeaconTransmitter::new(config) {
+        Ok(n) => n,
+        Err(_) => {
+            println!("Error template is too long for a single packet");
+            return;
+        },
+    };
 
     loop {
-        let msg: String = "beacon | ".to_string() + i.to_string().as_str();
-        match radio.transmit(msg.as_bytes()) {
-            Ok(_) => {
-                println!("{}", msg);
-                i += 1;
-            },
+        match beacon.wait_and_send(radio) {
+            Ok(Some(seq)) => {println!("sent beacon {}", seq)},
+            Ok(None) => {break;},
             Err(_) => {
                 println!("Error sending message");
-                radio.sync(6);
+                let _ = radio.sync(6);
             }
         };
-
-        thread::sleep(Duration::from_millis(DELAY));
-        
     }
 
+    println!("sent {} beacons", beacon.seq());
 }
 
 fn rx(radio: &mut Radio) {
+    let mut receiver = BeaconReceiver::new(WINDOW);
+    let mut reported: usize = 0;
+
     loop {
-        thread::sleep(Duration::from_millis(DELAY));
-
-        let packet: String = match radio.get_packet() {
-            Ok(n) => {
-                match std::str::from_utf8(&n) {
-                    Ok(v) => v.to_string(),
-                    Err(_) => {
-                        println!("{:#?}", n);
-                        continue;
-                    },
-                }
-            },
+        thread::sleep(Duration::from_millis(POLL_DELAY));
+
+        let (frame, arrival) = match receiver.poll(radio) {
+            Ok(Some(n)) => n,
+            Ok(None) => {continue;},
             Err(_) => {
                 println!("Error recieving message");
                 let _ = radio.sync(6);
@@ -66,10 +81,26 @@ fn rx(radio: &mut Radio) {
             }
         };
 
-        if packet != "" {
-            println!("got \"{}\"", packet);
+        let note = match arrival {
+            Arrival::InOrder => "",
+            Arrival::OutOfOrder => " (out of order)",
+            Arrival::Duplicate => " (duplicate)",
+            Arrival::Stale => " (too old to count)",
+        };
+        println!("got {} \"{}\"{}", frame.seq, String::from_utf8_lossy(&frame.payload), note);
+
+        // a line of statistics each time a window of sequence numbers completes
+        for window in &receiver.windows()[reported..] {
+            let stats = receiver.stats();
+
+            println!(
+                "-- {}-{} | PER {:.1}% | total: {} recieved, {} lost, {} duplicate, {} out of order | PER {:.1}% | interval {:.1} ms | jitter {:.2} ms",
+                window.first_seq, window.first_seq.wrapping_add(window.expected - 1), window.per * 100.0,
+                stats.received, stats.lost, stats.duplicates, stats.out_of_order, stats.per * 100.0,
+                stats.mean_interval / 1000.0, stats.jitter / 1000.0,
+            );
         }
-        
+        reported = receiver.windows().len();
     }
 }
 
@@ -91,9 +122,8 @@ fn main() {
 
     if tx_mode {
         tx(&mut radio);
+        return;
     }
 
     rx(&mut radio);
-
-   
-}
\ No newline at end of file
+}
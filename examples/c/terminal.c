@@ -0,0 +1,75 @@
+/*
+ * minimal C client for the ArmlabRadio C library
+ *
+ * finds a Radio (serial first, then i2c), tunes it, sends a message and
+ * prints any packets recieved over the next few seconds
+ *
+ * build with `make` in this directory, see the Makefile
+ */
+
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "armlab_radio.h"
+
+#define MAX_DEVICES 8
+
+static ArmlabRadio *open_radio(void) {
+    ArmlabDevice devices[MAX_DEVICES];
+    ArmlabRadio *radio = NULL;
+    size_t count = 0;
+
+    if (armlab_radio_discover_serial(devices, MAX_DEVICES, &count) == ARMLAB_OK && count > 0) {
+        printf("found radio on %s\n", devices[0].path);
+        if (armlab_radio_open_serial(devices[0].path, &radio) == ARMLAB_OK) {
+            return radio;
+        }
+    }
+
+    if (armlab_radio_discover_i2c(devices, MAX_DEVICES, &count) == ARMLAB_OK && count > 0) {
+        printf("found radio on %s at 0x%02x\n", devices[0].path, devices[0].address);
+        if (armlab_radio_open_i2c(devices[0].path, devices[0].address, &radio) == ARMLAB_OK) {
+            return radio;
+        }
+    }
+
+    return NULL;
+}
+
+int main(void) {
+    ArmlabRadio *radio = open_radio();
+    if (radio == NULL) {
+        fprintf(stderr, "no radio found\n");
+        return 1;
+    }
+
+    ArmlabStatus res = armlab_radio_set_frequency(radio, 915e6f);
+    if (res != ARMLAB_OK) {
+        fprintf(stderr, "error setting frequency | %d\n", res);
+    }
+
+    const char *msg = "hello from C";
+    res = armlab_radio_transmit(radio, (const uint8_t *)msg, strlen(msg));
+    if (res != ARMLAB_OK) {
+        fprintf(stderr, "error transmitting | %d\n", res);
+    }
+
+    uint8_t packet[255];
+    for (int i = 0; i < 50; i++) {
+        size_t len = 0;
+
+        res = armlab_radio_get_packet(radio, packet, sizeof(packet), &len);
+        if (res != ARMLAB_OK) {
+            fprintf(stderr, "error getting packet | %d\n", res);
+        }
+        else if (len > 0) {
+            printf("got \"%.*s\"\n", (int)len, (const char *)packet);
+        }
+
+        usleep(100 * 1000);
+    }
+
+    armlab_radio_close(radio);
+    return 0;
+}
@@ -0,0 +1,99 @@
+use std::{env, process::ExitCode, time::Duration};
+
+use ArmlabRadio::linklog::{load_log, Link, LinkRecorder, LinkReplay, RecordingPort, ReplayPort};
+use ArmlabRadio::radio_serial::{Radio, prompt_port};
+
+/// get_packet calls made after connecting, the same number is replayed
+const POLLS: usize = 5;
+
+/// what both modes do with the Radio, replay only passes if the driver
+/// makes exactly the same calls it did while recording
+fn session(radio: &mut Radio) {
+    println!("device available: {}", radio.is_device_available());
+
+    for _ in 0..POLLS {
+        match radio.get_packet() {
+            Ok(n) => println!("packet {:?}", n),
+            Err(e) => println!("error {:?}", e),
+        };
+    }
+}
+
+fn record(log: &str) -> ExitCode {
+    let path = prompt_port();
+
+    let port = match serialport::new(&path, 115200).timeout(Duration::from_millis(100)).open() {
+        Ok(n) => n,
+        Err(e) => {
+            println!("Error opening {} | {}", path, e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let recorder = match LinkRecorder::create(log, Link::Serial) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("Error creating {} | {}", log, e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let mut radio = match Radio::from_port(Box::new(RecordingPort::new(port, recorder)), &path) {
+        Ok(n) => n,
+        Err(e) => {
+            // the failed connection is still in the log, which is often the point
+            println!("Error creating Radio {:?}, recorded up to the failure", e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    session(&mut radio);
+    println!("recorded to {}", log);
+
+    return ExitCode::SUCCESS;
+}
+
+fn replay(log: &str) -> ExitCode {
+    let events = match load_log(log) {
+        Ok((Link::Serial, n)) => n,
+        Ok((Link::I2c, _)) => {
+            println!("Error {} was recorded on i2c, replay it with linklog::ReplayI2c", log);
+            return ExitCode::FAILURE;
+        },
+        Err(e) => {
+            println!("Error reading {} | {}", log, e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let replay = LinkReplay::new(events);
+
+    match Radio::from_port(Box::new(ReplayPort::new(replay.clone())), log) {
+        Ok(mut radio) => session(&mut radio),
+        Err(e) => println!("Error creating Radio {:?}", e),
+    };
+
+    match replay.finish() {
+        Ok(_) => {
+            println!("driver matched the log");
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            println!("driver diverged from the log | {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.iter().map(|s| s.as_str()).collect::<Vec<&str>>()[..] {
+        ["record", log] => record(log),
+        ["replay", log] => replay(log),
+        _ => {
+            println!("usage: linklog record|replay LOG");
+            ExitCode::FAILURE
+        },
+    }
+}
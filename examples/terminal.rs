@@ -1,11 +1,103 @@
-use std::{time::Duration, thread};
+use std::{env, fs, process, time::{Duration, Instant}, thread};
+use std::io::{self, BufRead, BufReader, IsTerminal};
 
-#[cfg(feature="i2clib")]
-use ArmlabRadio::radio_i2c::{Radio, ModulationFormat};
+use ArmlabRadio::ax25::Ax25Frame;
+use ArmlabRadio::encoding::{hex_decode, hex_encode, hexdump};
+use ArmlabRadio::protocol::MAX_PACKET_LEN;
 
-#[cfg(not(feature="i2clib"))]
+#[cfg(not(any(feature="i2c", feature="serial")))]
+compile_error!("the terminal example needs the `serial` or `i2c` feature");
+
+#[cfg(feature="i2c")]
+use ArmlabRadio::radio_i2c::{Radio, ResetConfig, ModulationFormat};
+#[cfg(feature="i2c")]
+use ArmlabRadio::linux_hal::{LinuxI2c, LinuxGpioPin, LinuxGpioError, StdDelay};
+#[cfg(feature="i2c")]
+use embedded_hal::digital::{self, OutputPin};
+
+#[cfg(not(feature="i2c"))]
 use ArmlabRadio::radio_serial::{Radio, ModulationFormat, prompt_port};
 
+/// the Radio type commands are run against
+#[cfg(feature="i2c")]
+type Device = Radio<LinuxI2c, StdDelay, ResetGpio>;
+#[cfg(not(feature="i2c"))]
+type Device = Radio;
+
+/// how long `expect` waits for a matching packet when no timeout is given (ms)
+const EXPECT_TIMEOUT: u64 = 1000;
+
+/// time between polls of the Radio while waiting on `expect`
+const EXPECT_POLL: Duration = Duration::from_millis(20);
+
+/// commands whose name is two words, matched before splitting off arguments
+const TWO_WORD_COMMANDS: [&str; 5] = ["symbol rate", "rx filter", "radio reset", "soft reset", "hard reset"];
+
+const USAGE: &str = "usage: terminal [--port PORT] [--reset-gpio CHIP:LINE] [SCRIPT]
+
+runs interactively, or runs the commands in SCRIPT (`-` for stdin) one per line
+commands are also read from stdin when it is piped
+
+--port PORT               serial port of the Radio, or i2c bus with the `i2c` feature
+--reset-gpio CHIP:LINE    gpio wired to the boards reset line for `hard reset`,
+                          e.g. gpiochip0:17, only with the `i2c` feature
+
+exits with 0 once every command succeeded, 1 on the first failing command,
+and 2 if the script could not be read";
+
+/// the gpio given with `--reset-gpio`
+///
+/// the Radio always has a reset pin so its type doesn't depend on the option,
+/// without a gpio the pin does nothing and `hard reset` refuses to run
+#[cfg(feature="i2c")]
+struct ResetGpio(Option<LinuxGpioPin>);
+
+#[cfg(feature="i2c")]
+impl digital::ErrorType for ResetGpio {
+    type Error = LinuxGpioError;
+}
+
+#[cfg(feature="i2c")]
+impl OutputPin for ResetGpio {
+    fn set_low(&mut self) -> Result<(), LinuxGpioError> {
+        match self.0.as_mut() {
+            Some(n) => n.set_low(),
+            None => Ok(()),
+        }
+    }
+
+    fn set_high(&mut self) -> Result<(), LinuxGpioError> {
+        match self.0.as_mut() {
+            Some(n) => n.set_high(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// opens a gpio given as CHIP:LINE, a bare chip name is looked up in /dev
+#[cfg(feature="i2c")]
+fn open_reset_gpio(arg: &str) -> Result<LinuxGpioPin, String> {
+    let (chip, line) = match arg.rsplit_once(':') {
+        Some(n) => n,
+        None => {return Err(format!("expected CHIP:LINE, got {}", arg));},
+    };
+
+    let line: u32 = match line.parse() {
+        Ok(n) => n,
+        Err(_) => {return Err(format!("invalid gpio line {}", line));},
+    };
+
+    let chip = match chip.contains('/') {
+        true => chip.to_string(),
+        false => format!("/dev/{}", chip),
+    };
+
+    return match LinuxGpioPin::new(&chip, line) {
+        Ok(n) => Ok(n),
+        Err(n) => Err(format!("error opening {} line {} | {}", chip, line, n)),
+    };
+}
+
 macro_rules! input {
     {} => {{
         input!("")
@@ -24,221 +116,534 @@ macro_rules! input {
     }};
 }
 
+/// splits a line into words, honouring double quotes
+///
+/// `\"` and `\\` escape inside quotes, an unquoted `#` starts a comment
+fn split_args(line: &str) -> Result<Vec<String>, String> {
+    let mut words: Vec<String> = vec![];
+    let mut chars = line.chars().peekable();
 
-fn main() {
-    let port = prompt_port();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
 
-    #[cfg(feature="i2clib")]
-    let mut radio = Radio::new_rpi().expect("Error Creating Radio");
-    #[cfg(not(feature="i2clib"))]
-    let mut radio = Radio::new(&port).expect("Error Creating Radio");
+        let mut word = String::new();
 
-    loop {
-        match input!("> ").as_str() {
+        match chars.peek() {
+            None | Some('#') => {break;},
+            Some('"') => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('"') => {break;},
+                        Some('\\') => match chars.next() {
+                            Some('n') => word.push('\n'),
+                            Some(c) => word.push(c),
+                            None => {return Err("unterminated escape".to_string());},
+                        },
+                        Some(c) => word.push(c),
+                        None => {return Err("unterminated quote".to_string());},
+                    };
+                }
+            },
+            Some(_) => {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    word.push(c);
+                }
+            },
+        };
+
+        words.push(word);
+    }
+
+    return Ok(words);
+}
+
+/// splits a line into its command and arguments
+///
+/// ## Returns
+/// None for blank and comment lines
+fn parse_line(line: &str) -> Result<Option<(String, Vec<String>)>, String> {
+    let mut words = split_args(line)?;
+
+    if words.is_empty() {
+        return Ok(None);
+    }
+
+    if words.len() >= 2 {
+        let name = format!("{} {}", words[0], words[1]);
+        if TWO_WORD_COMMANDS.contains(&name.as_str()) {
+            words.drain(..2);
+            return Ok(Some((name, words)));
+        }
+    }
+
+    let cmd = words.remove(0);
+    return Ok(Some((cmd, words)));
+}
+
+fn parse_modulation(val: &str) -> Option<ModulationFormat> {
+    match val.to_ascii_uppercase().as_str() {
+        "0" | "FSK2" => Some(ModulationFormat::FSK2),
+        "1" | "GFSK2" => Some(ModulationFormat::GFSK2),
+        "3" | "ASK" => Some(ModulationFormat::ASK),
+        "4" | "FSK4" => Some(ModulationFormat::FSK4),
+        "5" | "GFSK4" => Some(ModulationFormat::GFSK4),
+        _ => None,
+    }
+}
+
+/// how recieved packets are printed, see the `display` command
+#[derive(Clone, Copy, PartialEq)]
+enum Display {
+    /// quoted text, binary packets fall back to a hexdump
+    Text,
+    Hex,
+    /// offsets, hex bytes and an ascii column
+    Dump,
+    /// decoded AX.25 UI frames, other packets fall back to a hexdump
+    Ax25,
+}
+
+fn print_packet(packet: &[u8], display: Display) {
+    if display == Display::Ax25 {
+        if let Ok(frame) = Ax25Frame::decode(packet) {
+            println!("{}", frame);
+            return;
+        }
+    }
+
+    match (display, std::str::from_utf8(packet)) {
+        (Display::Text, Ok(v)) => println!("\"{}\"", v),
+        (Display::Hex, _) => println!("{}", hex_encode(packet)),
+        _ => print!("{}", hexdump(packet)),
+    };
+}
+
+struct Session {
+    /// only None while a soft reset re-opens the serial port
+    radio: Option<Device>,
+    #[cfg(not(feature="i2c"))]
+    port: String,
+    /// wheather `--reset-gpio` was given
+    #[cfg(feature="i2c")]
+    has_reset_gpio: bool,
+    /// prompt for missing arguments instead of failing
+    interactive: bool,
+    display: Display,
+}
+
+impl Session {
+    fn radio(&mut self) -> &mut Device {
+        self.radio.as_mut().expect("radio is re-opened before the next command")
+    }
+
+    /// the argument at `index`, prompted for when running interactively
+    fn arg(&self, args: &[String], index: usize, prompt: &str) -> Result<String, String> {
+        match args.get(index) {
+            Some(n) => Ok(n.clone()),
+            None if self.interactive => Ok(input!(prompt)),
+            None => Err("Missing Parameter".to_string()),
+        }
+    }
+
+    /// the bytes given by a payload argument
+    ///
+    /// `-x <hex>` for hex, `-f <path>` for a files contents, anything else
+    /// is sent as text with unquoted words joined by spaces
+    fn payload(&self, args: &[String]) -> Result<Vec<u8>, String> {
+        match args.first().map(|n| n.as_str()) {
+            Some("-x") => {
+                let hex = match args.len() {
+                    1 => self.arg(&[], 0, "Enter Hex> ")?,
+                    _ => args[1..].concat(),
+                };
+
+                hex_decode(&hex)
+            },
+            Some("-f") => {
+                let path = self.arg(&args[1..], 0, "Enter Path> ")?;
+                fs::read(&path).map_err(|e| format!("Error reading {} | {}", path, e))
+            },
+            Some(_) => Ok(args.join(" ").into_bytes()),
+            None => Ok(self.arg(args, 0, "Enter Message> ")?.into_bytes()),
+        }
+    }
+
+    /// parses the value argument and hands it to `set`
+    fn set_value<E>(&mut self, args: &[String], set: fn(&mut Device, f32) -> Result<(), E>) -> Result<(), String> {
+        let inp = self.arg(args, 0, "Enter Value> ")?;
+        let val: f32 = match inp.parse::<f32>() {
+            Ok(n) => n,
+            Err(_) => {return Err("Invalid Parameter".to_string());},
+        };
+
+        match set(self.radio(), val) {
+            Ok(_) => {println!("Value set | {}", val)},
+            Err(_) => {return Err("Error setting value".to_string());},
+        };
+
+        return Ok(());
+    }
+
+    /// runs a single command
+    fn run(&mut self, cmd: &str, args: &[String]) -> Result<(), String> {
+        match cmd {
             "write" |
             "w" => {
-                let msg = input!("Enter Message> ");
-                match radio.transmit(msg.as_bytes()) {
-                    Ok(_) => {println!("\"{}\"", msg)},
-                    Err(_) => {println!("Error transmitting")},
-                };
-            }
-            "read" |
-            "r" => {
-                match radio.get_packet() {
-                    Ok(n) => {
-                        match std::str::from_utf8(&n) {
-                            Ok(v) => println!("\"{}\"", v),
-                            Err(_) => println!("{:#?}", n),
-                        };
-                    },
-                    Err(n) => {println!("Error getting packet | {:?}", n)},
-                };
-            }
-            
-            "f" |
-            "frequency" => {
-                let inp = input!("Enter Message> ");
-                let val: f32 = match inp.parse::<f32>() {
-                    Ok(n) => n,
-                    Err(_) => {
-                        println!("Invalid Parameter");
-                        continue;
-                    },
-                };
+                let msg = self.payload(args)?;
 
-                match radio.set_frequency(val) {
-					Ok(_) => {println!("Value set | {}", val)},
-					Err(_) => {println!("Error setting value")},
-				}
+                if msg.is_empty() {
+                    return Err("Nothing to send".to_string());
+                }
 
-            },
+                // payloads too long for one packet, e.g. files, are sent in pieces
+                for chunk in msg.chunks(MAX_PACKET_LEN) {
+                    match self.radio().transmit(chunk) {
+                        Ok(_) => {},
+                        Err(_) => {return Err("Error transmitting".to_string());},
+                    };
+                }
 
-            "p" |
-            "power" => {
-                let inp = input!("Enter Value> ");
-                let val: f32 = match inp.parse::<f32>() {
-                    Ok(n) => n,
-                    Err(_) => {
-                        println!("Invalid Parameter");
-                        continue;
+                match (args.first().map(|n| n.as_str()), std::str::from_utf8(&msg)) {
+                    (Some("-x") | Some("-f"), _) | (_, Err(_)) => {
+                        println!("sent {} bytes in {} packet(s)", msg.len(), msg.len().div_ceil(MAX_PACKET_LEN));
+                        if self.display != Display::Text {
+                            print_packet(&msg, self.display);
+                        }
                     },
+                    (_, Ok(v)) => {println!("\"{}\"", v)},
                 };
-                match radio.set_power(val) {
-					Ok(_) => {println!("Value set | {}", val)},
-					Err(_) => {println!("Error setting value")},
-				}
-
             },
-            
-            "d" |
-            "deviation" => {
-                let inp = input!("Enter Value> ");
-                let val: f32 = match inp.parse::<f32>() {
+
+            "ax25" => {
+                let header = self.arg(args, 0, "Enter Path (SRC>DEST[,DIGI...])> ")?;
+                let msg = self.payload(args.get(1..).unwrap_or(&[]))?;
+
+                let frame = match Ax25Frame::with_path(&header, &msg) {
                     Ok(n) => n,
-                    Err(_) => {
-                        println!("Invalid Parameter");
-                        continue;
-                    },
+                    Err(n) => {return Err(format!("Invalid Path | {:?}", n));},
                 };
-                match radio.set_deviation(val) {
-					Ok(_) => {println!("Value set | {}", val)},
-					Err(_) => {println!("Error setting value")},
-				}
 
-            },
-            
-            "sr" |
-            "symbol rate" => {
-                let inp = input!("Enter Value> ");
-                let val: f32 = match inp.parse::<f32>() {
+                let packet = match frame.encode() {
                     Ok(n) => n,
-                    Err(_) => {
-                        println!("Invalid Parameter");
-                        continue;
-                    },
+                    Err(n) => {return Err(format!("Invalid Frame | {:?}", n));},
                 };
-                match radio.set_symbol_rate(val) {
-					Ok(_) => {println!("Value set | {}", val)},
-					Err(_) => {println!("Error setting value")},
-				}
 
-            },
-            
-            "rxf" |
-            "rx filter" => {
-                let inp = input!("Enter Value> ");
-                let val: f32 = match inp.parse::<f32>() {
-                    Ok(n) => n,
-                    Err(_) => {
-                        println!("Invalid Parameter");
-                        continue;
-                    },
+                match self.radio().transmit(&packet) {
+                    Ok(_) => {println!("{}", frame)},
+                    Err(_) => {return Err("Error transmitting".to_string());},
                 };
-                match radio.set_rx_filter(val) {
-					Ok(_) => {println!("Value set | {}", val)},
-					Err(_) => {println!("Error setting value")},
-				}
+            },
 
+            "read" |
+            "r" => {
+                match self.radio().get_packet() {
+                    Ok(n) => print_packet(&n, self.display),
+                    Err(n) => {return Err(format!("Error getting packet | {:?}", n));},
+                };
             },
 
+            "f" |
+            "frequency" => self.set_value(args, Device::set_frequency)?,
+
+            "p" |
+            "power" => self.set_value(args, Device::set_power)?,
+
+            "d" |
+            "deviation" => self.set_value(args, Device::set_deviation)?,
+
+            "sr" |
+            "symbol_rate" |
+            "symbol rate" => self.set_value(args, Device::set_symbol_rate)?,
+
+            "rxf" |
+            "rx_filter" |
+            "rx filter" => self.set_value(args, Device::set_rx_filter)?,
+
             "m" |
             "modulation" => {
-                println!("\tFSK2  (0)");
-                println!("\tGFSK2 (1)");
-                println!("\tASK   (3)");
-                println!("\tFSK4  (4)");
-                println!("\tGFSK4 (5)");
-                let mode = match input!("> ").as_str() {
-                    "0" | "FSK2" => {ModulationFormat::FSK2},
-                    "1" | "GFSK2" => {ModulationFormat::GFSK2},
-                    "3" | "ASK" => {ModulationFormat::ASK},
-                    "4" | "FSK4" => {ModulationFormat::FSK4},
-                    "5" | "GFSK4" => {ModulationFormat::GFSK4},
-                    _ => {
-                        println!("Invalid Argument");
-                        continue;
-                    },
+                if args.is_empty() && self.interactive {
+                    println!("\tFSK2  (0)");
+                    println!("\tGFSK2 (1)");
+                    println!("\tASK   (3)");
+                    println!("\tFSK4  (4)");
+                    println!("\tGFSK4 (5)");
+                }
+
+                let mode = match parse_modulation(&self.arg(args, 0, "> ")?) {
+                    Some(n) => n,
+                    None => {return Err("Invalid Argument".to_string());},
                 };
 
-                match radio.set_modulation(mode) {
-                    Ok(_) => {println!("Value set")},
-                    Err(_) => {println!("Error setting value")},
+                match self.radio().set_modulation(mode) {
+                    Ok(_) => {println!("Value set | {:?}", mode)},
+                    Err(_) => {return Err("Error setting value".to_string());},
                 };
             },
 
             "rr" |
             "radio reset" => {
-                match radio.radio_reset() {
+                match self.radio().radio_reset() {
                     Ok(_) => {println!("sent radio reset command")},
-					Err(_) => {println!("Error sending command")},
-				};
+                    Err(_) => {return Err("Error sending command".to_string());},
+                };
             },
 
             "rs" |
             "soft reset" => {
-                match radio.soft_reset() {
-                    Ok(_) => {
-                        println!("sent soft reset command, re-initializing");
-
-                        // drops old radio, which closes the serialport
-                        // continuously try to re-init port, windows / rust takes a while ~500ms
-                        // to re-detect port
-                        #[cfg(not(feature="i2clib"))]
-                        drop(radio);
-                        
-                        #[cfg(not(feature="i2clib"))]
-                        {
-                        radio = loop {
-                            match Radio::new_bare(&port) {
-                                Ok(n) => {break n;},
-                                Err(_) => {},
-                            };
-
-                            thread::sleep(Duration::from_millis(50));
-                        };
-
-                        println!("reset radio, re-initialized serial coms");
+                match self.radio().soft_reset() {
+                    Ok(_) => {println!("sent soft reset command, re-initializing")},
+                    Err(_) => {return Err("Error sending command".to_string());},
+                };
+
+                // drops old radio, which closes the serialport
+                // continuously try to re-init port, windows / rust takes a while ~500ms
+                // to re-detect port
+                #[cfg(not(feature="i2c"))]
+                {
+                    drop(self.radio.take());
+
+                    self.radio = Some(loop {
+                        if let Ok(n) = Radio::new_bare(&self.port) {
+                            break n;
                         }
-                    },
-					Err(_) => {println!("Error sending command")},
-				};
+
+                        thread::sleep(Duration::from_millis(50));
+                    });
+
+                    println!("reset radio, re-initialized serial coms");
+                }
             },
 
             "rh" |
             "hard reset" => {
-                #[cfg(feature="i2clib")]
-                {
-                    match radio.reset() {
-                        Ok(_) => {println!("triggered hardware reset")},
-                        Err(_) => {println!("Error performing reset")},
+                #[cfg(feature="i2c")]
+                if !self.has_reset_gpio {
+                    return Err("hard reset needs the reset line, start with --reset-gpio CHIP:LINE".to_string());
+                }
+                #[cfg(feature="i2c")]
+                match self.radio().reset() {
+                    Ok(_) => {println!("triggered hardware reset")},
+                    Err(_) => {return Err("Error performing reset".to_string());},
+                };
+                #[cfg(not(feature="i2c"))]
+                return Err("hard reset is not supported with serial".to_string());
+            },
+
+            "sleep" => {
+                let ms: u64 = match self.arg(args, 0, "Enter Time (ms)> ")?.parse::<u64>() {
+                    Ok(n) => n,
+                    Err(_) => {return Err("Invalid Parameter".to_string());},
+                };
+
+                thread::sleep(Duration::from_millis(ms));
+            },
+
+            "expect" => {
+                // `-x <hex>` matches binary packets
+                let (pattern, args) = match args.first().map(|n| n.as_str()) {
+                    Some("-x") => (hex_decode(&self.arg(&args[1..], 0, "Enter Hex> ")?)?, args.get(2..).unwrap_or(&[])),
+                    _ => (self.arg(args, 0, "Enter Message> ")?.into_bytes(), args.get(1..).unwrap_or(&[])),
+                };
+
+                let timeout: u64 = match args.first().map(|n| n.parse::<u64>()) {
+                    Some(Ok(n)) => n,
+                    Some(Err(_)) => {return Err("Invalid Parameter".to_string());},
+                    None => EXPECT_TIMEOUT,
+                };
+
+                let deadline = Instant::now() + Duration::from_millis(timeout);
+
+                loop {
+                    let packet = match self.radio().get_packet() {
+                        Ok(n) => n,
+                        Err(n) => {return Err(format!("Error getting packet | {:?}", n));},
                     };
+
+                    if !packet.is_empty() {
+                        print_packet(&packet, self.display);
+
+                        if pattern.is_empty() || packet.windows(pattern.len()).any(|w| w == pattern.as_slice()) {
+                            break;
+                        }
+                    }
+
+                    if Instant::now() >= deadline {
+                        return Err(format!("timed out after {} ms waiting for \"{}\"", timeout, String::from_utf8_lossy(&pattern)));
+                    }
+
+                    thread::sleep(EXPECT_POLL);
                 }
-                #[cfg(not(feature="i2clib"))]
-                println!("hard reset is not supported with serial")
-            }
-            
+            },
+
+            "display" => {
+                self.display = match self.arg(args, 0, "Enter Mode (text, hex, dump, ax25)> ")?.as_str() {
+                    "text" => Display::Text,
+                    "hex" => Display::Hex,
+                    "dump" => Display::Dump,
+                    "ax25" => Display::Ax25,
+                    _ => {return Err("Invalid Argument".to_string());},
+                };
+            },
+
             "h" |
             "help" => {
-                println!("write (w)\n\ttransmits a message");
+                println!("write (w) <message> | -x <hex> | -f <path>\n\ttransmits a message, hex bytes or a files contents");
+                println!("ax25 <SRC>DEST[,DIGI...]> <message> | -x <hex> | -f <path>\n\ttransmits an AX.25 UI frame, e.g. ax25 N0CALL>APRS,WIDE1-1 hello");
                 println!("read (r)\n\treads a message from radio \"\" if there is none");
-                
-                println!("frequency (f)\n\tsets the radios operating frequency");
-                println!("power (p)\n\tsets the radios TX power");
-                println!("deviation (d)\n\tsets the radios FSK deviation");
-                println!("symbol rate (sr)\n\tsets the radios symbol rate for TX and RX");
-                println!("rx filter (rxf)\n\tsets the RX bandwith filter");
-                println!("modulation (m)\n\tsets the radios modulation format");
+
+                println!("frequency (f) <Hz>\n\tsets the radios operating frequency");
+                println!("power (p) <dBm>\n\tsets the radios TX power");
+                println!("deviation (d) <Hz>\n\tsets the radios FSK deviation");
+                println!("symbol rate (sr) <symbols/s>\n\tsets the radios symbol rate for TX and RX");
+                println!("rx filter (rxf) <Hz>\n\tsets the RX bandwith filter");
+                println!("modulation (m) <FSK2|GFSK2|ASK|FSK4|GFSK4>\n\tsets the radios modulation format");
 
                 println!("radio reset (rr)\n\tattempts to reset the onboard CC1200 radio");
                 println!("soft reset (rs)\n\tattempts a software reset of the entire board");
                 println!("hard reset (rh)\n\tperforms a hard reset on the entire radio");
 
+                println!("sleep <ms>\n\twaits before running the next command");
+                println!("display <text|hex|dump|ax25>\n\tsets how recieved packets are shown, dump shows offsets and an ascii column, ax25 decodes UI frames");
+                println!("expect <message> | -x <hex> [timeout ms]\n\twaits for a packet containing message, fails after the timeout (default {} ms)", EXPECT_TIMEOUT);
+
                 println!("help (h)\n\tshows available commands");
-            }
-            
-            _ => {println!("Invalid Command | \"help\" to see available commands")}
+            },
+
+            _ => {return Err("Invalid Command | \"help\" to see available commands".to_string());},
+        };
+
+        return Ok(());
+    }
+}
+
+/// runs every command in `reader`, stopping at the first failure
+///
+/// ## Returns
+/// the process exit code
+fn run_script<R: BufRead>(session: &mut Session, reader: R) -> i32 {
+    for (i, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(n) => n,
+            Err(n) => {
+                eprintln!("error reading script | {}", n);
+                return 2;
+            },
+        };
+
+        let (cmd, args) = match parse_line(&line) {
+            Ok(Some(n)) => n,
+            Ok(None) => {continue;},
+            Err(n) => {
+                eprintln!("line {}: {}", i + 1, n);
+                return 1;
+            },
         };
-        println!();
+
+        println!("> {}", line.trim());
+
+        if let Err(n) = session.run(&cmd, &args) {
+            eprintln!("line {}: {}", i + 1, n);
+            return 1;
+        }
     }
-}
\ No newline at end of file
+
+    return 0;
+}
+
+fn main() {
+    let mut port: Option<String> = None;
+    let mut reset_gpio: Option<String> = None;
+    let mut script: Option<String> = None;
+
+    let mut argv = env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--port" => {port = argv.next();},
+            "--reset-gpio" => {reset_gpio = argv.next();},
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ if script.is_none() => {script = Some(arg);},
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            },
+        };
+    }
+
+    // a piped stdin is treated as a script
+    if script.is_none() && !io::stdin().is_terminal() {
+        script = Some("-".to_string());
+    }
+
+    #[cfg(feature="i2c")]
+    let pin = match reset_gpio.as_deref().map(open_reset_gpio) {
+        Some(Ok(n)) => Some(n),
+        Some(Err(n)) => {
+            eprintln!("{}", n);
+            process::exit(2);
+        },
+        None => None,
+    };
+    #[cfg(feature="i2c")]
+    let has_reset_gpio = pin.is_some();
+    #[cfg(feature="i2c")]
+    let mut radio = match Radio::new(port.as_deref().unwrap_or("/dev/i2c-1")).expect("Error Creating Radio")
+        .with_reset_pin(ResetGpio(pin), ResetConfig::default()) {
+        Ok(n) => n,
+        Err((_, n)) => panic!("Error driving the reset gpio: {:?}", n),
+    };
+    #[cfg(feature="i2c")]
+    match radio.calibrate(8) {
+        Ok(n) => {println!("calibrated i2c timing | latency {}-{} us, using {} us", n.min_latency, n.max_latency, n.max_latency)},
+        Err(n) => {println!("Error calibrating i2c timing, using defaults | {:?}", n)},
+    };
+    #[cfg(not(feature="i2c"))]
+    if reset_gpio.is_some() {
+        eprintln!("--reset-gpio needs the `i2c` feature");
+        process::exit(2);
+    }
+    #[cfg(not(feature="i2c"))]
+    let port = port.unwrap_or_else(prompt_port);
+    #[cfg(not(feature="i2c"))]
+    let radio = Radio::new(&port).expect("Error Creating Radio");
+
+    let mut session = Session {
+        radio: Some(radio),
+        #[cfg(not(feature="i2c"))]
+        port,
+        #[cfg(feature="i2c")]
+        has_reset_gpio,
+        interactive: script.is_none(),
+        display: Display::Text,
+    };
+
+    let code = match script.as_deref() {
+        Some("-") => run_script(&mut session, io::stdin().lock()),
+        Some(path) => match fs::File::open(path) {
+            Ok(n) => run_script(&mut session, BufReader::new(n)),
+            Err(n) => {
+                eprintln!("error opening {} | {}", path, n);
+                2
+            },
+        },
+        None => loop {
+            let line = input!("> ");
+
+            match parse_line(&line) {
+                Ok(Some((cmd, args))) => {
+                    if let Err(n) = session.run(&cmd, &args) {
+                        println!("{}", n);
+                    }
+                },
+                Ok(None) => {},
+                Err(n) => {println!("{}", n)},
+            };
+            println!();
+        },
+    };
+
+    process::exit(code);
+}
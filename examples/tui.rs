@@ -0,0 +1,435 @@
+use std::{io, sync::mpsc, thread, time::{Duration, Instant}};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::DefaultTerminal;
+
+#[cfg(not(any(feature="i2c", feature="serial")))]
+compile_error!("the tui example needs the `serial` or `i2c` feature");
+
+#[cfg(feature="i2c")]
+use ArmlabRadio::radio_i2c::Radio;
+
+#[cfg(not(feature="i2c"))]
+use ArmlabRadio::radio_serial::{Radio, prompt_port};
+
+use ArmlabRadio::protocol::{ModulationFormat, MAX_PACKET_LEN};
+use ArmlabRadio::shared::SharedRadio;
+use ArmlabRadio::transceiver::Transceiver;
+
+/// time between polls of the Radio for new packets
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// oldest log lines are dropped past this many
+const LOG_LEN: usize = 1000;
+
+const SETTINGS: [(&str, &str); 6] = [
+    ("frequency", "Hz"),
+    ("power", "dBm"),
+    ("deviation", "Hz"),
+    ("symbol rate", "sym/s"),
+    ("rx filter", "Hz"),
+    ("modulation", ""),
+];
+
+#[derive(PartialEq)]
+enum Focus {
+    Transmit,
+    Settings,
+}
+
+enum Dir {
+    Rx,
+    Tx,
+    Error,
+}
+
+struct LogLine {
+    time: Duration,
+    dir: Dir,
+    text: String,
+}
+
+#[derive(Default)]
+struct Counters {
+    rx_packets: u64,
+    rx_bytes: u64,
+    tx_packets: u64,
+    tx_bytes: u64,
+    errors: u64,
+}
+
+struct App {
+    start: Instant,
+    log: Vec<LogLine>,
+    /// lines scrolled up from the newest entry
+    scroll: usize,
+    input: String,
+    focus: Focus,
+    selected: usize,
+    /// the value being typed while a setting is edited
+    editing: Option<String>,
+    /// values applied this session, the Radio can't report them
+    settings: [Option<String>; 6],
+    counters: Counters,
+    status: String,
+    /// the failing poll and how many times in a row it failed, shown on the
+    /// status line instead of filling the log
+    poll_error: Option<(String, u64)>,
+}
+
+impl App {
+    fn new() -> App {
+        App {
+            start: Instant::now(),
+            log: vec![],
+            scroll: 0,
+            input: String::new(),
+            focus: Focus::Transmit,
+            selected: 0,
+            editing: None,
+            settings: Default::default(),
+            counters: Counters::default(),
+            status: String::new(),
+            poll_error: None,
+        }
+    }
+
+    fn push(&mut self, dir: Dir, text: String) {
+        if self.log.len() == LOG_LEN {
+            self.log.remove(0);
+        }
+
+        // hold the view still while the user is scrolled back
+        if self.scroll > 0 {
+            self.scroll += 1;
+        }
+
+        self.log.push(LogLine { time: self.start.elapsed(), dir, text });
+    }
+
+    fn error(&mut self, text: String) {
+        self.counters.errors += 1;
+        self.status = text.clone();
+        self.push(Dir::Error, text);
+    }
+
+    /// counts a failed poll, repeats of the same error only update the status line
+    fn poll_failed(&mut self, text: String) {
+        self.counters.errors += 1;
+
+        let count = match self.poll_error.take() {
+            Some((prev, n)) if prev == text => n + 1,
+            _ => 1,
+        };
+
+        self.status = match count {
+            1 => format!("error getting packet | {}", text),
+            n => format!("error getting packet | {} (x{})", text, n),
+        };
+        self.poll_error = Some((text, count));
+    }
+
+    /// polling works again, clears the error from the status line
+    fn poll_recovered(&mut self) {
+        if self.poll_error.take().is_some() {
+            self.status.clear();
+        }
+    }
+}
+
+/// printable text when the packet is utf-8, hex otherwise
+fn display(data: &[u8]) -> String {
+    match std::str::from_utf8(data) {
+        Ok(n) if !n.chars().any(|c| c.is_control()) => format!("\"{}\"", n),
+        _ => data.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(" "),
+    }
+}
+
+fn parse_modulation(val: &str) -> Option<ModulationFormat> {
+    match val.to_ascii_uppercase().as_str() {
+        "0" | "FSK2" | "2FSK" => Some(ModulationFormat::FSK2),
+        "1" | "GFSK2" | "2GFSK" => Some(ModulationFormat::GFSK2),
+        "3" | "ASK" | "OOK" => Some(ModulationFormat::ASK),
+        "4" | "FSK4" | "4FSK" => Some(ModulationFormat::FSK4),
+        "5" | "GFSK4" | "4GFSK" => Some(ModulationFormat::GFSK4),
+        _ => None,
+    }
+}
+
+/// applies setting `index` from `SETTINGS` to the Radio
+///
+/// ## Returns
+/// the value as it should be displayed
+fn apply<T: Transceiver>(radio: &mut T, index: usize, val: &str) -> Result<String, String> {
+    if index == 5 {
+        let mode = match parse_modulation(val) {
+            Some(n) => n,
+            None => {return Err(format!("unknown modulation \"{}\", use FSK2, GFSK2, ASK, FSK4 or GFSK4", val));},
+        };
+
+        radio.set_modulation(mode).map_err(|e| format!("{:?}", e))?;
+        return Ok(format!("{:?}", mode));
+    }
+
+    let num: f32 = match val.parse::<f32>() {
+        Ok(n) => n,
+        Err(_) => {return Err(format!("invalid number \"{}\"", val));},
+    };
+
+    let res = match index {
+        0 => radio.set_frequency(num),
+        1 => radio.set_power(num),
+        2 => radio.set_deviation(num),
+        3 => radio.set_symbol_rate(num),
+        _ => radio.set_rx_filter(num),
+    };
+
+    res.map_err(|e| format!("{:?}", e))?;
+    return Ok(num.to_string());
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let [main, input, status] = Layout::vertical([
+        Constraint::Min(5), Constraint::Length(3), Constraint::Length(1),
+    ]).areas(f.area());
+    let [log_area, side] = Layout::horizontal([Constraint::Min(30), Constraint::Length(36)]).areas(main);
+    let [settings_area, counters_area] = Layout::vertical([Constraint::Length(8), Constraint::Min(8)]).areas(side);
+
+    // receive log, following the newest line unless scrolled back
+    let height = log_area.height.saturating_sub(2) as usize;
+    let end = app.log.len().saturating_sub(app.scroll);
+    let lines: Vec<Line> = app.log[end.saturating_sub(height)..end].iter().map(|l| {
+        let (tag, style) = match l.dir {
+            Dir::Rx => ("rx", Style::new().fg(Color::Green)),
+            Dir::Tx => ("tx", Style::new().fg(Color::Cyan)),
+            Dir::Error => ("!!", Style::new().fg(Color::Red)),
+        };
+
+        Line::from(vec![
+            Span::styled(format!("{:>8.3} ", l.time.as_secs_f32()), Style::new().fg(Color::DarkGray)),
+            Span::styled(format!("{} ", tag), style),
+            Span::raw(l.text.clone()),
+        ])
+    }).collect();
+
+    let title = match app.scroll {
+        0 => " log ".to_string(),
+        n => format!(" log (-{}) ", n),
+    };
+    f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)), log_area);
+
+    // settings panel
+    let items: Vec<ListItem> = SETTINGS.iter().enumerate().map(|(i, (name, unit))| {
+        let val = match (&app.editing, i == app.selected) {
+            (Some(buf), true) => format!("{}_", buf),
+            _ => match &app.settings[i] {
+                Some(n) => format!("{} {}", n, unit),
+                None => "-".to_string(),
+            },
+        };
+
+        ListItem::new(format!("{:<12}{}", name, val))
+    }).collect();
+
+    let mut state = ListState::default();
+    if app.focus == Focus::Settings {
+        state.select(Some(app.selected));
+    }
+
+    let settings = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(" settings "))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(settings, settings_area, &mut state);
+
+    // counters
+    let c = &app.counters;
+    let counters = Paragraph::new(vec![
+        Line::from(format!("rx packets  {}", c.rx_packets)),
+        Line::from(format!("rx bytes    {}", c.rx_bytes)),
+        Line::from(format!("tx packets  {}", c.tx_packets)),
+        Line::from(format!("tx bytes    {}", c.tx_bytes)),
+        Line::from(format!("errors      {}", c.errors)),
+    ]).block(Block::default().borders(Borders::ALL).title(" counters "));
+    f.render_widget(counters, counters_area);
+
+    // transmit line
+    let input_block = Block::default().borders(Borders::ALL).title(" transmit ");
+    f.render_widget(Paragraph::new(app.input.as_str()).block(input_block), input);
+    if app.focus == Focus::Transmit {
+        f.set_cursor_position((input.x + 1 + app.input.chars().count() as u16, input.y + 1));
+    }
+
+    let help = "tab: switch pane | enter: send / edit | pgup/pgdn: scroll | esc: quit";
+    let text = match app.status.is_empty() {
+        true => help.to_string(),
+        false => format!("{} | {}", app.status, help),
+    };
+    f.render_widget(Paragraph::new(text).style(Style::new().fg(Color::DarkGray)), status);
+}
+
+/// handles a key press
+///
+/// ## Returns
+/// false once the user asked to quit
+fn handle_key<T: Transceiver>(app: &mut App, radio: &mut T, key: KeyEvent) -> bool {
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return false;
+    }
+
+    match key.code {
+        KeyCode::Esc if app.editing.is_some() => {app.editing = None;},
+        KeyCode::Esc => {return false;},
+        KeyCode::Tab | KeyCode::BackTab if app.editing.is_none() => {
+            app.focus = match app.focus {
+                Focus::Transmit => Focus::Settings,
+                Focus::Settings => Focus::Transmit,
+            };
+        },
+        KeyCode::PageUp => {app.scroll = (app.scroll + 10).min(app.log.len());},
+        KeyCode::PageDown => {app.scroll = app.scroll.saturating_sub(10);},
+        _ => match app.focus {
+            Focus::Transmit => handle_transmit_key(app, radio, key.code),
+            Focus::Settings => handle_settings_key(app, radio, key.code),
+        },
+    };
+
+    return true;
+}
+
+fn handle_transmit_key<T: Transceiver>(app: &mut App, radio: &mut T, code: KeyCode) {
+    match code {
+        KeyCode::Char(c) => {app.input.push(c);},
+        KeyCode::Backspace => {app.input.pop();},
+        KeyCode::Enter if !app.input.is_empty() => {
+            let msg = std::mem::take(&mut app.input);
+
+            if msg.len() > MAX_PACKET_LEN {
+                app.error(format!("message is {} bytes, the limit is {}", msg.len(), MAX_PACKET_LEN));
+                app.input = msg;
+                return;
+            }
+
+            match radio.transmit(msg.as_bytes()) {
+                Ok(_) => {
+                    app.counters.tx_packets += 1;
+                    app.counters.tx_bytes += msg.len() as u64;
+                    app.status.clear();
+                    app.push(Dir::Tx, display(msg.as_bytes()));
+                },
+                Err(n) => {
+                    app.error(format!("error transmitting | {:?}", n));
+                    app.input = msg;
+                },
+            };
+        },
+        _ => {},
+    };
+}
+
+fn handle_settings_key<T: Transceiver>(app: &mut App, radio: &mut T, code: KeyCode) {
+    let buf = match &mut app.editing {
+        Some(n) => n,
+        None => {
+            match code {
+                KeyCode::Up => {app.selected = app.selected.saturating_sub(1);},
+                KeyCode::Down => {app.selected = (app.selected + 1).min(SETTINGS.len() - 1);},
+                KeyCode::Enter => {app.editing = Some(app.settings[app.selected].clone().unwrap_or_default());},
+                _ => {},
+            };
+            return;
+        },
+    };
+
+    match code {
+        KeyCode::Char(c) => {buf.push(c);},
+        KeyCode::Backspace => {buf.pop();},
+        KeyCode::Enter => {
+            let val = buf.trim().to_string();
+
+            match apply(radio, app.selected, &val) {
+                Ok(n) => {
+                    app.status = format!("{} set", SETTINGS[app.selected].0);
+                    app.settings[app.selected] = Some(n);
+                    app.editing = None;
+                },
+                // keep editing so the value can be corrected
+                Err(n) => {app.error(format!("error setting {} | {}", SETTINGS[app.selected].0, n));},
+            };
+        },
+        _ => {},
+    };
+}
+
+fn run<R: Transceiver + Send + 'static>(terminal: &mut DefaultTerminal, mut radio: SharedRadio<R>) -> io::Result<()> {
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, String>>();
+
+    // poll on a separate thread so the ui never waits on the Radio,
+    // the shared handle keeps it from interleaving with ui commands
+    let mut poller = radio.clone();
+    thread::spawn(move || {
+        let mut failing = false;
+
+        loop {
+            let res = poller.get_packet().map_err(|e| format!("{:?}", e));
+
+            // empty polls are only sent to report that a failing Radio answers again
+            let send = match &res {
+                Ok(n) => !n.is_empty() || failing,
+                Err(_) => true,
+            };
+            failing = res.is_err();
+
+            if send && tx.send(res).is_err() {
+                return;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+
+    let mut app = App::new();
+
+    loop {
+        for res in rx.try_iter() {
+            match res {
+                Ok(packet) if packet.is_empty() => {app.poll_recovered();},
+                Ok(packet) => {
+                    app.poll_recovered();
+                    app.counters.rx_packets += 1;
+                    app.counters.rx_bytes += packet.len() as u64;
+                    app.push(Dir::Rx, format!("[{}] {}", packet.len(), display(&packet)));
+                },
+                Err(n) => {app.poll_failed(n);},
+            };
+        }
+
+        terminal.draw(|f| draw(f, &app))?;
+
+        if !event::poll(POLL_INTERVAL)? {
+            continue;
+        }
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !handle_key(&mut app, &mut radio, key) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    #[cfg(feature="i2c")]
+    let radio = Radio::new_rpi().expect("Error Creating Radio");
+    #[cfg(not(feature="i2c"))]
+    let port = prompt_port();
+    #[cfg(not(feature="i2c"))]
+    let radio = Radio::new(&port).expect("Error Creating Radio");
+
+    let mut terminal = ratatui::init();
+    let res = run(&mut terminal, SharedRadio::new(radio));
+    ratatui::restore();
+
+    return res;
+}
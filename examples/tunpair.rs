@@ -0,0 +1,84 @@
+use std::{env, process::{Command, ExitCode}, thread};
+use std::net::Ipv4Addr;
+
+use ArmlabRadio::mock::MockRadio;
+use ArmlabRadio::shared::SharedRadio;
+use ArmlabRadio::tun::{TunBridge, TunDevice, DEFAULT_MTU};
+
+const USAGE: &str = "usage: tunpair [NETNS]
+
+bridges two TUN interfaces through a pair of emulated Radios, so IP over
+the Radio can be tried without hardware (needs root)
+
+arm0 stays in this namespace as 10.9.0.1, arm1 is moved into the network
+namespace NETNS (default `armlab`, created if missing) as 10.9.0.2
+
+    ping 10.9.0.2
+    ip netns exec armlab iperf3 -s    then    iperf3 -c 10.9.0.2";
+
+const LOCAL: Ipv4Addr = Ipv4Addr::new(10, 9, 0, 1);
+const REMOTE: &str = "10.9.0.2/24";
+
+/// runs `ip` with `args`
+fn ip(args: &[&str]) -> Result<(), String> {
+    match Command::new("ip").args(args).status() {
+        Ok(n) if n.success() => Ok(()),
+        Ok(n) => Err(format!("ip {} failed | {}", args.join(" "), n)),
+        Err(e) => Err(format!("Error running ip | {}", e)),
+    }
+}
+
+fn setup(netns: &str) -> Result<(TunDevice, TunDevice), String> {
+    let local = TunDevice::create("arm0").map_err(|e| format!("Error creating arm0 | {}", e))?;
+    let remote = TunDevice::create("arm1").map_err(|e| format!("Error creating arm1 | {}", e))?;
+
+    for dev in [&local, &remote] {
+        dev.set_mtu(DEFAULT_MTU).map_err(|e| format!("Error setting MTU | {}", e))?;
+    }
+
+    local.set_address(LOCAL, 24).map_err(|e| format!("Error setting address | {}", e))?;
+    local.up().map_err(|e| format!("Error bringing up arm0 | {}", e))?;
+
+    // the namespace may be left over from an earlier run
+    let _ = Command::new("ip").args(["netns", "add", netns]).output();
+
+    ip(&["link", "set", remote.name(), "netns", netns])?;
+    ip(&["-n", netns, "addr", "add", REMOTE, "dev", remote.name()])?;
+    ip(&["-n", netns, "link", "set", remote.name(), "up"])?;
+
+    return Ok((local, remote));
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.iter().any(|n| n == "-h" || n == "--help") || args.len() > 1 {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let netns = args.first().map(|n| n.as_str()).unwrap_or("armlab");
+
+    let (local, remote) = match setup(netns) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{}", e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let (a, b) = MockRadio::pair();
+    let local_bridge = TunBridge::new(SharedRadio::new(a));
+    let remote_bridge = TunBridge::new(SharedRadio::new(b));
+
+    println!("arm0 {} <-> arm1 {} in netns {}, ctrl-c to stop", LOCAL, REMOTE, netns);
+
+    thread::spawn(move || remote_bridge.run(remote));
+
+    if let Err(e) = local_bridge.run(local) {
+        println!("Error reading arm0 | {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    return ExitCode::SUCCESS;
+}
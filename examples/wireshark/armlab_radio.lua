@@ -0,0 +1,46 @@
+-- dissector for captures written by ArmlabRadio::capture
+--
+-- copy into your wireshark plugins folder (Help > About > Folders), it
+-- registers itself for DLT 147 (USER0) which the capture files use
+
+local armlab = Proto("armlab", "ArmLab CC1200 Radio")
+
+local directions = { [0] = "rx", [1] = "tx" }
+local modulations = { [0] = "FSK2", [1] = "GFSK2", [3] = "ASK", [4] = "FSK4", [5] = "GFSK4" }
+
+local f = armlab.fields
+f.version = ProtoField.uint8("armlab.version", "Version")
+f.direction = ProtoField.uint8("armlab.direction", "Direction", base.DEC, directions)
+f.flags = ProtoField.uint8("armlab.flags", "Flags", base.HEX)
+f.modulation = ProtoField.uint8("armlab.modulation", "Modulation", base.DEC, modulations)
+f.frequency = ProtoField.float("armlab.frequency", "Frequency (Hz)")
+f.rssi = ProtoField.int8("armlab.rssi", "RSSI (dBm)")
+f.lqi = ProtoField.uint8("armlab.lqi", "LQI")
+f.payload = ProtoField.bytes("armlab.payload", "Payload")
+
+function armlab.dissector(buf, pinfo, tree)
+    if buf:len() < 12 then return 0 end
+
+    pinfo.cols.protocol = "ARMLAB"
+
+    local flags = buf(2, 1):uint()
+    local t = tree:add(armlab, buf(0, 12), "ArmLab CC1200 Radio")
+
+    t:add(f.version, buf(0, 1))
+    t:add(f.direction, buf(1, 1))
+    t:add(f.flags, buf(2, 1))
+    if bit.band(flags, 0x02) ~= 0 then t:add(f.modulation, buf(3, 1)) end
+    if bit.band(flags, 0x01) ~= 0 then t:add_le(f.frequency, buf(4, 4)) end
+    if bit.band(flags, 0x04) ~= 0 then t:add(f.rssi, buf(8, 1)) end
+    if bit.band(flags, 0x08) ~= 0 then t:add(f.lqi, buf(9, 1)) end
+
+    local len = buf:len() - 12
+    if len > 0 then
+        tree:add(f.payload, buf(12, len))
+    end
+
+    pinfo.cols.info = string.format("%s, %d bytes", directions[buf(1, 1):uint()] or "?", len)
+    return buf:len()
+end
+
+DissectorTable.get("wtap_encap"):add(wtap.USER0, armlab)
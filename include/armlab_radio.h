@@ -0,0 +1,214 @@
+#ifndef ARMLAB_RADIO_H
+#define ARMLAB_RADIO_H
+
+/* generated by cbindgen from src/ffi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// size of the path buffer in `ArmlabDevice`, including the terminating NUL
+#define ARMLAB_PATH_LEN 256
+
+// result of every `armlab_radio_*` call, `ARMLAB_OK` on success
+//
+// the remaining codes map the `RadioError` variants of both backends
+typedef enum ArmlabStatus {
+  ARMLAB_OK = 0,
+  // a required pointer argument was NULL
+  ARMLAB_NULL_POINTER = -1,
+  ARMLAB_INVALID_ARGUMENT = -2,
+  // the port or bus could not be opened
+  ARMLAB_OPEN_ERROR = -3,
+  // no Radio answered the ident request
+  ARMLAB_DEVICE_DETECT_ERROR = -4,
+  ARMLAB_WRITE_ERROR = -5,
+  ARMLAB_READ_ERROR = -6,
+  ARMLAB_SYNC_TIMEOUT_ERROR = -7,
+  // the system could not be scanned for ports / buses
+  ARMLAB_PORT_DETECT_ERROR = -8,
+  ARMLAB_NO_RESET_PIN = -9,
+  ARMLAB_RESET_PIN_ERROR = -10,
+  // the output buffer is too small, see the functions length / count output
+  ARMLAB_BUFFER_TOO_SMALL = -11,
+  // the operation is not available for this backend or build
+  ARMLAB_UNSUPPORTED = -12,
+} ArmlabStatus;
+
+// opaque handle to an open Radio
+//
+// created by `armlab_radio_open_serial` / `armlab_radio_open_i2c`,
+// released with `armlab_radio_close`
+typedef struct ArmlabRadio ArmlabRadio;
+
+// a Radio found by `armlab_radio_discover_serial` / `armlab_radio_discover_i2c`
+typedef struct ArmlabDevice {
+  // NUL terminated port name or i2c bus path
+  char path[ARMLAB_PATH_LEN];
+  // i2c address of the Radio, 0 for serial ports
+  uint8_t address;
+} ArmlabDevice;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// opens the Radio on the given serial port, see `radio_serial::Radio::new`
+//
+// on success `*out` holds a handle to be released with `armlab_radio_close`
+//
+// # Safety
+// `port` must be a NUL terminated string, `out` a valid pointer
+enum ArmlabStatus armlab_radio_open_serial(const char *port, struct ArmlabRadio **out);
+
+// opens the Radio at `address` on the given i2c bus (e.g. "/dev/i2c-1"),
+// see `radio_i2c::Radio::new_at`
+//
+// `address` is one reported by `armlab_radio_discover_i2c`, or 0 to try the
+// primary address then the backup address like `radio_i2c::Radio::new`
+//
+// on success `*out` holds a handle to be released with `armlab_radio_close`
+//
+// `ARMLAB_UNSUPPORTED` if the library was built without i2c support
+//
+// # Safety
+// `bus` must be a NUL terminated string, `out` a valid pointer
+enum ArmlabStatus armlab_radio_open_i2c(const char *bus, uint8_t address, struct ArmlabRadio **out);
+
+// closes the Radio and frees the handle, NULL is ignored
+//
+// # Safety
+// `radio` must come from an `armlab_radio_open_*` call and not be used afterwards
+void armlab_radio_close(struct ArmlabRadio *radio);
+
+// transmits `len` bytes from `msg`, at most 255
+//
+// # Safety
+// `radio` must be an open handle, `msg` must point to `len` readable bytes
+enum ArmlabStatus armlab_radio_transmit(struct ArmlabRadio *radio,
+                                        const uint8_t *msg,
+                                        uintptr_t len);
+
+// gets the most recent packet stored on the Radio
+//
+// copies the packet into `buf` and its length into `*len`, 0 if no packet
+// was available, a 255 byte buffer always fits
+//
+// `ARMLAB_BUFFER_TOO_SMALL` if the packet is longer than `cap`, `*len` still
+// holds the packet length, the packet itself is lost
+//
+// # Safety
+// `radio` must be an open handle, `buf` must point to `cap` writable bytes
+enum ArmlabStatus armlab_radio_get_packet(struct ArmlabRadio *radio,
+                                          uint8_t *buf,
+                                          uintptr_t cap,
+                                          uintptr_t *len);
+
+// attempts to set the frequency of the radio
+//
+// # Safety
+// `radio` must be an open handle
+enum ArmlabStatus armlab_radio_set_frequency(struct ArmlabRadio *radio, float frequency);
+
+// attempts to set the tx gain of the radio
+//
+// # Safety
+// `radio` must be an open handle
+enum ArmlabStatus armlab_radio_set_power(struct ArmlabRadio *radio, float power);
+
+// attempts to set the FSK bandwith of the radio
+//
+// # Safety
+// `radio` must be an open handle
+enum ArmlabStatus armlab_radio_set_deviation(struct ArmlabRadio *radio, float deviation);
+
+// attempts to set the symbol rate of the radio
+//
+// # Safety
+// `radio` must be an open handle
+enum ArmlabStatus armlab_radio_set_symbol_rate(struct ArmlabRadio *radio, float symbol_rate);
+
+// attempts to set the recieve filter of the radio
+//
+// # Safety
+// `radio` must be an open handle
+enum ArmlabStatus armlab_radio_set_rx_filter(struct ArmlabRadio *radio, float rx_filter);
+
+// sets the modulation mode of the radio
+//
+// `mode` is the firmware value, FSK2 (0), GFSK2 (1), ASK (3), FSK4 (4), GFSK4 (5)
+//
+// # Safety
+// `radio` must be an open handle
+enum ArmlabStatus armlab_radio_set_modulation(struct ArmlabRadio *radio, uint8_t mode);
+
+// sends a reset command to reset the onboard Radio chip
+//
+// # Safety
+// `radio` must be an open handle
+enum ArmlabStatus armlab_radio_radio_reset(struct ArmlabRadio *radio);
+
+// sends a reset command to perform a soft reset on the entire board
+//
+// serial handles must be re-opened afterwards, the port re-enumerates
+//
+// # Safety
+// `radio` must be an open handle
+enum ArmlabStatus armlab_radio_soft_reset(struct ArmlabRadio *radio);
+
+// attaches the gpio wired to the boards reset line to an i2c handle,
+// see `radio_i2c::Radio::with_reset_pin`
+//
+// `chip` is the gpio character device (e.g. "/dev/gpiochip0"), `line` the
+// active low line offset on it, the line is pulsed for `pulse_width` ms,
+// then the board is given `post_reset_wait` ms to boot
+//
+// `ARMLAB_UNSUPPORTED` for serial handles, `ARMLAB_RESET_PIN_ERROR` if the
+// line can't be requested or driven, the handle is left as it was
+//
+// # Safety
+// `radio` must be an open handle, `chip` a NUL terminated string
+enum ArmlabStatus armlab_radio_attach_reset_gpio(struct ArmlabRadio *radio,
+                                                 const char *chip,
+                                                 uint32_t line,
+                                                 uint32_t pulse_width,
+                                                 uint32_t post_reset_wait);
+
+// performs a hard reset using the gpio given to `armlab_radio_attach_reset_gpio`
+//
+// `ARMLAB_NO_RESET_PIN` if none was attached, `ARMLAB_UNSUPPORTED` for serial handles
+//
+// # Safety
+// `radio` must be an open handle
+enum ArmlabStatus armlab_radio_reset(struct ArmlabRadio *radio);
+
+// finds every serial port with a Radio attached, see `radio_serial::get_radio_ports`
+//
+// fills up to `cap` entries of `out` and writes the number of Radios found
+// to `*count`, `ARMLAB_BUFFER_TOO_SMALL` if that is more than `cap`
+//
+// # Safety
+// `out` must point to `cap` writable entries, `count` must be a valid pointer
+enum ArmlabStatus armlab_radio_discover_serial(struct ArmlabDevice *out,
+                                               uintptr_t cap,
+                                               uintptr_t *count);
+
+// finds every Radio on every i2c adapter, see `radio_i2c::discover`
+//
+// fills up to `cap` entries of `out` and writes the number of Radios found
+// to `*count`, `ARMLAB_BUFFER_TOO_SMALL` if that is more than `cap`
+//
+// `ARMLAB_UNSUPPORTED` if the library was built without i2c support
+//
+// # Safety
+// `out` must point to `cap` writable entries, `count` must be a valid pointer
+enum ArmlabStatus armlab_radio_discover_i2c(struct ArmlabDevice *out,
+                                            uintptr_t cap,
+                                            uintptr_t *count);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ARMLAB_RADIO_H */
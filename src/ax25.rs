@@ -0,0 +1,386 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::protocol::MAX_PACKET_LEN;
+
+/// control field of an unnumbered information frame, poll bit clear
+pub const CONTROL_UI: u8 = 0x03;
+/// protocol id for frames with no layer 3 protocol, plain text
+pub const PID_NO_LAYER3: u8 = 0xF0;
+/// most digipeaters a frame can list
+pub const MAX_DIGIPEATERS: usize = 8;
+/// longest callsign, not counting the SSID
+pub const MAX_CALLSIGN_LEN: usize = 6;
+
+/// length of one encoded address
+const ADDR_LEN: usize = 7;
+/// set on the last address of the header
+const EXTENSION_BIT: u8 = 0x01;
+/// command/response bit on the destination and source, has-been-repeated on a digipeater
+const CH_BIT: u8 = 0x80;
+/// reserved SSID bits, sent as 1
+const RESERVED_BITS: u8 = 0x60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ax25Error {
+    /// callsign empty, longer than 6 characters, or not upper case letters and digits
+    InvalidCallsign,
+    /// SSID above 15
+    InvalidSsid,
+    /// more than `MAX_DIGIPEATERS` in the path
+    TooManyDigipeaters,
+    /// encoded frame is longer than `MAX_PACKET_LEN`
+    PacketTooLong,
+    /// packet ends before the address header, control and pid
+    Truncated,
+    /// control field is not a UI frame
+    NotUiFrame,
+}
+
+/// a callsign and SSID, `N0CALL-7`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    pub callsign: String,
+    /// secondary station id, 0-15
+    pub ssid: u8,
+}
+
+impl Address {
+    /// validates `callsign` and `ssid`, the callsign is upper cased
+    pub fn new(callsign: &str, ssid: u8) -> Result<Address, Ax25Error> {
+        let callsign = callsign.to_ascii_uppercase();
+
+        if callsign.is_empty() || callsign.len() > MAX_CALLSIGN_LEN || !callsign.bytes().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(Ax25Error::InvalidCallsign);
+        }
+
+        if ssid > 15 {
+            return Err(Ax25Error::InvalidSsid);
+        }
+
+        return Ok(Address { callsign, ssid });
+    }
+
+    /// the 7 byte on air form, each callsign character shifted left one bit
+    /// and padded with spaces, followed by the SSID byte
+    fn encode(&self, flag: bool, last: bool) -> [u8; ADDR_LEN] {
+        let mut buf = [b' ' << 1; ADDR_LEN];
+
+        for (i, c) in self.callsign.bytes().enumerate() {
+            buf[i] = c << 1;
+        }
+
+        buf[6] = RESERVED_BITS | (self.ssid << 1);
+        if flag {
+            buf[6] |= CH_BIT;
+        }
+        if last {
+            buf[6] |= EXTENSION_BIT;
+        }
+
+        return buf;
+    }
+
+    /// ## Returns
+    /// the address, its C/H bit and whether it is the last one in the header
+    fn decode(buf: &[u8]) -> Result<(Address, bool, bool), Ax25Error> {
+        let mut callsign = String::with_capacity(MAX_CALLSIGN_LEN);
+
+        for &c in &buf[..6] {
+            match c >> 1 {
+                b' ' => break,
+                n => callsign.push(n as char),
+            }
+        }
+
+        let address = Address::new(&callsign, (buf[6] >> 1) & 0x0F)?;
+        return Ok((address, buf[6] & CH_BIT != 0, buf[6] & EXTENSION_BIT != 0));
+    }
+}
+
+impl FromStr for Address {
+    type Err = Ax25Error;
+
+    /// parses `CALL` or `CALL-SSID`
+    fn from_str(s: &str) -> Result<Address, Ax25Error> {
+        match s.split_once('-') {
+            Some((call, ssid)) => match ssid.parse::<u8>() {
+                Ok(n) => Address::new(call, n),
+                Err(_) => Err(Ax25Error::InvalidSsid),
+            },
+            None => Address::new(s, 0),
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    /// `CALL`, or `CALL-SSID` when the SSID is not 0
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ssid {
+            0 => write!(f, "{}", self.callsign),
+            n => write!(f, "{}-{}", self.callsign, n),
+        }
+    }
+}
+
+/// a station in the digipeater path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Digipeater {
+    pub address: Address,
+    /// set by the digipeater once it has relayed the frame
+    pub repeated: bool,
+}
+
+impl FromStr for Digipeater {
+    type Err = Ax25Error;
+
+    /// parses `CALL-SSID`, with a trailing `*` when already repeated
+    fn from_str(s: &str) -> Result<Digipeater, Ax25Error> {
+        let (s, repeated) = match s.strip_suffix('*') {
+            Some(n) => (n, true),
+            None => (s, false),
+        };
+
+        return Ok(Digipeater { address: s.parse()?, repeated });
+    }
+}
+
+impl fmt::Display for Digipeater {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.repeated {
+            true => write!(f, "{}*", self.address),
+            false => write!(f, "{}", self.address),
+        }
+    }
+}
+
+/// an AX.25 unnumbered information frame
+///
+/// frames are sent without the HDLC flags and FCS, the CC1200 packet
+/// engine already frames and CRCs each packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ax25Frame {
+    pub destination: Address,
+    pub source: Address,
+    /// digipeaters the frame is relayed through, in order
+    pub path: Vec<Digipeater>,
+    /// layer 3 protocol id, `PID_NO_LAYER3` for text
+    pub pid: u8,
+    pub info: Vec<u8>,
+}
+
+impl Ax25Frame {
+    /// a text frame from `source` to `destination` with no digipeaters
+    pub fn new(source: Address, destination: Address, info: &[u8]) -> Ax25Frame {
+        Ax25Frame {
+            destination,
+            source,
+            path: Vec::new(),
+            pid: PID_NO_LAYER3,
+            info: info.to_vec(),
+        }
+    }
+
+    /// parses a TNC2 style path, `SRC>DEST` or `SRC>DEST,DIGI1,DIGI2`
+    pub fn with_path(header: &str, info: &[u8]) -> Result<Ax25Frame, Ax25Error> {
+        let (source, rest) = match header.split_once('>') {
+            Some(n) => n,
+            None => {return Err(Ax25Error::InvalidCallsign);},
+        };
+
+        let mut calls = rest.split(',');
+        let destination = calls.next().unwrap_or("").parse()?;
+
+        let mut frame = Ax25Frame::new(source.parse()?, destination, info);
+        for digi in calls {
+            frame.path.push(digi.parse()?);
+        }
+
+        if frame.path.len() > MAX_DIGIPEATERS {
+            return Err(Ax25Error::TooManyDigipeaters);
+        }
+
+        return Ok(frame);
+    }
+
+    /// number of bytes `encode` produces
+    pub fn encoded_len(&self) -> usize {
+        (2 + self.path.len()) * ADDR_LEN + 2 + self.info.len()
+    }
+
+    /// encodes the frame for `Transceiver::transmit`
+    ///
+    /// the frame is marked as a command, destination C bit set and source C bit clear
+    pub fn encode(&self) -> Result<Vec<u8>, Ax25Error> {
+        if self.path.len() > MAX_DIGIPEATERS {
+            return Err(Ax25Error::TooManyDigipeaters);
+        }
+
+        if self.encoded_len() > MAX_PACKET_LEN {
+            return Err(Ax25Error::PacketTooLong);
+        }
+
+        let mut buf = Vec::with_capacity(self.encoded_len());
+        buf.extend_from_slice(&self.destination.encode(true, false));
+        buf.extend_from_slice(&self.source.encode(false, self.path.is_empty()));
+
+        for (i, digi) in self.path.iter().enumerate() {
+            buf.extend_from_slice(&digi.address.encode(digi.repeated, i + 1 == self.path.len()));
+        }
+
+        buf.push(CONTROL_UI);
+        buf.push(self.pid);
+        buf.extend_from_slice(&self.info);
+
+        return Ok(buf);
+    }
+
+    /// parses a packet from `Transceiver::get_packet`
+    ///
+    /// only UI frames are accepted, the poll/final bit is ignored
+    pub fn decode(buf: &[u8]) -> Result<Ax25Frame, Ax25Error> {
+        let mut addresses = Vec::with_capacity(2);
+        let mut offset = 0;
+
+        loop {
+            if buf.len() < offset + ADDR_LEN {
+                return Err(Ax25Error::Truncated);
+            }
+
+            let (address, flag, last) = Address::decode(&buf[offset..offset + ADDR_LEN])?;
+            addresses.push((address, flag));
+            offset += ADDR_LEN;
+
+            if last {
+                break;
+            }
+
+            if addresses.len() == 2 + MAX_DIGIPEATERS {
+                return Err(Ax25Error::TooManyDigipeaters);
+            }
+        }
+
+        if addresses.len() < 2 {
+            return Err(Ax25Error::Truncated);
+        }
+
+        if buf.len() < offset + 2 {
+            return Err(Ax25Error::Truncated);
+        }
+
+        // ignore the poll/final bit
+        if buf[offset] & !0x10 != CONTROL_UI {
+            return Err(Ax25Error::NotUiFrame);
+        }
+
+        let mut addresses = addresses.into_iter();
+        let (destination, _) = addresses.next().unwrap();
+        let (source, _) = addresses.next().unwrap();
+
+        return Ok(Ax25Frame {
+            destination,
+            source,
+            path: addresses.map(|(address, repeated)| Digipeater { address, repeated }).collect(),
+            pid: buf[offset + 1],
+            info: buf[offset + 2..].to_vec(),
+        });
+    }
+
+    /// the info field as text, None if it is not UTF-8
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.info).ok()
+    }
+}
+
+impl fmt::Display for Ax25Frame {
+    /// TNC2 monitor format, `SRC>DEST,DIGI*:info`, binary info is shown lossily
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}>{}", self.source, self.destination)?;
+
+        for digi in &self.path {
+            write!(f, ",{}", digi)?;
+        }
+
+        write!(f, ":{}", String::from_utf8_lossy(&self.info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        let frame = Ax25Frame::with_path("N0CALL-7>APRS", b"hi").unwrap();
+
+        let mut expected: Vec<u8> = b"APRS  ".iter().map(|c| c << 1).collect();
+        expected.push(0xE0);
+        expected.extend(b"N0CALL".iter().map(|c| c << 1));
+        expected.push(0x60 | (7 << 1) | 0x01);
+        expected.extend([CONTROL_UI, PID_NO_LAYER3, b'h', b'i']);
+
+        assert_eq!(frame.encode().unwrap(), expected);
+        assert_eq!(frame.encoded_len(), expected.len());
+    }
+
+    #[test]
+    fn round_trip() {
+        let frame = Ax25Frame::with_path("N0CALL>APRS,WIDE1-1*,WIDE2-2", &[0, 0xFF, b':']).unwrap();
+        let decoded = Ax25Frame::decode(&frame.encode().unwrap()).unwrap();
+
+        assert_eq!(decoded, frame);
+        assert!(decoded.path[0].repeated);
+        assert_eq!(decoded.to_string(), "N0CALL>APRS,WIDE1-1*,WIDE2-2:\0\u{FFFD}:");
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!("n0call-15".parse(), Address::new("N0CALL", 15));
+        assert_eq!("N0CALL-16".parse::<Address>(), Err(Ax25Error::InvalidSsid));
+        assert_eq!("N0CALL-".parse::<Address>(), Err(Ax25Error::InvalidSsid));
+        assert_eq!("TOOLONG".parse::<Address>(), Err(Ax25Error::InvalidCallsign));
+        assert_eq!("N0 CAL".parse::<Address>(), Err(Ax25Error::InvalidCallsign));
+        assert_eq!("".parse::<Address>(), Err(Ax25Error::InvalidCallsign));
+
+        assert_eq!(Ax25Frame::with_path("N0CALL", b""), Err(Ax25Error::InvalidCallsign));
+        assert_eq!(Ax25Frame::with_path("A>B,1,2,3,4,5,6,7,8,9", b""), Err(Ax25Error::TooManyDigipeaters));
+    }
+
+    #[test]
+    fn encode_limits() {
+        let frame = Ax25Frame::with_path("N0CALL>APRS", &[0; MAX_PACKET_LEN]).unwrap();
+        assert_eq!(frame.encode(), Err(Ax25Error::PacketTooLong));
+    }
+
+    #[test]
+    fn malformed_frames() {
+        let frame = Ax25Frame::with_path("N0CALL>APRS,WIDE1-1", b"info").unwrap().encode().unwrap();
+
+        // cut inside the addresses, then before the control field
+        assert_eq!(Ax25Frame::decode(&frame[..10]), Err(Ax25Error::Truncated));
+        assert_eq!(Ax25Frame::decode(&frame[..21]), Err(Ax25Error::Truncated));
+        assert_eq!(Ax25Frame::decode(&[]), Err(Ax25Error::Truncated));
+
+        // a single address marked as the last one
+        let mut lone = frame[..7].to_vec();
+        lone[6] |= EXTENSION_BIT;
+        lone.extend([CONTROL_UI, PID_NO_LAYER3]);
+        assert_eq!(Ax25Frame::decode(&lone), Err(Ax25Error::Truncated));
+
+        // an I frame, then a UI frame with the poll bit set
+        let mut other = frame.clone();
+        other[21] = 0x00;
+        assert_eq!(Ax25Frame::decode(&other), Err(Ax25Error::NotUiFrame));
+        other[21] = CONTROL_UI | 0x10;
+        assert!(Ax25Frame::decode(&other).is_ok());
+
+        // a callsign character that isn't a letter or digit
+        let mut bad_call = frame.clone();
+        bad_call[0] = b'!' << 1;
+        assert_eq!(Ax25Frame::decode(&bad_call), Err(Ax25Error::InvalidCallsign));
+
+        // no address ever has the extension bit
+        let endless: Vec<u8> = frame[..7].iter().cycle().take(7 * 12).copied().collect();
+        assert_eq!(Ax25Frame::decode(&endless), Err(Ax25Error::TooManyDigipeaters));
+    }
+}
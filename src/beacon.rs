@@ -0,0 +1,540 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+use std::thread;
+
+use crate::protocol::{ProtocolError, MAX_PACKET_LEN};
+use crate::transceiver::Transceiver;
+
+/// first bytes of every beacon, lets the receiver skip unrelated traffic
+pub const BEACON_MAGIC: [u8; 2] = [0xBE, 0xAC];
+
+/// magic, sequence number (u32) and sender timestamp (u64 us), little endian
+pub const BEACON_HEADER_LEN: usize = 14;
+
+/// sequence numbers remembered for duplicate detection, older beacons are `Arrival::Stale`
+const SEEN_HISTORY: i64 = 4096;
+
+/// how beacons are sent
+#[derive(Debug, Clone)]
+pub struct BeaconConfig {
+    /// time between beacons
+    pub interval: Duration,
+    /// payload sent after the header, `{seq}` and `{time}` (ms since the
+    /// first beacon) are replaced in each beacon
+    pub template: Vec<u8>,
+    /// stop after this many beacons, None to run forever
+    pub count: Option<u32>,
+}
+
+impl Default for BeaconConfig {
+    /// ## Default
+    /// 100 ms interval, "beacon | {seq}", unlimited
+    fn default() -> Self {
+        BeaconConfig {
+            interval: Duration::from_millis(100),
+            template: b"beacon | {seq}".to_vec(),
+            count: None,
+        }
+    }
+}
+
+/// a decoded beacon
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeaconFrame {
+    pub seq: u32,
+    /// us since the sender started, on the senders clock
+    pub timestamp: u64,
+    /// the rendered template
+    pub payload: Vec<u8>,
+}
+
+impl BeaconFrame {
+    /// encodes the frame into a packet
+    pub fn encode(&self) -> Result<Vec<u8>, ProtocolError> {
+        if BEACON_HEADER_LEN + self.payload.len() > MAX_PACKET_LEN {
+            return Err(ProtocolError::PacketTooLong);
+        }
+
+        let mut out: Vec<u8> = Vec::with_capacity(BEACON_HEADER_LEN + self.payload.len());
+        out.extend_from_slice(&BEACON_MAGIC);
+        out.extend_from_slice(&self.seq.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+        out.extend_from_slice(&self.payload);
+
+        return Ok(out);
+    }
+
+    /// decodes a packet produced by `BeaconFrame::encode`
+    ///
+    /// ## Returns
+    /// None if the packet is not a beacon
+    pub fn decode(packet: &[u8]) -> Option<BeaconFrame> {
+        if packet.len() < BEACON_HEADER_LEN || packet[..2] != BEACON_MAGIC {
+            return None;
+        }
+
+        let seq = u32::from_le_bytes(packet[2..6].try_into().ok()?);
+        let timestamp = u64::from_le_bytes(packet[6..14].try_into().ok()?);
+
+        Some(BeaconFrame { seq, timestamp, payload: packet[BEACON_HEADER_LEN..].to_vec() })
+    }
+}
+
+/// replaces `{seq}` and `{time}` in `template`
+fn render(template: &[u8], seq: u32, time_ms: u64) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(template.len());
+    let mut rest = template;
+
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix(b"{seq}") {
+            out.extend_from_slice(seq.to_string().as_bytes());
+            rest = tail;
+        }
+        else if let Some(tail) = rest.strip_prefix(b"{time}") {
+            out.extend_from_slice(time_ms.to_string().as_bytes());
+            rest = tail;
+        }
+        else {
+            out.push(rest[0]);
+            rest = &rest[1..];
+        }
+    }
+
+    return out;
+}
+
+/// sends numbered, timestamped beacons at a fixed rate
+///
+/// the Radio is passed to each call rather than owned, so the same
+/// Radio can be retuned or used for other traffic between beacons
+pub struct BeaconTransmitter {
+    config: BeaconConfig,
+    seq: u32,
+    start: Instant,
+    next: Instant,
+}
+
+impl BeaconTransmitter {
+    /// creates a transmitter, the first beacon is due immediately
+    ///
+    /// fails if the template could render longer than a single packet
+    pub fn new(config: BeaconConfig) -> Result<BeaconTransmitter, ProtocolError> {
+        // the longest the template can render to
+        if BEACON_HEADER_LEN + render(&config.template, u32::MAX, u64::MAX).len() > MAX_PACKET_LEN {
+            return Err(ProtocolError::PacketTooLong);
+        }
+
+        let now = Instant::now();
+        Ok(BeaconTransmitter { config, seq: 0, start: now, next: now })
+    }
+
+    /// the sequence number of the next beacon
+    pub fn seq(&self) -> u32 {
+        self.seq
+    }
+
+    /// wheather or not `count` beacons have been sent
+    pub fn is_done(&self) -> bool {
+        self.config.count.is_some_and(|n| self.seq >= n)
+    }
+
+    /// builds the next beacon and advances the sequence number
+    pub fn next_frame(&mut self) -> BeaconFrame {
+        let elapsed = self.start.elapsed();
+        let payload = render(&self.config.template, self.seq, elapsed.as_millis() as u64);
+
+        let frame = BeaconFrame { seq: self.seq, timestamp: elapsed.as_micros() as u64, payload };
+        self.seq = self.seq.wrapping_add(1);
+
+        return frame;
+    }
+
+    /// sends the next beacon immediately
+    ///
+    /// ## Returns
+    /// the sequence number sent
+    pub fn send<T: Transceiver>(&mut self, radio: &mut T) -> Result<u32, T::Error> {
+        let frame = self.next_frame();
+
+        // length was checked against the template in `new`
+        radio.transmit(&frame.encode().unwrap_or_default())?;
+        return Ok(frame.seq);
+    }
+
+    /// sleeps until the next beacon is due, then sends it
+    ///
+    /// beacons are scheduled from the first one, so a slow transmit
+    /// doesn't push every later beacon back
+    ///
+    /// ## Returns
+    /// the sequence number sent, None once `count` beacons have been sent
+    pub fn wait_and_send<T: Transceiver>(&mut self, radio: &mut T) -> Result<Option<u32>, T::Error> {
+        if self.is_done() {
+            return Ok(None);
+        }
+
+        let now = Instant::now();
+        if self.next > now {
+            thread::sleep(self.next - now);
+        }
+
+        // skip beacons that are already late instead of bursting them out
+        self.next += self.config.interval;
+        if self.next < Instant::now() {
+            self.next = Instant::now() + self.config.interval;
+        }
+
+        return self.send(radio).map(Some);
+    }
+}
+
+/// how a recieved beacon relates to the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrival {
+    /// newer than every beacon so far
+    InOrder,
+    /// older than the newest beacon, but not seen before
+    OutOfOrder,
+    /// already recieved
+    Duplicate,
+    /// older than the remembered history, so it can't be told apart from a
+    /// duplicate, it isn't counted as recieved
+    Stale,
+}
+
+/// packet error rate over a fixed run of sequence numbers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowReport {
+    /// first sequence number in the window
+    pub first_seq: u32,
+    pub expected: u32,
+    pub received: u32,
+    /// 0.0 - 1.0
+    pub per: f64,
+}
+
+/// totals since the receiver was created
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BeaconStats {
+    /// distinct beacons recieved
+    pub received: u64,
+    /// beacons sent between the first and newest seen
+    pub expected: u64,
+    pub lost: u64,
+    pub duplicates: u64,
+    pub out_of_order: u64,
+    /// beacons too old to tell wheather they were duplicates, see `Arrival::Stale`
+    pub stale: u64,
+    /// packets that were not beacons
+    pub invalid: u64,
+    /// overall packet error rate, 0.0 - 1.0
+    pub per: f64,
+    /// mean time between recieved beacons (us)
+    pub mean_interval: f64,
+    /// inter-arrival jitter (us), smoothed as in RFC 3550
+    pub jitter: f64,
+}
+
+/// tracks beacons from a `BeaconTransmitter` and computes link statistics
+///
+/// sequence numbers wrap, so each is placed on a line that doesn't, within
+/// 2^31 of the newest beacon, and compared there
+pub struct BeaconReceiver {
+    window: u32,
+    start: Instant,
+    /// first sequence number seen, windows are counted from here
+    base: Option<i64>,
+    lowest: i64,
+    highest: i64,
+    seen: BTreeSet<i64>,
+    stats: BeaconStats,
+    last_arrival: Option<Instant>,
+    interval_sum: f64,
+    intervals: u64,
+    /// arrival - send time of the previous in order beacon (us)
+    last_transit: Option<f64>,
+    /// beacons recieved in each unfinished window
+    open_windows: BTreeMap<i64, u32>,
+    next_window: i64,
+    windows: Vec<WindowReport>,
+}
+
+impl BeaconReceiver {
+    /// creates a receiver reporting PER over every `window` sequence numbers
+    pub fn new(window: u32) -> BeaconReceiver {
+        BeaconReceiver {
+            window: window.max(1),
+            start: Instant::now(),
+            base: None,
+            lowest: 0,
+            highest: 0,
+            seen: BTreeSet::new(),
+            stats: BeaconStats::default(),
+            last_arrival: None,
+            interval_sum: 0.0,
+            intervals: 0,
+            last_transit: None,
+            open_windows: BTreeMap::new(),
+            next_window: 0,
+            windows: vec![],
+        }
+    }
+
+    /// polls the Radio once, recording a beacon if one was waiting
+    ///
+    /// ## Returns
+    /// the beacon and how it arrived, None if there was no beacon
+    pub fn poll<T: Transceiver>(&mut self, radio: &mut T) -> Result<Option<(BeaconFrame, Arrival)>, T::Error> {
+        let packet = radio.get_packet()?;
+
+        if packet.is_empty() {
+            return Ok(None);
+        }
+
+        return Ok(self.receive(&packet));
+    }
+
+    /// records a packet recieved now
+    pub fn receive(&mut self, packet: &[u8]) -> Option<(BeaconFrame, Arrival)> {
+        self.receive_at(packet, Instant::now())
+    }
+
+    /// records a packet recieved at `at`
+    pub fn receive_at(&mut self, packet: &[u8], at: Instant) -> Option<(BeaconFrame, Arrival)> {
+        let frame = match BeaconFrame::decode(packet) {
+            Some(n) => n,
+            None => {
+                self.stats.invalid += 1;
+                return None;
+            },
+        };
+
+        let seq = self.unwrap_seq(frame.seq);
+
+        let arrival = match self.base {
+            None => {
+                self.base = Some(seq);
+                self.lowest = seq;
+                self.highest = seq;
+                Arrival::InOrder
+            },
+            Some(_) if self.seen.contains(&seq) => Arrival::Duplicate,
+            Some(_) if seq > self.highest => {
+                self.highest = seq;
+                Arrival::InOrder
+            },
+            Some(_) if seq + SEEN_HISTORY < self.highest => Arrival::Stale,
+            Some(_) => {
+                self.lowest = self.lowest.min(seq);
+                Arrival::OutOfOrder
+            },
+        };
+
+        match arrival {
+            Arrival::Duplicate => {
+                self.stats.duplicates += 1;
+                return Some((frame, arrival));
+            },
+            Arrival::Stale => {
+                self.stats.stale += 1;
+                return Some((frame, arrival));
+            },
+            Arrival::OutOfOrder => self.stats.out_of_order += 1,
+            Arrival::InOrder => {},
+        };
+
+        self.stats.received += 1;
+        self.seen.insert(seq);
+        while self.seen.first().is_some_and(|n| n + SEEN_HISTORY < self.highest) {
+            self.seen.pop_first();
+        }
+
+        // inter-arrival time and jitter only make sense between in order beacons
+        if arrival == Arrival::InOrder {
+            if let Some(last) = self.last_arrival {
+                self.interval_sum += at.duration_since(last).as_micros() as f64;
+                self.intervals += 1;
+            }
+            self.last_arrival = Some(at);
+
+            let transit = at.duration_since(self.start).as_micros() as f64 - frame.timestamp as f64;
+            if let Some(last) = self.last_transit {
+                self.stats.jitter += ((transit - last).abs() - self.stats.jitter) / 16.0;
+            }
+            self.last_transit = Some(transit);
+        }
+
+        self.record_window(seq);
+
+        return Some((frame, arrival));
+    }
+
+    /// `seq` on the line that doesn't wrap, the closest position to the newest beacon
+    fn unwrap_seq(&self, seq: u32) -> i64 {
+        match self.base {
+            None => seq as i64,
+            // the distance from the newest beacon, negative for older ones
+            Some(_) => self.highest + seq.wrapping_sub(self.highest as u32) as i32 as i64,
+        }
+    }
+
+    /// counts `seq` in its window and closes every window the newest beacon has moved past
+    fn record_window(&mut self, seq: i64) {
+        let base = self.base.unwrap_or(seq);
+        let window = self.window as i64;
+
+        // beacons from before the first one seen, or from closed windows, only count in the totals
+        if seq >= base {
+            let index = (seq - base) / window;
+            if index >= self.next_window {
+                *self.open_windows.entry(index).or_insert(0) += 1;
+            }
+        }
+
+        let current = (self.highest - base) / window;
+        while self.next_window < current {
+            let received = self.open_windows.remove(&self.next_window).unwrap_or(0);
+
+            self.windows.push(WindowReport {
+                // back to the sequence number the beacon carried
+                first_seq: (base + self.next_window * window) as u32,
+                expected: self.window,
+                received,
+                per: 1.0 - received as f64 / self.window as f64,
+            });
+
+            self.next_window += 1;
+        }
+    }
+
+    /// totals so far
+    pub fn stats(&self) -> BeaconStats {
+        let mut stats = self.stats;
+
+        if self.base.is_some() {
+            stats.expected = (self.highest - self.lowest) as u64 + 1;
+        }
+
+        stats.lost = stats.expected.saturating_sub(stats.received);
+        stats.per = match stats.expected {
+            0 => 0.0,
+            n => stats.lost as f64 / n as f64,
+        };
+        stats.mean_interval = match self.intervals {
+            0 => 0.0,
+            n => self.interval_sum / n as f64,
+        };
+
+        return stats;
+    }
+
+    /// every window completed so far, oldest first
+    pub fn windows(&self) -> &[WindowReport] {
+        &self.windows
+    }
+
+    /// forgets every beacon and statistic
+    pub fn reset(&mut self) {
+        *self = BeaconReceiver::new(self.window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beacon(seq: u32) -> Vec<u8> {
+        BeaconFrame { seq, timestamp: 0, payload: b"hi".to_vec() }.encode().unwrap()
+    }
+
+    /// feeds `seqs` to `receiver`, returning how each arrived
+    fn receive(receiver: &mut BeaconReceiver, seqs: &[u32]) -> Vec<Arrival> {
+        seqs.iter().map(|n| receiver.receive(&beacon(*n)).unwrap().1).collect()
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let frame = BeaconFrame { seq: 7, timestamp: 1234, payload: render(b"n{seq} t{time}", 7, 99) };
+        assert_eq!(frame.payload, b"n7 t99".to_vec());
+        assert_eq!(BeaconFrame::decode(&frame.encode().unwrap()), Some(frame));
+
+        assert_eq!(BeaconFrame::decode(b"not a beacon at all"), None);
+        assert!(BeaconFrame { seq: 0, timestamp: 0, payload: vec![0; MAX_PACKET_LEN] }.encode().is_err());
+    }
+
+    #[test]
+    fn loss() {
+        let mut receiver = BeaconReceiver::new(5);
+        receive(&mut receiver, &[10, 11, 13, 14, 16, 17]);
+        assert!(receiver.receive(b"noise").is_none());
+
+        let stats = receiver.stats();
+        assert_eq!((stats.received, stats.expected, stats.lost, stats.invalid), (6, 8, 2, 1));
+        assert_eq!(stats.per, 0.25);
+
+        // 10-14 closed once 15 was passed
+        assert_eq!(receiver.windows(), &[WindowReport { first_seq: 10, expected: 5, received: 4, per: 1.0 - 4.0 / 5.0 }]);
+    }
+
+    #[test]
+    fn duplicates_and_reordering() {
+        let mut receiver = BeaconReceiver::new(100);
+        let arrivals = receive(&mut receiver, &[0, 2, 1, 2, 3, 0]);
+
+        use Arrival::*;
+        assert_eq!(arrivals, vec![InOrder, InOrder, OutOfOrder, Duplicate, InOrder, Duplicate]);
+
+        let stats = receiver.stats();
+        assert_eq!((stats.received, stats.expected, stats.lost), (4, 4, 0));
+        assert_eq!((stats.duplicates, stats.out_of_order), (2, 1));
+
+        // a late beacon from before the first one widens the range
+        assert_eq!(receive(&mut receiver, &[u32::MAX]), vec![OutOfOrder]);
+        assert_eq!((receiver.stats().received, receiver.stats().expected), (5, 5));
+    }
+
+    #[test]
+    fn forgotten_beacons_are_stale() {
+        let mut receiver = BeaconReceiver::new(100);
+        receive(&mut receiver, &[0, 1, 5000]);
+
+        // 0 and 1 are no longer remembered, so they can't be counted again
+        assert_eq!(receive(&mut receiver, &[1, 2]), vec![Arrival::Stale, Arrival::Stale]);
+        // but a late beacon still within the history is
+        assert_eq!(receive(&mut receiver, &[4000]), vec![Arrival::OutOfOrder]);
+
+        let stats = receiver.stats();
+        assert_eq!((stats.received, stats.stale, stats.duplicates), (4, 2, 0));
+        assert!(stats.received <= stats.expected);
+    }
+
+    #[test]
+    fn wraparound() {
+        let mut receiver = BeaconReceiver::new(4);
+        let start = u32::MAX - 5;
+
+        let seqs: Vec<u32> = (0..12).map(|n| start.wrapping_add(n)).filter(|n| *n != 1).collect();
+        let arrivals = receive(&mut receiver, &seqs);
+        assert!(arrivals.iter().all(|a| *a == Arrival::InOrder));
+
+        let stats = receiver.stats();
+        assert_eq!((stats.received, stats.expected, stats.lost), (11, 12, 1));
+
+        let windows: Vec<(u32, u32)> = receiver.windows().iter().map(|w| (w.first_seq, w.received)).collect();
+        assert_eq!(windows, vec![(start, 4), (start.wrapping_add(4), 3)]);
+
+        // a duplicate and a late beacon from before the wrap
+        assert_eq!(receive(&mut receiver, &[u32::MAX, start.wrapping_sub(1)]), vec![Arrival::Duplicate, Arrival::OutOfOrder]);
+        assert_eq!(receiver.stats().lost, 1);
+    }
+
+    #[test]
+    fn reset() {
+        let mut receiver = BeaconReceiver::new(4);
+        receive(&mut receiver, &[1, 2, 3]);
+        receiver.reset();
+
+        assert_eq!(receiver.stats(), BeaconStats::default());
+        assert_eq!(receive(&mut receiver, &[1]), vec![Arrival::InOrder]);
+    }
+}
@@ -0,0 +1,113 @@
+use std::{env, fs, path::PathBuf};
+
+use serde_json::{Map, Value};
+
+use ArmlabRadio::protocol::ModulationFormat;
+use ArmlabRadio::transceiver::RadioConfig;
+
+use crate::device::Target;
+
+/// names of the settings `config set` can change, in display order
+pub const SETTINGS: [&str; 6] = ["frequency", "power", "deviation", "symbol_rate", "rx_filter", "modulation"];
+
+/// settings last applied to each Radio through this tool
+///
+/// the Radio firmware can't report its settings, so `config get` reads
+/// them back from here, stored as json keyed by `serial:<port>` / `i2c:<bus>`
+pub struct ConfigStore {
+    path: Option<PathBuf>,
+    devices: Map<String, Value>,
+}
+
+impl ConfigStore {
+    /// loads the store, an unreadable or missing file is treated as empty
+    pub fn load() -> ConfigStore {
+        let path = store_path();
+
+        let devices = path.as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+            .and_then(|v| match v {
+                Value::Object(map) => Some(map),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        ConfigStore { path, devices }
+    }
+
+    /// the settings recorded for `target`, as a json object
+    pub fn get(&self, target: &Target) -> Map<String, Value> {
+        match self.devices.get(&target.to_string()) {
+            Some(Value::Object(map)) => map.clone(),
+            _ => Map::new(),
+        }
+    }
+
+    /// the settings recorded for `target`, anything not recorded is None
+    pub fn radio_config(&self, target: &Target) -> RadioConfig {
+        let settings = self.get(target);
+        let num = |name: &str| settings.get(name).and_then(|v| v.as_f64()).map(|n| n as f32);
+
+        RadioConfig {
+            frequency: num("frequency"),
+            power: num("power"),
+            deviation: num("deviation"),
+            symbol_rate: num("symbol_rate"),
+            rx_filter: num("rx_filter"),
+            modulation: match settings.get("modulation").and_then(|v| v.as_str()) {
+                Some("fsk2") => Some(ModulationFormat::FSK2),
+                Some("gfsk2") => Some(ModulationFormat::GFSK2),
+                Some("ask") => Some(ModulationFormat::ASK),
+                Some("fsk4") => Some(ModulationFormat::FSK4),
+                Some("gfsk4") => Some(ModulationFormat::GFSK4),
+                _ => None,
+            },
+        }
+    }
+
+    /// records that `setting` was set to `value` on `target`
+    pub fn set(&mut self, target: &Target, setting: &str, value: Value) {
+        let entry = self.devices.entry(target.to_string()).or_insert_with(|| Value::Object(Map::new()));
+
+        if !entry.is_object() {
+            *entry = Value::Object(Map::new());
+        }
+
+        if let Value::Object(map) = entry {
+            map.insert(setting.to_string(), value);
+        }
+    }
+
+    /// forgets every setting recorded for `target`, used after a reset
+    pub fn clear(&mut self, target: &Target) {
+        self.devices.remove(&target.to_string());
+    }
+
+    /// writes the store back to disk
+    pub fn save(&self) -> Result<(), String> {
+        let path = match &self.path {
+            Some(n) => n,
+            None => {return Err("no config directory, set XDG_CONFIG_HOME or HOME".to_string());},
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("error creating {} | {}", dir.display(), e))?;
+        }
+
+        let data = serde_json::to_string_pretty(&self.devices).map_err(|e| e.to_string())?;
+        fs::write(path, data).map_err(|e| format!("error writing {} | {}", path.display(), e))?;
+
+        return Ok(());
+    }
+}
+
+/// `$XDG_CONFIG_HOME/armlab-radio/settings.json`, falling back to
+/// `~/.config` and then `%APPDATA%`
+fn store_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+
+    return Some(base.join("armlab-radio").join("settings.json"));
+}
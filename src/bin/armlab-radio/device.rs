@@ -0,0 +1,235 @@
+use std::fmt;
+
+use ArmlabRadio::protocol::ModulationFormat;
+use ArmlabRadio::transceiver::Transceiver;
+
+#[cfg(feature = "serial")]
+use ArmlabRadio::radio_serial;
+
+#[cfg(all(feature = "i2c", target_os = "linux"))]
+use ArmlabRadio::radio_i2c::{self, LinuxRadio};
+
+/// the address Radios answer on out of the box
+///
+/// targets at it are named without the address, like before addresses were
+/// kept, so settings saved for them still apply
+const PRIMARY_I2C_ADDR: u8 = 0x34;
+
+/// the link a Radio is reached over, before it is opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Serial(String),
+    /// bus path and address, None tries the primary address then the backup
+    I2c(String, Option<u8>),
+}
+
+/// parses an i2c address given as hex (0x35) or decimal (53)
+pub fn parse_i2c_addr(arg: &str) -> Result<u8, String> {
+    let res = match arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+
+    match res {
+        Ok(n) if n < 0x80 => Ok(n),
+        _ => Err(format!("invalid i2c address {}, expected 0x00 - 0x7F", arg)),
+    }
+}
+
+impl Target {
+    /// picks the Radio to talk to from `--port` / `--i2c-bus` / `--i2c-addr`
+    ///
+    /// with neither given the first serial Radio found is used,
+    /// then the first i2c Radio found
+    pub fn resolve(port: Option<String>, i2c_bus: Option<String>, i2c_addr: Option<u8>) -> Result<Target, String> {
+        if let Some(port) = port {
+            return Ok(Target::Serial(port));
+        }
+
+        if let Some(bus) = i2c_bus {
+            return Ok(Target::I2c(bus, i2c_addr));
+        }
+
+        match list()?.into_iter().next() {
+            Some(target) => Ok(target),
+            None => Err("no Radio found, use --port or --i2c-bus".to_string()),
+        }
+    }
+
+    /// opens the Radio, checking that it answers the ident request
+    pub fn open(&self) -> Result<Device, String> {
+        match self {
+            #[cfg(feature = "serial")]
+            Target::Serial(port) => {
+                // `Radio::new` reports its progress on stdout, which would
+                // end up mixed into piped output
+                let mut radio = radio_serial::Radio::new_bare(port).map_err(|e| format!("{:?}", e))?;
+                radio.sync(6).map_err(|e| format!("{:?}", e))?;
+
+                if !radio.is_device_available() {
+                    return Err(format!("{:?}", radio_serial::RadioError::DevciceDetectError));
+                }
+
+                Ok(Device::Serial(port.clone(), radio))
+            },
+            #[cfg(all(feature = "i2c", target_os = "linux"))]
+            Target::I2c(bus, addr) => {
+                let res = match addr {
+                    Some(n) => radio_i2c::Radio::new_at(bus, *n),
+                    None => radio_i2c::Radio::new(bus),
+                };
+
+                let radio = res.map_err(|e| format!("{:?}", e))?;
+                Ok(Device::I2c(bus.clone(), radio))
+            },
+            #[allow(unreachable_patterns)]
+            _ => Err(format!("{} support was not compiled in", self.kind())),
+        }
+    }
+
+    /// "serial" or "i2c"
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Target::Serial(_) => "serial",
+            Target::I2c(..) => "i2c",
+        }
+    }
+
+    /// the port or bus path
+    pub fn path(&self) -> &str {
+        match self {
+            Target::Serial(path) | Target::I2c(path, _) => path,
+        }
+    }
+
+    /// the i2c address, None over serial or when it wasn't chosen
+    pub fn address(&self) -> Option<u8> {
+        match self {
+            Target::Serial(_) => None,
+            Target::I2c(_, addr) => *addr,
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.address() {
+            Some(n) if n != PRIMARY_I2C_ADDR => write!(f, "{}:{}@0x{:02x}", self.kind(), self.path(), n),
+            _ => write!(f, "{}:{}", self.kind(), self.path()),
+        }
+    }
+}
+
+/// every Radio attached to the system, i2c targets with the address they answered on
+pub fn list() -> Result<Vec<Target>, String> {
+    #[allow(unused_mut)]
+    let mut out: Vec<Target> = vec![];
+
+    #[cfg(feature = "serial")]
+    for port in radio_serial::get_radio_ports().map_err(|e| format!("{:?}", e))? {
+        out.push(Target::Serial(port));
+    }
+
+    #[cfg(all(feature = "i2c", target_os = "linux"))]
+    for (bus, addr) in radio_i2c::discover().map_err(|e| format!("{:?}", e))? {
+        out.push(Target::I2c(bus, Some(addr)));
+    }
+
+    return Ok(out);
+}
+
+/// an opened Radio on either link
+pub enum Device {
+    #[cfg(feature = "serial")]
+    Serial(String, radio_serial::Radio),
+    #[cfg(all(feature = "i2c", target_os = "linux"))]
+    I2c(String, LinuxRadio),
+}
+
+/// runs `$body` against whichever Radio the device holds, stringifying the error
+macro_rules! dispatch {
+    ($dev:expr, $radio:ident => $body:expr) => {
+        match $dev {
+            #[cfg(feature = "serial")]
+            Device::Serial(_, $radio) => $body.map_err(|e| format!("{:?}", e)),
+            #[cfg(all(feature = "i2c", target_os = "linux"))]
+            Device::I2c(_, $radio) => $body.map_err(|e| format!("{:?}", e)),
+        }
+    };
+}
+
+impl Device {
+    /// the target this device was opened from
+    pub fn target(&self) -> Target {
+        match self {
+            #[cfg(feature = "serial")]
+            Device::Serial(port, _) => Target::Serial(port.clone()),
+            #[cfg(all(feature = "i2c", target_os = "linux"))]
+            Device::I2c(bus, radio) => Target::I2c(bus.clone(), Some(radio.address())),
+        }
+    }
+
+    /// the i2c address of the Radio, None over serial
+    pub fn address(&self) -> Option<u8> {
+        match self {
+            #[cfg(feature = "serial")]
+            Device::Serial(..) => None,
+            #[cfg(all(feature = "i2c", target_os = "linux"))]
+            Device::I2c(_, radio) => Some(radio.address()),
+        }
+    }
+
+    /// queries the radio and checks if it is available
+    pub fn is_device_available(&mut self) -> bool {
+        match self {
+            #[cfg(feature = "serial")]
+            Device::Serial(_, radio) => radio.is_device_available(),
+            #[cfg(all(feature = "i2c", target_os = "linux"))]
+            Device::I2c(_, radio) => radio.is_device_available(),
+        }
+    }
+}
+
+impl Transceiver for Device {
+    type Error = String;
+
+    fn transmit(&mut self, msg: &[u8]) -> Result<(), String> {
+        dispatch!(self, r => r.transmit(msg))
+    }
+
+    fn get_packet(&mut self) -> Result<Vec<u8>, String> {
+        dispatch!(self, r => r.get_packet())
+    }
+
+    fn set_frequency(&mut self, frequency: f32) -> Result<(), String> {
+        dispatch!(self, r => r.set_frequency(frequency))
+    }
+
+    fn set_power(&mut self, power: f32) -> Result<(), String> {
+        dispatch!(self, r => r.set_power(power))
+    }
+
+    fn set_deviation(&mut self, deviation: f32) -> Result<(), String> {
+        dispatch!(self, r => r.set_deviation(deviation))
+    }
+
+    fn set_symbol_rate(&mut self, symbol_rate: f32) -> Result<(), String> {
+        dispatch!(self, r => r.set_symbol_rate(symbol_rate))
+    }
+
+    fn set_rx_filter(&mut self, rx_filter: f32) -> Result<(), String> {
+        dispatch!(self, r => r.set_rx_filter(rx_filter))
+    }
+
+    fn set_modulation(&mut self, mode: ModulationFormat) -> Result<(), String> {
+        dispatch!(self, r => r.set_modulation(mode))
+    }
+
+    fn radio_reset(&mut self) -> Result<(), String> {
+        dispatch!(self, r => r.radio_reset())
+    }
+
+    fn soft_reset(&mut self) -> Result<(), String> {
+        dispatch!(self, r => r.soft_reset())
+    }
+}
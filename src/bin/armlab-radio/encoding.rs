@@ -0,0 +1,35 @@
+use clap::ValueEnum;
+
+use ArmlabRadio::encoding::{base64_decode, hex_decode};
+
+/// how packet data is written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// utf-8 text, invalid bytes are replaced
+    Text,
+    /// lowercase hex
+    Hex,
+    /// standard padded base64
+    Base64,
+    /// the bytes exactly as recieved
+    Raw,
+}
+
+/// how a message given on the command line is interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    Text,
+    Hex,
+    Base64,
+}
+
+impl Encoding {
+    /// converts a command line argument into bytes
+    pub fn decode(&self, msg: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Encoding::Text => Ok(msg.as_bytes().to_vec()),
+            Encoding::Hex => hex_decode(msg),
+            Encoding::Base64 => base64_decode(msg),
+        }
+    }
+}
@@ -0,0 +1,1049 @@
+#[cfg(not(any(feature = "serial", feature = "i2c")))]
+compile_error!("armlab-radio needs the `serial` or `i2c` feature");
+
+mod config;
+mod device;
+mod encoding;
+
+use std::{fs, io::{self, Read, Write}, net::ToSocketAddrs, path::{Path, PathBuf}, process::ExitCode, thread};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use serde_json::{json, Map, Value};
+
+use ArmlabRadio::capture::Direction;
+use ArmlabRadio::daemon::{config_json, ConfigAccess, Daemon};
+use ArmlabRadio::encoding::{base64_encode, hex_encode};
+use ArmlabRadio::kiss::KissServer;
+use ArmlabRadio::linktest::{self, LinkTestConfig, LinkTestReceiver, LinkTestReport, LinkTestSender, Pattern};
+use ArmlabRadio::protocol::{ModulationFormat, MAX_PACKET_LEN};
+use ArmlabRadio::replay::{ReplayConfig, Replayer};
+use ArmlabRadio::shared::SharedRadio;
+use ArmlabRadio::transceiver::Transceiver;
+use ArmlabRadio::uart::{UartConfig, WirelessUart};
+use ArmlabRadio::udp::{Framing, UdpGateway};
+
+use config::{ConfigStore, SETTINGS};
+use device::{Device, Target};
+use encoding::{Encoding, Format};
+
+/// command line tool for ArmLab CC1200 Radios
+#[derive(Parser)]
+#[command(name = "armlab-radio", version)]
+struct Cli {
+    /// serial port of the Radio (e.g. /dev/ttyACM0, COM4)
+    #[arg(long, global = true, conflicts_with = "i2c_bus")]
+    port: Option<String>,
+
+    /// i2c bus of the Radio (e.g. /dev/i2c-1)
+    #[arg(long, global = true)]
+    i2c_bus: Option<String>,
+
+    /// i2c address of the Radio (e.g. 0x35), by default the primary address then the backup
+    #[arg(long, global = true, requires = "i2c_bus", value_parser = device::parse_i2c_addr)]
+    i2c_addr: Option<u8>,
+
+    /// how packet data is printed
+    #[arg(long, short, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// print json instead of text, one object per line
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Cmd,
+}
+
+#[derive(Subcommand)]
+enum Cmd {
+    /// lists every Radio attached to the system
+    List,
+
+    /// opens the Radio and reports how it is connected
+    Info,
+
+    /// transmits a message, split into 255 byte packets if needed
+    Send {
+        /// the message to send
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        message: Option<String>,
+
+        /// send the contents of a file instead, `-` for stdin
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        /// how the message argument is interpreted
+        #[arg(long, short, value_enum, default_value_t = Encoding::Text)]
+        encoding: Encoding,
+    },
+
+    /// waits for packets and prints them
+    Recv {
+        /// number of packets to wait for, 0 to run until the timeout
+        #[arg(long, short = 'n', default_value_t = 1)]
+        count: usize,
+
+        /// give up after this many seconds
+        #[arg(long, short)]
+        timeout: Option<f64>,
+
+        /// time between polls of the Radio (ms)
+        #[arg(long, default_value_t = 50)]
+        interval: u64,
+    },
+
+    /// changes or shows the Radio settings
+    Config {
+        #[command(subcommand)]
+        action: ConfigCmd,
+    },
+
+    /// measures packet and bit error rate between two Radios
+    Linktest {
+        #[command(subcommand)]
+        action: LinktestCmd,
+    },
+
+    /// re-transmits the packets in a pcapng capture
+    Replay {
+        /// capture written by `capture::CaptureRadio`
+        file: PathBuf,
+
+        /// playback rate, 2 is twice as fast, 0 sends the packets back to back
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+
+        /// only replay packets that were recieved or transmitted
+        #[arg(long, value_enum)]
+        direction: Option<ReplayDirection>,
+
+        /// retune to each packet's recorded frequency and modulation
+        #[arg(long)]
+        retune: bool,
+    },
+
+    /// runs a KISS TNC so packet radio software (e.g. Direwolf clients, kissattach) can use the Radio
+    ///
+    /// listens on tcp 127.0.0.1:8001 unless --tcp or --pty is given
+    Kiss {
+        /// accept KISS clients on this address
+        #[arg(long)]
+        tcp: Option<String>,
+
+        /// serve KISS on a pseudo terminal, its path is printed on start
+        #[arg(long)]
+        pty: bool,
+    },
+
+    /// owns the Radio and shares it with several programs over json lines
+    ///
+    /// clients can subscribe to recieved packets, transmit and read settings,
+    /// changing settings needs --allow-config or --config-token, listens on
+    /// tcp 127.0.0.1:8002 unless --tcp or --unix is given
+    #[command(group(ArgGroup::new("access").args(["allow_config", "config_token"])))]
+    Daemon {
+        /// accept clients on this address
+        #[arg(long)]
+        tcp: Option<String>,
+
+        /// accept clients on a Unix socket at this path
+        #[arg(long)]
+        unix: Option<PathBuf>,
+
+        /// let every client change settings
+        #[arg(long)]
+        allow_config: bool,
+
+        /// let clients that send this token with an `auth` request change settings
+        #[arg(long)]
+        config_token: Option<String>,
+    },
+
+    /// forwards recieved packets to UDP destinations and transmits datagrams sent to a local port
+    Udp {
+        /// where recieved packets are sent, can be given more than once
+        #[arg(long, required = true)]
+        forward: Vec<String>,
+
+        /// local address datagrams to transmit are accepted on
+        #[arg(long, default_value = "127.0.0.1:9001")]
+        listen: String,
+
+        /// metadata sent with each packet
+        #[arg(long, value_enum, default_value_t = UdpFraming::Raw)]
+        metadata: UdpFraming,
+    },
+
+    /// turns the Radio into a wireless serial cable, exposed as a pseudo terminal
+    ///
+    /// bytes written to the terminal are sent in packets, recieved packets
+    /// are written back to it, run on both ends of the link
+    Uart {
+        /// longest a byte waits before a partial packet is sent, in ms
+        #[arg(long, default_value_t = 20)]
+        flush_ms: u64,
+
+        /// bytes per packet, a full packet is sent right away
+        #[arg(long, default_value_t = MAX_PACKET_LEN)]
+        max_size: usize,
+
+        /// also make the terminal available at this path, for software with a fixed port name
+        #[arg(long)]
+        link: Option<PathBuf>,
+    },
+
+    /// carries IP over the Radio through a TUN interface, run on both ends of the link
+    ///
+    /// packets above 255 bytes are fragmented, needs the `tun` feature and root
+    Tun {
+        /// interface name, `arm%d` picks the next free number
+        #[arg(long, default_value = "arm%d")]
+        name: String,
+
+        /// IPv4 address and prefix length to assign, e.g. 10.9.0.1/24
+        #[arg(long)]
+        address: Option<String>,
+
+        /// interface MTU, larger packets take more Radio packets
+        #[arg(long, default_value_t = 1280)]
+        mtu: usize,
+    },
+
+    /// resets the Radio chip or the whole board
+    #[command(group(ArgGroup::new("kind").required(true).args(["radio", "soft"])))]
+    Reset {
+        /// reset the onboard CC1200
+        #[arg(long)]
+        radio: bool,
+
+        /// soft reset the entire board
+        #[arg(long)]
+        soft: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCmd {
+    /// applies the given settings to the Radio
+    #[command(group(ArgGroup::new("settings").required(true).multiple(true)
+        .args(["frequency", "power", "deviation", "symbol_rate", "rx_filter", "modulation"])))]
+    Set {
+        /// carrier frequency (Hz)
+        #[arg(long)]
+        frequency: Option<f32>,
+
+        /// tx gain (dBm)
+        #[arg(long, allow_negative_numbers = true)]
+        power: Option<f32>,
+
+        /// FSK deviation (Hz)
+        #[arg(long)]
+        deviation: Option<f32>,
+
+        /// symbol rate (symbols/s)
+        #[arg(long)]
+        symbol_rate: Option<f32>,
+
+        /// recieve filter bandwidth (Hz)
+        #[arg(long)]
+        rx_filter: Option<f32>,
+
+        #[arg(long, value_enum)]
+        modulation: Option<Modulation>,
+    },
+
+    /// shows the settings last applied with `config set`
+    ///
+    /// the Radio can't report its settings, so these are read
+    /// from the local settings file rather than the device
+    Get,
+}
+
+#[derive(Subcommand)]
+enum LinktestCmd {
+    /// sends a numbered run of test packets
+    Send {
+        /// payload carried by every packet
+        #[arg(long, value_enum, default_value_t = TestPattern::Pn9)]
+        pattern: TestPattern,
+
+        /// number of packets to send
+        #[arg(long, short = 'n', default_value_t = 1000)]
+        count: u32,
+
+        /// payload bytes per packet, not counting the 12 byte header
+        #[arg(long, short, default_value_t = 64)]
+        length: usize,
+
+        /// time between packets (ms)
+        #[arg(long, default_value_t = 20)]
+        interval: u64,
+    },
+
+    /// recieves a test run and reports PER, BER and throughput
+    ///
+    /// the report is tagged with the settings last applied with `config set`
+    Recv {
+        /// end the test after this many seconds without a packet
+        #[arg(long, default_value_t = 5.0)]
+        idle_timeout: f64,
+
+        /// append the report to a file, as json lines if it ends in .json and csv otherwise
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// print the report as csv
+        #[arg(long, conflicts_with = "json")]
+        csv: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TestPattern {
+    Pn9,
+    Counter,
+}
+
+impl From<TestPattern> for Pattern {
+    fn from(pattern: TestPattern) -> Pattern {
+        match pattern {
+            TestPattern::Pn9 => Pattern::Pn9,
+            TestPattern::Counter => Pattern::Counter,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ReplayDirection {
+    Rx,
+    Tx,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum UdpFraming {
+    /// the payload only
+    Raw,
+    /// 20 bytes of binary metadata (timestamp, capture header) then the payload
+    Header,
+    /// a JSON object with the metadata and the payload in base64
+    Json,
+}
+
+impl From<UdpFraming> for Framing {
+    fn from(framing: UdpFraming) -> Framing {
+        match framing {
+            UdpFraming::Raw => Framing::Raw,
+            UdpFraming::Header => Framing::Header,
+            UdpFraming::Json => Framing::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Modulation {
+    Fsk2,
+    Gfsk2,
+    Ask,
+    Fsk4,
+    Gfsk4,
+}
+
+impl Modulation {
+    /// the name used on the command line
+    fn name(&self) -> String {
+        self.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default()
+    }
+}
+
+impl From<Modulation> for ModulationFormat {
+    fn from(mode: Modulation) -> ModulationFormat {
+        match mode {
+            Modulation::Fsk2 => ModulationFormat::FSK2,
+            Modulation::Gfsk2 => ModulationFormat::GFSK2,
+            Modulation::Ask => ModulationFormat::ASK,
+            Modulation::Fsk4 => ModulationFormat::FSK4,
+            Modulation::Gfsk4 => ModulationFormat::GFSK4,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(n) => {
+            eprintln!("error: {}", n);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    match &cli.command {
+        Cmd::List => list(&cli),
+        Cmd::Info => info(&cli),
+        Cmd::Send {message, file, encoding} => send(&cli, message.as_deref(), file.as_ref(), *encoding),
+        Cmd::Recv {count, timeout, interval} => recv(&cli, *count, *timeout, *interval),
+        Cmd::Config {action: ConfigCmd::Get} => config_get(&cli),
+        Cmd::Config {action: ConfigCmd::Set {frequency, power, deviation, symbol_rate, rx_filter, modulation}} => {
+            config_set(&cli, *frequency, *power, *deviation, *symbol_rate, *rx_filter, *modulation)
+        },
+        Cmd::Linktest {action: LinktestCmd::Send {pattern, count, length, interval}} => {
+            linktest_send(&cli, *pattern, *count, *length, *interval)
+        },
+        Cmd::Linktest {action: LinktestCmd::Recv {idle_timeout, output, csv}} => {
+            linktest_recv(&cli, *idle_timeout, output.as_deref(), *csv)
+        },
+        Cmd::Replay {file, speed, direction, retune} => replay(&cli, file, *speed, *direction, *retune),
+        Cmd::Kiss {tcp, pty} => kiss(&cli, tcp.as_deref(), *pty),
+        Cmd::Daemon {tcp, unix, allow_config, config_token} => {
+            daemon(&cli, tcp.as_deref(), unix.as_deref(), *allow_config, config_token.clone())
+        },
+        Cmd::Udp {forward, listen, metadata} => udp(&cli, forward, listen, *metadata),
+        Cmd::Uart {flush_ms, max_size, link} => uart(&cli, *flush_ms, *max_size, link.as_deref()),
+        Cmd::Tun {name, address, mtu} => tun(&cli, name, address.as_deref(), *mtu),
+        Cmd::Reset {radio: chip, ..} => {
+            let mut radio = open(&cli)?;
+
+            match *chip {
+                true => radio.radio_reset()?,
+                false => radio.soft_reset()?,
+            };
+
+            // either reset returns the Radio to its firmware defaults
+            let mut store = ConfigStore::load();
+            store.clear(&radio.target());
+            let _ = store.save();
+
+            match cli.json {
+                true => println!("{}", json!({"reset": if *chip {"radio"} else {"soft"}})),
+                false => println!("reset"),
+            };
+
+            Ok(())
+        },
+    }
+}
+
+fn target(cli: &Cli) -> Result<Target, String> {
+    Target::resolve(cli.port.clone(), cli.i2c_bus.clone(), cli.i2c_addr)
+}
+
+fn open(cli: &Cli) -> Result<Device, String> {
+    target(cli)?.open()
+}
+
+fn list(cli: &Cli) -> Result<(), String> {
+    let radios = device::list()?;
+
+    if cli.json {
+        let out: Vec<Value> = radios.iter().map(|target| json!({
+            "link": target.kind(),
+            "path": target.path(),
+            "address": target.address(),
+        })).collect();
+
+        println!("{}", Value::Array(out));
+        return Ok(());
+    }
+
+    if radios.is_empty() {
+        eprintln!("no Radios found");
+    }
+
+    for target in radios {
+        match target.address() {
+            Some(n) => println!("{:<8}{} 0x{:02X}", target.kind(), target.path(), n),
+            None => println!("{:<8}{}", target.kind(), target.path()),
+        };
+    }
+
+    return Ok(());
+}
+
+fn info(cli: &Cli) -> Result<(), String> {
+    let mut radio = open(cli)?;
+    let target = radio.target();
+    let available = radio.is_device_available();
+
+    if cli.json {
+        println!("{}", json!({
+            "link": target.kind(),
+            "path": target.path(),
+            "address": radio.address(),
+            "available": available,
+        }));
+        return Ok(());
+    }
+
+    println!("link      {}", target.kind());
+    println!("path      {}", target.path());
+    if let Some(n) = radio.address() {
+        println!("address   0x{:02X}", n);
+    }
+    println!("available {}", available);
+
+    return Ok(());
+}
+
+fn send(cli: &Cli, message: Option<&str>, file: Option<&PathBuf>, encoding: Encoding) -> Result<(), String> {
+    let data: Vec<u8> = match (message, file) {
+        (Some(msg), _) => encoding.decode(msg)?,
+        (None, Some(path)) if path.as_os_str() == "-" => {
+            let mut buf: Vec<u8> = vec![];
+            io::stdin().read_to_end(&mut buf).map_err(|e| format!("error reading stdin | {}", e))?;
+            buf
+        },
+        (None, Some(path)) => fs::read(path).map_err(|e| format!("error reading {} | {}", path.display(), e))?,
+        (None, None) => unreachable!("clap requires a message or --file"),
+    };
+
+    if data.is_empty() {
+        return Err("nothing to send".to_string());
+    }
+
+    let mut radio = open(cli)?;
+    let mut packets: usize = 0;
+
+    for chunk in data.chunks(MAX_PACKET_LEN) {
+        radio.transmit(chunk).map_err(|e| format!("error transmitting packet {} | {}", packets, e))?;
+        packets += 1;
+    }
+
+    match cli.json {
+        true => println!("{}", json!({"bytes": data.len(), "packets": packets})),
+        false => println!("sent {} bytes in {} packet(s)", data.len(), packets),
+    };
+
+    return Ok(());
+}
+
+fn recv(cli: &Cli, count: usize, timeout: Option<f64>, interval: u64) -> Result<(), String> {
+    let mut radio = open(cli)?;
+
+    let deadline = match timeout {
+        Some(n) if n.is_finite() && n >= 0.0 => Some(Instant::now() + Duration::from_secs_f64(n)),
+        Some(_) => {return Err("--timeout must be a positive number of seconds".to_string());},
+        None => None,
+    };
+
+    let mut stdout = io::stdout().lock();
+    let mut recieved: usize = 0;
+
+    while count == 0 || recieved < count {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            if count == 0 {
+                break;
+            }
+            return Err(format!("timed out after {} of {} packet(s)", recieved, count));
+        }
+
+        let packet = radio.get_packet()?;
+
+        if packet.is_empty() {
+            thread::sleep(Duration::from_millis(interval));
+            continue;
+        }
+
+        recieved += 1;
+
+        let res = match cli.json {
+            true => {
+                let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or(0.0);
+                let data = match cli.format {
+                    Format::Text => json!(String::from_utf8_lossy(&packet)),
+                    Format::Hex => json!(hex_encode(&packet)),
+                    Format::Base64 => json!(base64_encode(&packet)),
+                    Format::Raw => json!(packet),
+                };
+
+                writeln!(stdout, "{}", json!({"time": time, "len": packet.len(), "data": data}))
+            },
+            false => match cli.format {
+                Format::Text => writeln!(stdout, "{}", String::from_utf8_lossy(&packet)),
+                Format::Hex => writeln!(stdout, "{}", hex_encode(&packet)),
+                Format::Base64 => writeln!(stdout, "{}", base64_encode(&packet)),
+                Format::Raw => stdout.write_all(&packet),
+            },
+        };
+
+        // flush per packet so the output can be piped into other tools live
+        match res.and_then(|_| stdout.flush()) {
+            Ok(_) => {},
+            // stdout was closed (e.g. piped into head), stop quietly
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {return Ok(());},
+            Err(e) => {return Err(format!("error writing output | {}", e));},
+        };
+    }
+
+    return Ok(());
+}
+
+#[allow(clippy::too_many_arguments)]
+fn config_set(cli: &Cli, frequency: Option<f32>, power: Option<f32>, deviation: Option<f32>,
+    symbol_rate: Option<f32>, rx_filter: Option<f32>, modulation: Option<Modulation>) -> Result<(), String> {
+
+    let mut radio = open(cli)?;
+    let target = radio.target();
+    let mut applied: Vec<(&str, Value)> = vec![];
+
+    // stops at the first failure, anything applied before it is still recorded
+    let res = (|| {
+        if let Some(val) = frequency {
+            radio.set_frequency(val).map_err(|e| format!("error setting frequency | {}", e))?;
+            applied.push(("frequency", json!(val)));
+        }
+        if let Some(val) = power {
+            radio.set_power(val).map_err(|e| format!("error setting power | {}", e))?;
+            applied.push(("power", json!(val)));
+        }
+        if let Some(val) = deviation {
+            radio.set_deviation(val).map_err(|e| format!("error setting deviation | {}", e))?;
+            applied.push(("deviation", json!(val)));
+        }
+        if let Some(val) = symbol_rate {
+            radio.set_symbol_rate(val).map_err(|e| format!("error setting symbol_rate | {}", e))?;
+            applied.push(("symbol_rate", json!(val)));
+        }
+        if let Some(val) = rx_filter {
+            radio.set_rx_filter(val).map_err(|e| format!("error setting rx_filter | {}", e))?;
+            applied.push(("rx_filter", json!(val)));
+        }
+        if let Some(val) = modulation {
+            radio.set_modulation(val.into()).map_err(|e| format!("error setting modulation | {}", e))?;
+            applied.push(("modulation", json!(val.name())));
+        }
+
+        Ok::<(), String>(())
+    })();
+
+    let mut store = ConfigStore::load();
+    for (setting, value) in &applied {
+        store.set(&target, setting, value.clone());
+    }
+
+    if !applied.is_empty() {
+        // the Radio has already been configured, failing to remember it isn't fatal
+        if let Err(n) = store.save() {
+            eprintln!("warning: settings not saved | {}", n);
+        }
+    }
+
+    res?;
+
+    match cli.json {
+        true => println!("{}", json!({"device": target.to_string(), "settings": store.get(&target)})),
+        false => print_settings(&store.get(&target)),
+    };
+
+    return Ok(());
+}
+
+fn config_get(cli: &Cli) -> Result<(), String> {
+    let target = target(cli)?;
+    let settings = ConfigStore::load().get(&target);
+
+    if cli.json {
+        println!("{}", json!({"device": target.to_string(), "settings": settings}));
+        return Ok(());
+    }
+
+    if settings.is_empty() {
+        eprintln!("no settings recorded for {}, the Radio is using its firmware defaults", target);
+    }
+
+    print_settings(&settings);
+
+    return Ok(());
+}
+
+fn linktest_send(cli: &Cli, pattern: TestPattern, count: u32, length: usize, interval: u64) -> Result<(), String> {
+    let config = LinkTestConfig {
+        pattern: pattern.into(),
+        count,
+        payload_len: length,
+        interval: Duration::from_millis(interval),
+    };
+
+    let mut sender = match LinkTestSender::new(config) {
+        Ok(n) => n,
+        Err(_) => {return Err(format!("--length can be at most {}", MAX_PACKET_LEN - linktest::LINKTEST_HEADER_LEN));},
+    };
+
+    let mut radio = open(cli)?;
+    let start = Instant::now();
+
+    while sender.send_next(&mut radio).map_err(|e| format!("error transmitting packet {} | {}", sender.sent(), e))?.is_some() {
+        if !cli.json {
+            eprint!("\rsent {}/{}", sender.sent(), count);
+        }
+    }
+
+    let secs = start.elapsed().as_secs_f64();
+
+    match cli.json {
+        true => println!("{}", json!({"sent": sender.sent(), "duration_s": secs})),
+        false => eprintln!("\rsent {} packet(s) in {:.1} s", sender.sent(), secs),
+    };
+
+    return Ok(());
+}
+
+fn linktest_recv(cli: &Cli, idle_timeout: f64, output: Option<&Path>, csv: bool) -> Result<(), String> {
+    if !idle_timeout.is_finite() || idle_timeout <= 0.0 {
+        return Err("--idle-timeout must be a positive number of seconds".to_string());
+    }
+    let idle = Duration::from_secs_f64(idle_timeout);
+
+    let mut radio = open(cli)?;
+    let mut receiver = LinkTestReceiver::new();
+    let start = Instant::now();
+
+    if !cli.json {
+        eprintln!("waiting for link test packets, stops after {} s idle", idle_timeout);
+    }
+
+    // the test ends with its last packet, or once packets stop arriving
+    while !receiver.is_complete() && receiver.last_packet().unwrap_or(start).elapsed() < idle {
+        match receiver.poll(&mut radio)? {
+            Some(_) => {},
+            None => thread::sleep(Duration::from_millis(1)),
+        };
+    }
+
+    let report = receiver.report(ConfigStore::load().radio_config(&radio.target()));
+
+    if let Some(path) = output {
+        append_report(path, &report)?;
+    }
+
+    if cli.json {
+        println!("{}", report.to_json());
+    }
+    else if csv {
+        println!("{}\n{}", linktest::CSV_HEADER, report.to_csv());
+    }
+    else {
+        print_report(&report);
+    }
+
+    return Ok(());
+}
+
+/// appends `report` to a json lines file, or a csv file which gets a header when new
+fn append_report(path: &Path, report: &LinkTestReport) -> Result<(), String> {
+    let is_json = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let is_new = fs::metadata(path).map(|m| m.len() == 0).unwrap_or(true);
+
+    let line = match (is_json, is_new) {
+        (true, _) => report.to_json(),
+        (false, true) => format!("{}\n{}", linktest::CSV_HEADER, report.to_csv()),
+        (false, false) => report.to_csv(),
+    };
+
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)
+        .map_err(|e| format!("error opening {} | {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("error writing {} | {}", path.display(), e))?;
+
+    return Ok(());
+}
+
+fn print_report(report: &LinkTestReport) {
+    if report.pattern.is_none() {
+        println!("no link test packets recieved");
+        return;
+    }
+
+    println!("pattern    {} ({} byte payload)", report.pattern.map(|p| p.name()).unwrap_or_default(), report.payload_len);
+    println!("packets    {} sent, {} recieved, {} lost, {} duplicate, {} invalid",
+        report.sent, report.received, report.lost, report.duplicates, report.invalid);
+    println!("PER        {:.3}%", report.per * 100.0);
+    println!("BER        {:.2e} ({} of {} bits)", report.ber, report.bit_errors, report.bits);
+    match report.rssi {
+        Some(r) => println!("RSSI       {:.1} / {:.1} / {:.1} dBm (min / mean / max)", r.min, r.mean, r.max),
+        None => println!("RSSI       n/a"),
+    };
+    println!("throughput {:.0} bit/s over {:.1} s", report.throughput, report.duration.as_secs_f64());
+}
+
+fn replay(cli: &Cli, file: &Path, speed: f64, direction: Option<ReplayDirection>, retune: bool) -> Result<(), String> {
+    if !speed.is_finite() || speed < 0.0 {
+        return Err("--speed must be 0 or more".to_string());
+    }
+
+    let config = ReplayConfig {
+        speed,
+        direction: direction.map(|d| match d {
+            ReplayDirection::Rx => Direction::Rx,
+            ReplayDirection::Tx => Direction::Tx,
+        }),
+        retune,
+    };
+
+    let replayer = Replayer::open(file, config).map_err(|e| format!("error reading {} | {}", file.display(), e))?;
+    let total = replayer.records().len();
+
+    if total == 0 {
+        return Err(format!("no packets to replay in {}", file.display()));
+    }
+
+    let mut radio = open(cli)?;
+
+    if !cli.json {
+        eprintln!("replaying {} packet(s) over {:.1} s", total, replayer.duration().as_secs_f64());
+    }
+
+    let sent = replayer.transmit_with(&mut radio, |i, record| {
+        match cli.json {
+            true => println!("{}", json!({"index": i, "len": record.payload.len()})),
+            false => eprint!("\rsent {}/{}", i + 1, total),
+        };
+    }).map_err(|e| format!("error replaying packet | {}", e))?;
+
+    if !cli.json {
+        eprintln!("\rsent {} packet(s)", sent);
+    }
+
+    return Ok(());
+}
+
+fn kiss(cli: &Cli, tcp: Option<&str>, pty: bool) -> Result<(), String> {
+    let tcp = match (tcp, pty) {
+        (None, false) => Some("127.0.0.1:8001"),
+        (n, _) => n,
+    };
+
+    let server = KissServer::new(SharedRadio::new(open(cli)?));
+
+    if let Some(addr) = tcp {
+        let local = server.listen_tcp(addr).map_err(|e| format!("error listening on {} | {}", addr, e))?;
+
+        match cli.json {
+            true => println!("{}", json!({"kiss": "tcp", "address": local.to_string()})),
+            false => println!("KISS on tcp {}", local),
+        };
+    }
+
+    if pty {
+        let path = open_pty(&server)?;
+
+        match cli.json {
+            true => println!("{}", json!({"kiss": "pty", "path": path})),
+            false => println!("KISS on pty {}", path),
+        };
+    }
+
+    server.run();
+    return Ok(());
+}
+
+#[cfg(all(unix, feature = "serial"))]
+fn open_pty(server: &KissServer<Device>) -> Result<String, String> {
+    server.open_pty().map_err(|e| format!("error creating pseudo terminal | {}", e))
+}
+
+#[cfg(not(all(unix, feature = "serial")))]
+fn open_pty(_: &KissServer<Device>) -> Result<String, String> {
+    Err("--pty needs unix and the `serial` feature".to_string())
+}
+
+fn daemon(cli: &Cli, tcp: Option<&str>, unix: Option<&Path>, allow_config: bool, token: Option<String>) -> Result<(), String> {
+    let tcp = match (tcp, unix) {
+        (None, None) => Some("127.0.0.1:8002"),
+        (n, _) => n,
+    };
+
+    let access = match (allow_config, token) {
+        (true, _) => ConfigAccess::Anyone,
+        (false, Some(n)) => ConfigAccess::Token(n),
+        (false, None) => ConfigAccess::Nobody,
+    };
+
+    let radio = open(cli)?;
+    let target = radio.target();
+    let config = ConfigStore::load().radio_config(&target);
+
+    let mut daemon = Daemon::new(SharedRadio::new(radio), config, access);
+
+    // keep `config get` in step with what clients set
+    daemon.on_config_change(move |config| {
+        let mut store = ConfigStore::load();
+        for (setting, value) in config_json(config) {
+            store.set(&target, &setting, value);
+        }
+        let _ = store.save();
+    });
+
+    if let Some(addr) = tcp {
+        let local = daemon.listen_tcp(addr).map_err(|e| format!("error listening on {} | {}", addr, e))?;
+
+        match cli.json {
+            true => println!("{}", json!({"daemon": "tcp", "address": local.to_string()})),
+            false => println!("serving on tcp {}", local),
+        };
+    }
+
+    if let Some(path) = unix {
+        listen_unix(&daemon, path)?;
+
+        match cli.json {
+            true => println!("{}", json!({"daemon": "unix", "path": path.display().to_string()})),
+            false => println!("serving on {}", path.display()),
+        };
+    }
+
+    daemon.run();
+    return Ok(());
+}
+
+#[cfg(unix)]
+fn listen_unix(daemon: &Daemon<Device>, path: &Path) -> Result<(), String> {
+    daemon.listen_unix(path).map_err(|e| format!("error listening on {} | {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn listen_unix(_: &Daemon<Device>, _: &Path) -> Result<(), String> {
+    Err("--unix needs unix".to_string())
+}
+
+fn udp(cli: &Cli, forward: &[String], listen: &str, metadata: UdpFraming) -> Result<(), String> {
+    let mut destinations = vec![];
+    for addr in forward {
+        match addr.to_socket_addrs().ok().and_then(|mut n| n.next()) {
+            Some(n) => destinations.push(n),
+            None => {return Err(format!("invalid address {}", addr));},
+        };
+    }
+
+    let radio = open(cli)?;
+    let config = ConfigStore::load().radio_config(&radio.target());
+
+    let mut gateway = UdpGateway::bind(SharedRadio::new(radio), listen).map_err(|e| format!("error listening on {} | {}", listen, e))?;
+    gateway.set_framing(metadata.into());
+    gateway.set_config(config);
+
+    for addr in &destinations {
+        gateway.add_destination(*addr);
+    }
+
+    let local = gateway.local_addr().map_err(|e| e.to_string())?;
+    match cli.json {
+        true => println!("{}", json!({"udp": local.to_string(), "forward": destinations.iter().map(|n| n.to_string()).collect::<Vec<String>>()})),
+        false => println!("transmitting datagrams sent to {}, forwarding to {}", local, forward.join(", ")),
+    };
+
+    gateway.run();
+    return Ok(());
+}
+
+fn uart(cli: &Cli, flush_ms: u64, max_size: usize, link: Option<&Path>) -> Result<(), String> {
+    if !(1..=MAX_PACKET_LEN).contains(&max_size) {
+        return Err(format!("--max-size must be between 1 and {}", MAX_PACKET_LEN));
+    }
+
+    let config = UartConfig {
+        flush_interval: Duration::from_millis(flush_ms),
+        max_packet: max_size,
+    };
+
+    let uart = WirelessUart::new(SharedRadio::new(open(cli)?), config);
+    let path = open_uart_pty(&uart)?;
+
+    if let Some(link) = link {
+        link_pty(&path, link)?;
+    }
+
+    match cli.json {
+        true => println!("{}", json!({"uart": path, "link": link.map(|n| n.display().to_string())})),
+        false => println!("wireless serial on {}", link.map(|n| n.display().to_string()).unwrap_or(path)),
+    };
+
+    uart.run();
+    return Ok(());
+}
+
+#[cfg(all(unix, feature = "serial"))]
+fn open_uart_pty(uart: &WirelessUart<Device>) -> Result<String, String> {
+    uart.open_pty().map_err(|e| format!("error creating pseudo terminal | {}", e))
+}
+
+#[cfg(not(all(unix, feature = "serial")))]
+fn open_uart_pty(_: &WirelessUart<Device>) -> Result<String, String> {
+    Err("uart needs unix and the `serial` feature".to_string())
+}
+
+/// points `link` at the terminal, replacing a link left by an earlier run
+#[cfg(unix)]
+fn link_pty(path: &str, link: &Path) -> Result<(), String> {
+    if fs::symlink_metadata(link).is_ok_and(|n| n.file_type().is_symlink()) {
+        let _ = fs::remove_file(link);
+    }
+
+    std::os::unix::fs::symlink(path, link).map_err(|e| format!("error linking {} | {}", link.display(), e))
+}
+
+#[cfg(not(unix))]
+fn link_pty(_: &str, _: &Path) -> Result<(), String> {
+    Err("--link needs unix".to_string())
+}
+
+#[cfg(all(feature = "tun", target_os = "linux"))]
+fn tun(cli: &Cli, name: &str, address: Option<&str>, mtu: usize) -> Result<(), String> {
+    use std::net::Ipv4Addr;
+    use ArmlabRadio::tun::{TunBridge, TunDevice, MAX_MTU};
+
+    if !(68..=MAX_MTU).contains(&mtu) {
+        return Err(format!("--mtu must be between 68 and {}", MAX_MTU));
+    }
+
+    let address = match address {
+        Some(n) => {
+            let parsed = n.split_once('/')
+                .and_then(|(ip, prefix)| Some((ip.parse::<Ipv4Addr>().ok()?, prefix.parse::<u8>().ok()?)))
+                .filter(|(_, prefix)| *prefix <= 32);
+
+            match parsed {
+                Some(n) => Some(n),
+                None => {return Err(format!("invalid address {}, expected e.g. 10.9.0.1/24", n));},
+            }
+        },
+        None => None,
+    };
+
+    let radio = open(cli)?;
+
+    let device = TunDevice::create(name).map_err(|e| format!("error creating {} | {}", name, e))?;
+    device.set_mtu(mtu).map_err(|e| format!("error setting MTU | {}", e))?;
+
+    if let Some((ip, prefix)) = address {
+        device.set_address(ip, prefix).map_err(|e| format!("error setting address | {}", e))?;
+    }
+
+    device.up().map_err(|e| format!("error bringing up {} | {}", device.name(), e))?;
+
+    match cli.json {
+        true => println!("{}", json!({"tun": device.name(), "address": address.map(|(ip, prefix)| format!("{}/{}", ip, prefix)), "mtu": mtu})),
+        false => println!("forwarding IP on {} (mtu {})", device.name(), mtu),
+    };
+
+    let ifname = device.name().to_string();
+    let bridge = TunBridge::new(SharedRadio::new(radio));
+    return bridge.run(device).map_err(|e| format!("error reading {} | {}", ifname, e));
+}
+
+#[cfg(not(all(feature = "tun", target_os = "linux")))]
+fn tun(_: &Cli, _: &str, _: Option<&str>, _: usize) -> Result<(), String> {
+    Err("tun needs linux and the `tun` feature".to_string())
+}
+
+fn print_settings(settings: &Map<String, Value>) {
+    for setting in SETTINGS {
+        match settings.get(setting) {
+            Some(Value::String(s)) => println!("{} = {}", setting, s),
+            Some(value) => println!("{} = {}", setting, value),
+            None => {},
+        };
+    }
+}
@@ -0,0 +1,659 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::protocol::ModulationFormat;
+use crate::transceiver::{RadioConfig, Transceiver};
+
+/// LINKTYPE_USER0, wireshark needs a dissector mapped to this DLT to decode the packets
+pub const LINKTYPE: u16 = 147;
+
+/// length of the header placed in front of every captured payload
+///
+/// | offset | size | field |
+/// |--------|------|-------|
+/// | 0 | 1 | version (1) |
+/// | 1 | 1 | direction, 0 rx / 1 tx |
+/// | 2 | 1 | flags, which of the fields below are valid (`FLAG_*`) |
+/// | 3 | 1 | modulation (`ModulationFormat` as u8) |
+/// | 4 | 4 | frequency, f32 LE (Hz) |
+/// | 8 | 1 | rssi, i8 (dBm) |
+/// | 9 | 1 | lqi, u8 |
+/// | 10 | 2 | reserved |
+pub const CAPTURE_HEADER_LEN: usize = 12;
+
+pub const CAPTURE_VERSION: u8 = 1;
+
+pub const FLAG_FREQUENCY: u8 = 0x01;
+pub const FLAG_MODULATION: u8 = 0x02;
+pub const FLAG_RSSI: u8 = 0x04;
+pub const FLAG_LQI: u8 = 0x08;
+
+const BLOCK_SHB: u32 = 0x0A0D0D0A;
+const BLOCK_IDB: u32 = 0x00000001;
+const BLOCK_EPB: u32 = 0x00000006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B3C4D;
+
+const OPT_END: u16 = 0;
+const OPT_EPB_FLAGS: u16 = 2;
+const OPT_IF_TSRESOL: u16 = 9;
+
+/// largest block the reader accepts, well above anything a Radio can produce
+const MAX_BLOCK_LEN: usize = 1 << 20;
+
+/// which way a captured packet went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Rx = 0,
+    Tx = 1,
+}
+
+/// a single captured packet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureRecord {
+    pub direction: Direction,
+    /// time since the unix epoch
+    pub timestamp: Duration,
+    pub payload: Vec<u8>,
+    /// None when the firmware doesn't report it, which is currently always
+    pub rssi: Option<i8>,
+    pub lqi: Option<u8>,
+    pub frequency: Option<f32>,
+    pub modulation: Option<ModulationFormat>,
+}
+
+impl CaptureRecord {
+    /// a record stamped with the current time and the given Radio settings
+    pub fn now(direction: Direction, payload: &[u8], config: &RadioConfig) -> CaptureRecord {
+        CaptureRecord {
+            direction,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default(),
+            payload: payload.to_vec(),
+            rssi: None,
+            lqi: None,
+            frequency: config.frequency,
+            modulation: config.modulation,
+        }
+    }
+
+    /// the captured packet data, `CAPTURE_HEADER_LEN` bytes of header followed by the payload
+    pub fn encode(&self) -> Vec<u8> {
+        let mut flags: u8 = 0;
+        let mut out: Vec<u8> = vec![0; CAPTURE_HEADER_LEN];
+
+        out[0] = CAPTURE_VERSION;
+        out[1] = self.direction as u8;
+
+        if let Some(mode) = self.modulation {
+            flags |= FLAG_MODULATION;
+            out[3] = mode as u8;
+        }
+        if let Some(freq) = self.frequency {
+            flags |= FLAG_FREQUENCY;
+            out[4..8].copy_from_slice(&freq.to_le_bytes());
+        }
+        if let Some(rssi) = self.rssi {
+            flags |= FLAG_RSSI;
+            out[8] = rssi as u8;
+        }
+        if let Some(lqi) = self.lqi {
+            flags |= FLAG_LQI;
+            out[9] = lqi;
+        }
+
+        out[2] = flags;
+        out.extend_from_slice(&self.payload);
+
+        return out;
+    }
+
+    /// parses captured packet data written by `encode`
+    ///
+    /// ## Returns
+    /// None if the header is missing or from an unknown version
+    pub fn decode(data: &[u8], timestamp: Duration) -> Option<CaptureRecord> {
+        if data.len() < CAPTURE_HEADER_LEN || data[0] != CAPTURE_VERSION {
+            return None;
+        }
+
+        let flags = data[2];
+        let has = |flag: u8| flags & flag != 0;
+
+        Some(CaptureRecord {
+            direction: match data[1] {
+                0 => Direction::Rx,
+                1 => Direction::Tx,
+                _ => {return None;},
+            },
+            timestamp,
+            payload: data[CAPTURE_HEADER_LEN..].to_vec(),
+            rssi: has(FLAG_RSSI).then_some(data[8] as i8),
+            lqi: has(FLAG_LQI).then_some(data[9]),
+            frequency: has(FLAG_FREQUENCY).then(|| f32::from_le_bytes([data[4], data[5], data[6], data[7]])),
+            modulation: match has(FLAG_MODULATION) {
+                true => ModulationFormat::try_from(data[3]).ok(),
+                false => None,
+            },
+        })
+    }
+}
+
+/// pads a block body to a multiple of 4 bytes as pcapng requires
+fn pad(buf: &mut Vec<u8>) {
+    while !buf.len().is_multiple_of(4) {
+        buf.push(0);
+    }
+}
+
+/// writes a block, `body` is everything between the two length fields
+fn write_block<W: Write>(out: &mut W, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let len = (body.len() + 12) as u32;
+
+    out.write_all(&block_type.to_le_bytes())?;
+    out.write_all(&len.to_le_bytes())?;
+    out.write_all(body)?;
+    out.write_all(&len.to_le_bytes())?;
+
+    return Ok(());
+}
+
+/// writes captured packets to a pcapng file with a single `LINKTYPE` interface
+pub struct PcapngWriter<W: Write> {
+    out: W,
+}
+
+impl PcapngWriter<BufWriter<File>> {
+    /// creates (or truncates) a capture file at `path`
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        PcapngWriter::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// writes the section and interface headers to `out`
+    pub fn new(mut out: W) -> io::Result<Self> {
+        // section header, version 1.0, unknown section length
+        let mut shb: Vec<u8> = vec![];
+        shb.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        shb.extend_from_slice(&1u16.to_le_bytes());
+        shb.extend_from_slice(&0u16.to_le_bytes());
+        shb.extend_from_slice(&(-1i64).to_le_bytes());
+        write_block(&mut out, BLOCK_SHB, &shb)?;
+
+        // interface description, no snap length limit, default microsecond timestamps
+        let mut idb: Vec<u8> = vec![];
+        idb.extend_from_slice(&LINKTYPE.to_le_bytes());
+        idb.extend_from_slice(&0u16.to_le_bytes());
+        idb.extend_from_slice(&0u32.to_le_bytes());
+        write_block(&mut out, BLOCK_IDB, &idb)?;
+
+        Ok(PcapngWriter { out })
+    }
+
+    /// appends a packet to the capture
+    pub fn write_record(&mut self, record: &CaptureRecord) -> io::Result<()> {
+        let data = record.encode();
+        let micros = record.timestamp.as_micros() as u64;
+
+        let mut epb: Vec<u8> = vec![];
+        epb.extend_from_slice(&0u32.to_le_bytes());
+        epb.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(micros as u32).to_le_bytes());
+        epb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&data);
+        pad(&mut epb);
+
+        // epb_flags, bits 0-1 are the direction: 1 inbound, 2 outbound
+        let flags: u32 = match record.direction {
+            Direction::Rx => 1,
+            Direction::Tx => 2,
+        };
+        epb.extend_from_slice(&OPT_EPB_FLAGS.to_le_bytes());
+        epb.extend_from_slice(&4u16.to_le_bytes());
+        epb.extend_from_slice(&flags.to_le_bytes());
+        epb.extend_from_slice(&OPT_END.to_le_bytes());
+        epb.extend_from_slice(&0u16.to_le_bytes());
+
+        return write_block(&mut self.out, BLOCK_EPB, &epb);
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// the underlying writer
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+/// reads the packets back out of a capture written by `PcapngWriter`
+///
+/// only little endian files are supported, packets on interfaces other
+/// than `LINKTYPE` and blocks other than enhanced packets are skipped
+pub struct CaptureReader<R: Read> {
+    input: R,
+    /// (link type, timestamp units per second) of each interface in the section
+    interfaces: Vec<(u16, u64)>,
+}
+
+impl CaptureReader<BufReader<File>> {
+    /// opens the capture file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        CaptureReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// checks that `input` starts with a pcapng section header
+    pub fn new(input: R) -> io::Result<Self> {
+        let mut reader = CaptureReader { input, interfaces: vec![] };
+
+        match reader.read_block() {
+            Ok(Some((BLOCK_SHB, _))) => {},
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => {return Err(e);},
+            _ => {return Err(invalid("not a pcapng file"));},
+        };
+
+        return Ok(reader);
+    }
+
+    /// reads the next block
+    ///
+    /// ## Returns
+    /// (block type, body), None at the end of the file
+    fn read_block(&mut self) -> io::Result<Option<(u32, Vec<u8>)>> {
+        let mut head = [0u8; 8];
+
+        // a clean end of file can only happen between blocks
+        match self.input.read(&mut head[..1])? {
+            0 => {return Ok(None);},
+            _ => self.input.read_exact(&mut head[1..])?,
+        };
+
+        let block_type = u32_at(&head, 0);
+        let len = u32_at(&head, 4) as usize;
+
+        if len < 12 || !len.is_multiple_of(4) || len > MAX_BLOCK_LEN {
+            return Err(invalid("bad block length"));
+        }
+
+        let mut body = vec![0u8; len - 8];
+        self.input.read_exact(&mut body)?;
+        body.truncate(len - 12);
+
+        if block_type == BLOCK_SHB {
+            if body.len() < 16 || u32_at(&body, 0) != BYTE_ORDER_MAGIC {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "only little endian pcapng files are supported"));
+            }
+
+            // interfaces are numbered per section
+            self.interfaces.clear();
+        }
+
+        return Ok(Some((block_type, body)));
+    }
+
+    /// reads the next packet
+    ///
+    /// ## Returns
+    /// None at the end of the capture
+    pub fn read_record(&mut self) -> io::Result<Option<CaptureRecord>> {
+        loop {
+            let (block_type, body) = match self.read_block()? {
+                Some(n) => n,
+                None => {return Ok(None);},
+            };
+
+            match block_type {
+                BLOCK_IDB if body.len() >= 8 => {
+                    self.interfaces.push((u16_at(&body, 0), interface_resolution(&body[8..])));
+                },
+                BLOCK_EPB if body.len() >= 20 => {
+                    let (link, units) = match self.interfaces.get(u32_at(&body, 0) as usize) {
+                        Some(n) => *n,
+                        None => {return Err(invalid("packet on an undeclared interface"));},
+                    };
+
+                    let captured = u32_at(&body, 12) as usize;
+                    if link != LINKTYPE || body.len() < 20 + captured {
+                        continue;
+                    }
+
+                    let ts = ((u32_at(&body, 4) as u64) << 32) | u32_at(&body, 8) as u64;
+                    let timestamp = Duration::from_secs(ts / units)
+                        + Duration::from_nanos(((ts % units) as u128 * 1_000_000_000 / units as u128) as u64);
+
+                    match CaptureRecord::decode(&body[20..20 + captured], timestamp) {
+                        Some(n) => {return Ok(Some(n));},
+                        None => {continue;},
+                    };
+                },
+                _ => {},
+            };
+        }
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = io::Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// timestamp units per second from an interface's `if_tsresol` option, microseconds if absent
+fn interface_resolution(mut options: &[u8]) -> u64 {
+    while options.len() >= 4 {
+        let code = u16_at(options, 0);
+        let len = u16_at(options, 2) as usize;
+        let padded = (len + 3) & !3;
+
+        if code == OPT_END || options.len() < 4 + padded {
+            break;
+        }
+
+        if code == OPT_IF_TSRESOL && len >= 1 {
+            let res = options[4];
+
+            // msb set means a power of 2, otherwise a power of 10
+            return match res & 0x80 {
+                0 => 10u64.checked_pow(res as u32).unwrap_or(1_000_000),
+                _ => 1u64.checked_shl((res & 0x7F) as u32).unwrap_or(1_000_000),
+            };
+        }
+
+        options = &options[4 + padded..];
+    }
+
+    return 1_000_000;
+}
+
+/// wraps a Radio, copying every transmitted and recieved packet into a capture
+///
+/// the Radio can't report its settings, so the frequency and modulation
+/// recorded are the ones last set through this wrapper
+///
+/// a failed capture write never fails the Radio call, capturing stops and
+/// the error is kept for `capture_error`
+pub struct CaptureRadio<R, W: Write> {
+    radio: R,
+    writer: Option<PcapngWriter<W>>,
+    config: RadioConfig,
+    error: Option<io::Error>,
+}
+
+impl<R: Transceiver, W: Write> CaptureRadio<R, W> {
+    /// captures into `writer`, `config` is what the Radio is already set to, if known
+    pub fn new(radio: R, writer: PcapngWriter<W>, config: RadioConfig) -> CaptureRadio<R, W> {
+        CaptureRadio { radio, writer: Some(writer), config, error: None }
+    }
+
+    /// the settings recorded with each packet
+    pub fn config(&self) -> &RadioConfig {
+        &self.config
+    }
+
+    /// the error that stopped capturing, if any
+    pub fn capture_error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.radio
+    }
+
+    /// direct access to the Radio, settings changed through here aren't recorded
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.radio
+    }
+
+    /// stops capturing, returning the Radio and the flushed writer
+    pub fn into_inner(mut self) -> (R, Option<PcapngWriter<W>>) {
+        if let Some(writer) = self.writer.as_mut() {
+            let _ = writer.flush();
+        }
+
+        return (self.radio, self.writer);
+    }
+
+    /// records a packet, flushing so a crash loses at most the packet in flight
+    fn record(&mut self, direction: Direction, payload: &[u8]) {
+        let writer = match self.writer.as_mut() {
+            Some(n) => n,
+            None => {return;},
+        };
+
+        let record = CaptureRecord::now(direction, payload, &self.config);
+
+        match writer.write_record(&record).and_then(|_| writer.flush()) {
+            Ok(_) => {},
+            Err(e) => {
+                self.writer = None;
+                self.error = Some(e);
+            },
+        };
+    }
+}
+
+impl<R: Transceiver, W: Write> Transceiver for CaptureRadio<R, W> {
+    type Error = R::Error;
+
+    fn transmit(&mut self, msg: &[u8]) -> Result<(), R::Error> {
+        self.radio.transmit(msg)?;
+        self.record(Direction::Tx, msg);
+
+        return Ok(());
+    }
+
+    fn get_packet(&mut self) -> Result<Vec<u8>, R::Error> {
+        let packet = self.radio.get_packet()?;
+
+        if !packet.is_empty() {
+            self.record(Direction::Rx, &packet);
+        }
+
+        return Ok(packet);
+    }
+
+    fn set_frequency(&mut self, frequency: f32) -> Result<(), R::Error> {
+        self.radio.set_frequency(frequency)?;
+        self.config.frequency = Some(frequency);
+        return Ok(());
+    }
+
+    fn set_power(&mut self, power: f32) -> Result<(), R::Error> {
+        self.radio.set_power(power)?;
+        self.config.power = Some(power);
+        return Ok(());
+    }
+
+    fn set_deviation(&mut self, deviation: f32) -> Result<(), R::Error> {
+        self.radio.set_deviation(deviation)?;
+        self.config.deviation = Some(deviation);
+        return Ok(());
+    }
+
+    fn set_symbol_rate(&mut self, symbol_rate: f32) -> Result<(), R::Error> {
+        self.radio.set_symbol_rate(symbol_rate)?;
+        self.config.symbol_rate = Some(symbol_rate);
+        return Ok(());
+    }
+
+    fn set_rx_filter(&mut self, rx_filter: f32) -> Result<(), R::Error> {
+        self.radio.set_rx_filter(rx_filter)?;
+        self.config.rx_filter = Some(rx_filter);
+        return Ok(());
+    }
+
+    fn set_modulation(&mut self, mode: ModulationFormat) -> Result<(), R::Error> {
+        self.radio.set_modulation(mode)?;
+        self.config.modulation = Some(mode);
+        return Ok(());
+    }
+
+    /// the Radio returns to its firmware defaults, which aren't known here
+    fn radio_reset(&mut self) -> Result<(), R::Error> {
+        self.radio.radio_reset()?;
+        self.config = RadioConfig::default();
+        return Ok(());
+    }
+
+    fn soft_reset(&mut self) -> Result<(), R::Error> {
+        self.radio.soft_reset()?;
+        self.config = RadioConfig::default();
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockRadio;
+
+    fn record(direction: Direction, payload: &[u8], micros: u64) -> CaptureRecord {
+        CaptureRecord {
+            direction,
+            timestamp: Duration::from_micros(micros),
+            payload: payload.to_vec(),
+            rssi: Some(-90),
+            lqi: Some(40),
+            frequency: Some(915e6),
+            modulation: Some(ModulationFormat::FSK2),
+        }
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let full = record(Direction::Tx, b"payload", 0);
+        assert_eq!(CaptureRecord::decode(&full.encode(), Duration::ZERO), Some(full));
+
+        let bare = CaptureRecord { rssi: None, lqi: None, frequency: None, modulation: None, ..record(Direction::Rx, &[], 0) };
+        let encoded = bare.encode();
+        assert_eq!(encoded.len(), CAPTURE_HEADER_LEN);
+        assert_eq!(CaptureRecord::decode(&encoded, Duration::ZERO), Some(bare));
+    }
+
+    #[test]
+    fn malformed_records() {
+        let encoded = record(Direction::Rx, b"x", 0).encode();
+
+        assert_eq!(CaptureRecord::decode(&encoded[..CAPTURE_HEADER_LEN - 1], Duration::ZERO), None);
+
+        let mut version = encoded.clone();
+        version[0] = 2;
+        assert_eq!(CaptureRecord::decode(&version, Duration::ZERO), None);
+
+        let mut direction = encoded.clone();
+        direction[1] = 2;
+        assert_eq!(CaptureRecord::decode(&direction, Duration::ZERO), None);
+
+        // an unknown modulation is dropped, the rest of the record is kept
+        let mut modulation = encoded.clone();
+        modulation[3] = 0x2;
+        assert_eq!(CaptureRecord::decode(&modulation, Duration::ZERO).unwrap().modulation, None);
+    }
+
+    #[test]
+    fn pcapng_round_trip() {
+        let records = vec![
+            record(Direction::Rx, b"first", 1_700_000_000_123_456),
+            record(Direction::Tx, &[0; 255], 1_700_000_001_000_000),
+            record(Direction::Rx, b"", 0),
+        ];
+
+        let mut writer = PcapngWriter::new(vec![]).unwrap();
+        for n in &records {
+            writer.write_record(n).unwrap();
+        }
+        let file = writer.into_inner();
+
+        assert_eq!(file.len() % 4, 0);
+
+        let read: Vec<CaptureRecord> = CaptureReader::new(file.as_slice()).unwrap().map(|n| n.unwrap()).collect();
+        assert_eq!(read, records);
+    }
+
+    #[test]
+    fn capture_radio() {
+        let mut radio = MockRadio::new();
+        radio.push_rx(b"in");
+
+        let writer = PcapngWriter::new(vec![]).unwrap();
+        let mut capture = CaptureRadio::new(radio, writer, RadioConfig::default());
+        capture.set_frequency(433e6).unwrap();
+        capture.transmit(b"out").unwrap();
+        assert_eq!(capture.get_packet().unwrap(), b"in");
+        // nothing waiting isn't captured
+        assert!(capture.get_packet().unwrap().is_empty());
+
+        let (_, writer) = capture.into_inner();
+        let file = writer.unwrap().into_inner();
+        let read: Vec<CaptureRecord> = CaptureReader::new(file.as_slice()).unwrap().map(|n| n.unwrap()).collect();
+
+        assert_eq!(read.len(), 2);
+        assert_eq!((read[0].direction, read[0].payload.as_slice()), (Direction::Tx, b"out".as_slice()));
+        assert_eq!((read[1].direction, read[1].payload.as_slice()), (Direction::Rx, b"in".as_slice()));
+        assert_eq!(read[1].frequency, Some(433e6));
+    }
+
+    #[test]
+    fn malformed_files() {
+        let mut file = PcapngWriter::new(vec![]).unwrap().into_inner();
+
+        assert_eq!(CaptureReader::new(&b"not a capture"[..]).err().unwrap().kind(), io::ErrorKind::InvalidData);
+        assert!(CaptureReader::new(&[][..]).is_err());
+
+        let mut big_endian = file.clone();
+        big_endian[8..12].copy_from_slice(&BYTE_ORDER_MAGIC.to_be_bytes());
+        assert_eq!(CaptureReader::new(big_endian.as_slice()).err().unwrap().kind(), io::ErrorKind::Unsupported);
+
+        // a packet on an interface that was never declared
+        let mut epb = vec![0u8; 20];
+        epb[0] = 5;
+        let mut undeclared = file.clone();
+        write_block(&mut undeclared, BLOCK_EPB, &epb).unwrap();
+        assert!(CaptureReader::new(undeclared.as_slice()).unwrap().read_record().is_err());
+
+        // a block length that isn't a multiple of 4, then a truncated block
+        let mut bad_len = file.clone();
+        bad_len.extend_from_slice(&BLOCK_EPB.to_le_bytes());
+        bad_len.extend_from_slice(&13u32.to_le_bytes());
+        assert!(CaptureReader::new(bad_len.as_slice()).unwrap().read_record().is_err());
+
+        let mut writer = PcapngWriter::new(vec![]).unwrap();
+        writer.write_record(&record(Direction::Rx, b"cut", 0)).unwrap();
+        let truncated = writer.into_inner();
+        assert!(CaptureReader::new(&truncated[..truncated.len() - 3]).unwrap().read_record().is_err());
+
+        // captured data claiming to be longer than its block is skipped
+        let mut epb = vec![0u8; 20];
+        epb[12] = 200;
+        write_block(&mut file, BLOCK_EPB, &epb).unwrap();
+        assert!(CaptureReader::new(file.as_slice()).unwrap().read_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn timestamp_resolution() {
+        assert_eq!(interface_resolution(&[]), 1_000_000);
+        assert_eq!(interface_resolution(&[9, 0, 1, 0, 9, 0, 0, 0]), 1_000_000_000);
+        assert_eq!(interface_resolution(&[9, 0, 1, 0, 0x8A, 0, 0, 0]), 1024);
+        // an option running past the end is ignored
+        assert_eq!(interface_resolution(&[9, 0, 8, 0, 9]), 1_000_000);
+    }
+}
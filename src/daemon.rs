@@ -0,0 +1,774 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+#[cfg(unix)]
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+
+use crate::encoding::{base64_decode, base64_encode, hex_decode, hex_encode};
+use crate::kiss::parse_set_hardware;
+use crate::protocol::{ModulationFormat, MAX_PACKET_LEN};
+use crate::shared::SharedRadio;
+use crate::transceiver::{RadioConfig, Transceiver};
+
+/// longest request line accepted, clients sending more are disconnected
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// how long a write to a client may block before the client is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// who may change Radio settings through a `Daemon`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigAccess {
+    /// every client
+    Anyone,
+    /// clients that sent `{"op": "auth", "token": ...}` with this token
+    Token(String),
+    /// no client, settings can only be read
+    Nobody,
+}
+
+/// how payloads are written in requests and events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadEncoding {
+    Base64,
+    Hex,
+    /// UTF-8, recieved packets that aren't valid UTF-8 are converted lossily
+    Text,
+}
+
+impl PayloadEncoding {
+    fn parse(name: Option<&Value>) -> Result<PayloadEncoding, String> {
+        match name.map(|v| v.as_str()) {
+            None | Some(Some("base64")) => Ok(PayloadEncoding::Base64),
+            Some(Some("hex")) => Ok(PayloadEncoding::Hex),
+            Some(Some("text")) => Ok(PayloadEncoding::Text),
+            _ => Err("encoding must be base64, hex or text".to_string()),
+        }
+    }
+
+    fn encode(&self, data: &[u8]) -> String {
+        match self {
+            PayloadEncoding::Base64 => base64_encode(data),
+            PayloadEncoding::Hex => hex_encode(data),
+            PayloadEncoding::Text => String::from_utf8_lossy(data).to_string(),
+        }
+    }
+
+    fn decode(&self, data: &str) -> Result<Vec<u8>, String> {
+        match self {
+            PayloadEncoding::Base64 => base64_decode(data),
+            PayloadEncoding::Hex => hex_decode(data),
+            PayloadEncoding::Text => Ok(data.as_bytes().to_vec()),
+        }
+    }
+}
+
+/// counters for a running `Daemon`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DaemonStats {
+    /// packets transmitted for clients
+    pub transmitted: usize,
+    /// packets recieved by the Radio
+    pub received: usize,
+    /// transmissions that failed, too long or a Radio error
+    pub tx_errors: usize,
+    /// failed polls of the Radio
+    pub rx_errors: usize,
+    /// config changes refused by the `ConfigAccess`
+    pub denied: usize,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(n) => n,
+        Err(n) => n.into_inner(),
+    }
+}
+
+fn modulation_name(mode: ModulationFormat) -> &'static str {
+    match mode {
+        ModulationFormat::FSK2 => "fsk2",
+        ModulationFormat::GFSK2 => "gfsk2",
+        ModulationFormat::ASK => "ask",
+        ModulationFormat::FSK4 => "fsk4",
+        ModulationFormat::GFSK4 => "gfsk4",
+    }
+}
+
+/// the settings that are Some, as a json object
+pub fn config_json(config: &RadioConfig) -> Map<String, Value> {
+    let mut map = Map::new();
+
+    let nums = [
+        ("frequency", config.frequency),
+        ("power", config.power),
+        ("deviation", config.deviation),
+        ("symbol_rate", config.symbol_rate),
+        ("rx_filter", config.rx_filter),
+    ];
+
+    for (name, val) in nums {
+        if let Some(val) = val {
+            map.insert(name.to_string(), json!(val));
+        }
+    }
+
+    if let Some(mode) = config.modulation {
+        map.insert("modulation".to_string(), json!(modulation_name(mode)));
+    }
+
+    return map;
+}
+
+/// copies the settings that are Some in `changes` into `config`
+fn merge(config: &mut RadioConfig, changes: &RadioConfig) {
+    config.frequency = changes.frequency.or(config.frequency);
+    config.power = changes.power.or(config.power);
+    config.deviation = changes.deviation.or(config.deviation);
+    config.symbol_rate = changes.symbol_rate.or(config.symbol_rate);
+    config.rx_filter = changes.rx_filter.or(config.rx_filter);
+    config.modulation = changes.modulation.or(config.modulation);
+}
+
+/// sends the settings in `changes` one at a time, in the order `RadioConfig::apply` does
+///
+/// ## Returns
+/// the settings the Radio took, and the error that stopped the rest
+fn apply_each<T: Transceiver + ?Sized>(changes: &RadioConfig, radio: &mut T) -> (RadioConfig, Result<(), T::Error>) {
+    let none = RadioConfig::default();
+    let settings = [
+        RadioConfig { frequency: changes.frequency, ..none },
+        RadioConfig { power: changes.power, ..none },
+        RadioConfig { deviation: changes.deviation, ..none },
+        RadioConfig { symbol_rate: changes.symbol_rate, ..none },
+        RadioConfig { rx_filter: changes.rx_filter, ..none },
+        RadioConfig { modulation: changes.modulation, ..none },
+    ];
+
+    let mut applied = none;
+    for setting in settings {
+        if let Err(e) = setting.apply(radio) {
+            return (applied, Err(e));
+        }
+
+        merge(&mut applied, &setting);
+    }
+
+    return (applied, Ok(()));
+}
+
+struct Client {
+    id: usize,
+    writer: Box<dyn Write + Send>,
+    /// how recieved packets are sent, None when not subscribed
+    subscribed: Option<PayloadEncoding>,
+    authorized: bool,
+}
+
+type ConfigHook = Arc<dyn Fn(&RadioConfig) + Send + Sync>;
+
+/// owns a Radio and shares it with any number of clients
+///
+/// clients connect over TCP (`listen_tcp`), a Unix socket (`listen_unix`)
+/// or any stream (`attach`) and talk JSON lines, one request per line and
+/// one response line per request, carrying the request's `id` if it had one
+///
+/// | request | response |
+/// |---------|----------|
+/// | `{"op": "subscribe", "encoding": "hex"}` | `{"ok": true}`, then a `{"event": "packet", "timestamp": 1700000000.5, "payload": "..."}` line per recieved packet |
+/// | `{"op": "unsubscribe"}` | `{"ok": true}` |
+/// | `{"op": "transmit", "payload": "aGk=", "encoding": "base64"}` | `{"ok": true}` |
+/// | `{"op": "config"}` | `{"ok": true, "config": {"frequency": 915000000.0, ...}}` |
+/// | `{"op": "set", "frequency": 915e6, "modulation": "gfsk2"}` | `{"ok": true, "config": {...}}` |
+/// | `{"op": "auth", "token": "..."}` | `{"ok": true}` |
+/// | `{"op": "stats"}` | `{"ok": true, "stats": {...}}` |
+///
+/// failures are `{"ok": false, "error": "..."}`, `encoding` is base64,
+/// hex or text and defaults to base64, `set` takes any of frequency,
+/// power, deviation, symbol_rate, rx_filter and modulation and is subject
+/// to the `ConfigAccess`
+pub struct Daemon<T> {
+    radio: SharedRadio<T>,
+    clients: Arc<Mutex<Vec<Client>>>,
+    next_id: Arc<AtomicUsize>,
+    access: Arc<ConfigAccess>,
+    /// settings last applied, the Radio can't report them
+    config: Arc<Mutex<RadioConfig>>,
+    on_config: Option<ConfigHook>,
+    stats: Arc<Mutex<DaemonStats>>,
+    poll_interval: Duration,
+}
+
+impl<T> Clone for Daemon<T> {
+    fn clone(&self) -> Self {
+        Daemon {
+            radio: self.radio.clone(),
+            clients: self.clients.clone(),
+            next_id: self.next_id.clone(),
+            access: self.access.clone(),
+            config: self.config.clone(),
+            on_config: self.on_config.clone(),
+            stats: self.stats.clone(),
+            poll_interval: self.poll_interval,
+        }
+    }
+}
+
+impl<T: Transceiver + Send + 'static> Daemon<T> {
+    /// `config` is what the Radio is currently set to, reported to clients until changed
+    pub fn new(radio: SharedRadio<T>, config: RadioConfig, access: ConfigAccess) -> Daemon<T> {
+        Daemon {
+            radio,
+            clients: Arc::new(Mutex::new(vec![])),
+            next_id: Arc::new(AtomicUsize::new(0)),
+            access: Arc::new(access),
+            config: Arc::new(Mutex::new(config)),
+            on_config: None,
+            stats: Arc::new(Mutex::new(DaemonStats::default())),
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+
+    /// sets the time between polls of the Radio
+    ///
+    /// ## Default
+    /// 10 ms
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// calls `f` with the settings a client applied, e.g. to save them
+    pub fn on_config_change<F: Fn(&RadioConfig) + Send + Sync + 'static>(&mut self, f: F) {
+        self.on_config = Some(Arc::new(f));
+    }
+
+    pub fn config(&self) -> RadioConfig {
+        *lock(&self.config)
+    }
+
+    pub fn stats(&self) -> DaemonStats {
+        *lock(&self.stats)
+    }
+
+    /// the number of clients currently attached
+    pub fn clients(&self) -> usize {
+        lock(&self.clients).len()
+    }
+
+    /// serves a client over a pair of streams until `reader` closes
+    ///
+    /// requests are read on a new thread
+    ///
+    /// ## Returns
+    /// an id for the client, unique for this daemon
+    pub fn attach<R: Read + Send + 'static, W: Write + Send + 'static>(&self, reader: R, writer: W) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let authorized = *self.access == ConfigAccess::Anyone;
+
+        lock(&self.clients).push(Client { id, writer: Box::new(writer), subscribed: None, authorized });
+
+        let daemon = self.clone();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut line = String::new();
+
+            loop {
+                line.clear();
+
+                match (&mut reader).take(MAX_LINE_LEN as u64 + 1).read_line(&mut line) {
+                    Ok(0) => {break;},
+                    Ok(n) if n > MAX_LINE_LEN => {break;},
+                    Ok(_) => {},
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {continue;},
+                    Err(_) => {break;},
+                };
+
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let response = daemon.handle_line(id, &line);
+                if !daemon.send(id, &response) {
+                    break;
+                }
+            }
+
+            lock(&daemon.clients).retain(|c| c.id != id);
+        });
+
+        return id;
+    }
+
+    /// accepts clients on `addr` in the background
+    ///
+    /// ## Returns
+    /// the address being listened on, useful when binding to port 0
+    pub fn listen_tcp<A: ToSocketAddrs>(&self, addr: A) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let local = listener.local_addr()?;
+
+        let daemon = self.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(n) => n,
+                    Err(_) => {continue;},
+                };
+
+                let _ = stream.set_nodelay(true);
+                let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                if let Ok(writer) = stream.try_clone() {
+                    daemon.attach(stream, writer);
+                }
+            }
+        });
+
+        return Ok(local);
+    }
+
+    /// accepts clients on a Unix socket at `path` in the background
+    ///
+    /// a socket left at `path` by an earlier run is replaced, the socket
+    /// file's permissions decide which users can connect
+    #[cfg(unix)]
+    pub fn listen_unix<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        use std::os::unix::fs::FileTypeExt;
+
+        let path = path.as_ref();
+        if std::fs::symlink_metadata(path).is_ok_and(|n| n.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+
+        let daemon = self.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(n) => n,
+                    Err(_) => {continue;},
+                };
+
+                let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                if let Ok(writer) = stream.try_clone() {
+                    daemon.attach(stream, writer);
+                }
+            }
+        });
+
+        return Ok(());
+    }
+
+    /// writes a line to a client, dropping it if that fails
+    ///
+    /// ## Returns
+    /// wheather or not the client is still attached
+    fn send(&self, id: usize, value: &Value) -> bool {
+        let line = format!("{}\n", value);
+
+        let mut clients = lock(&self.clients);
+        let ok = match clients.iter_mut().find(|c| c.id == id) {
+            Some(c) => c.writer.write_all(line.as_bytes()).and_then(|_| c.writer.flush()).is_ok(),
+            None => false,
+        };
+
+        if !ok {
+            clients.retain(|c| c.id != id);
+        }
+
+        return ok;
+    }
+
+    /// runs `f` on the client's state
+    fn with_client<F: FnOnce(&mut Client)>(&self, id: usize, f: F) {
+        if let Some(c) = lock(&self.clients).iter_mut().find(|c| c.id == id) {
+            f(c);
+        }
+    }
+
+    /// acts on a request line from client `id`
+    ///
+    /// ## Returns
+    /// the response to send back
+    pub fn handle_line(&self, id: usize, line: &str) -> Value {
+        let request = match serde_json::from_str::<Value>(line) {
+            Ok(Value::Object(n)) => n,
+            _ => {return json!({"ok": false, "error": "request must be a json object"});},
+        };
+
+        let mut response = match self.handle_request(id, &request) {
+            Ok(n) => n,
+            Err(e) => {
+                let mut map = Map::new();
+                map.insert("ok".to_string(), json!(false));
+                map.insert("error".to_string(), json!(e));
+                map
+            },
+        };
+
+        if let Some(req_id) = request.get("id") {
+            response.insert("id".to_string(), req_id.clone());
+        }
+
+        return Value::Object(response);
+    }
+
+    fn handle_request(&self, id: usize, request: &Map<String, Value>) -> Result<Map<String, Value>, String> {
+        let mut response = Map::new();
+        response.insert("ok".to_string(), json!(true));
+
+        match request.get("op").and_then(|v| v.as_str()) {
+            Some("subscribe") => {
+                let encoding = PayloadEncoding::parse(request.get("encoding"))?;
+                self.with_client(id, |c| c.subscribed = Some(encoding));
+            },
+            Some("unsubscribe") => self.with_client(id, |c| c.subscribed = None),
+            Some("transmit") => {
+                let encoding = PayloadEncoding::parse(request.get("encoding"))?;
+                let payload = match request.get("payload").and_then(|v| v.as_str()) {
+                    Some(n) => encoding.decode(n)?,
+                    None => {return Err("missing payload".to_string());},
+                };
+
+                if payload.is_empty() || payload.len() > MAX_PACKET_LEN {
+                    lock(&self.stats).tx_errors += 1;
+                    return Err(format!("payload must be 1 to {} bytes", MAX_PACKET_LEN));
+                }
+
+                let res = self.radio.with(|r| r.transmit(&payload));
+
+                let mut stats = lock(&self.stats);
+                match res {
+                    Ok(_) => stats.transmitted += 1,
+                    Err(e) => {
+                        stats.tx_errors += 1;
+                        return Err(format!("error transmitting | {:?}", e));
+                    },
+                };
+            },
+            Some("config") => {
+                response.insert("config".to_string(), Value::Object(config_json(&self.config())));
+            },
+            Some("set") => {
+                let authorized = lock(&self.clients).iter().any(|c| c.id == id && c.authorized);
+                if !authorized {
+                    lock(&self.stats).denied += 1;
+                    return Err("not allowed to change settings".to_string());
+                }
+
+                let changes = parse_settings(request)?;
+                let (applied, res) = self.radio.with(|r| apply_each(&changes, r));
+
+                // settings before a failure were still applied, the rest weren't
+                let mut config = lock(&self.config);
+                merge(&mut config, &applied);
+                let config = *config;
+
+                if applied != RadioConfig::default() {
+                    if let Some(hook) = &self.on_config {
+                        hook(&config);
+                    }
+                }
+
+                if let Err(e) = res {
+                    return Err(format!("error setting value | {:?}", e));
+                }
+
+                response.insert("config".to_string(), Value::Object(config_json(&config)));
+            },
+            Some("auth") => {
+                let token = request.get("token").and_then(|v| v.as_str());
+
+                let granted = match self.access.as_ref() {
+                    ConfigAccess::Anyone => true,
+                    ConfigAccess::Token(n) => token == Some(n.as_str()),
+                    ConfigAccess::Nobody => false,
+                };
+
+                if !granted {
+                    lock(&self.stats).denied += 1;
+                    return Err("invalid token".to_string());
+                }
+
+                self.with_client(id, |c| c.authorized = true);
+            },
+            Some("stats") => {
+                let stats = self.stats();
+                response.insert("stats".to_string(), json!({
+                    "clients": self.clients(),
+                    "transmitted": stats.transmitted,
+                    "received": stats.received,
+                    "tx_errors": stats.tx_errors,
+                    "rx_errors": stats.rx_errors,
+                    "denied": stats.denied,
+                }));
+            },
+            Some(n) => {return Err(format!("unknown op {}", n));},
+            None => {return Err("missing op".to_string());},
+        };
+
+        return Ok(response);
+    }
+
+    /// polls the Radio forever, sending every packet to the subscribed clients
+    pub fn run(&self) {
+        loop {
+            match self.poll() {
+                true => {},
+                false => thread::sleep(self.poll_interval),
+            };
+        }
+    }
+
+    /// polls the Radio once
+    ///
+    /// ## Returns
+    /// wheather or not a packet was recieved
+    pub fn poll(&self) -> bool {
+        let packet = match self.radio.with(|r| r.get_packet()) {
+            Ok(n) => n,
+            Err(_) => {
+                lock(&self.stats).rx_errors += 1;
+                return false;
+            },
+        };
+
+        if packet.is_empty() {
+            return false;
+        }
+
+        lock(&self.stats).received += 1;
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+
+        lock(&self.clients).retain_mut(|c| {
+            let encoding = match c.subscribed {
+                Some(n) => n,
+                None => {return true;},
+            };
+
+            let line = format!("{}\n", json!({
+                "event": "packet",
+                "timestamp": timestamp,
+                "payload": encoding.encode(&packet),
+            }));
+
+            c.writer.write_all(line.as_bytes()).and_then(|_| c.writer.flush()).is_ok()
+        });
+
+        return true;
+    }
+}
+
+/// the settings in a `set` request, every key other than op and id must be one
+fn parse_settings(request: &Map<String, Value>) -> Result<RadioConfig, String> {
+    let mut config = RadioConfig::default();
+
+    for (name, value) in request {
+        if name == "op" || name == "id" {
+            continue;
+        }
+
+        let value = match value {
+            Value::String(n) => n.clone(),
+            Value::Number(n) => n.to_string(),
+            _ => {return Err(format!("invalid value for {}", name));},
+        };
+
+        match parse_set_hardware(format!("{}={}", name, value).as_bytes()) {
+            Some(n) => merge(&mut config, &n),
+            None => {return Err(format!("invalid setting {}={}", name, value));},
+        };
+    }
+
+    if config == RadioConfig::default() {
+        return Err("no settings given".to_string());
+    }
+
+    return Ok(config);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockRadio, RadioError};
+
+    /// attaches a client that stays connected for as long as the returned pipe is kept
+    fn client<T: Transceiver + Send + 'static>(daemon: &Daemon<T>) -> (usize, io::PipeWriter) {
+        let (reader, writer) = io::pipe().unwrap();
+        return (daemon.attach(reader, io::sink()), writer);
+    }
+
+    /// a Radio that refuses every power setting
+    struct NoPower(MockRadio);
+
+    impl Transceiver for NoPower {
+        type Error = RadioError;
+
+        fn transmit(&mut self, msg: &[u8]) -> Result<(), RadioError> {
+            self.0.transmit(msg)
+        }
+
+        fn get_packet(&mut self) -> Result<Vec<u8>, RadioError> {
+            self.0.get_packet()
+        }
+
+        fn set_frequency(&mut self, frequency: f32) -> Result<(), RadioError> {
+            self.0.set_frequency(frequency)
+        }
+
+        fn set_power(&mut self, _power: f32) -> Result<(), RadioError> {
+            Err(RadioError::InvalidArgument)
+        }
+
+        fn set_deviation(&mut self, deviation: f32) -> Result<(), RadioError> {
+            self.0.set_deviation(deviation)
+        }
+
+        fn set_symbol_rate(&mut self, symbol_rate: f32) -> Result<(), RadioError> {
+            self.0.set_symbol_rate(symbol_rate)
+        }
+
+        fn set_rx_filter(&mut self, rx_filter: f32) -> Result<(), RadioError> {
+            self.0.set_rx_filter(rx_filter)
+        }
+
+        fn set_modulation(&mut self, mode: ModulationFormat) -> Result<(), RadioError> {
+            self.0.set_modulation(mode)
+        }
+
+        fn radio_reset(&mut self) -> Result<(), RadioError> {
+            self.0.radio_reset()
+        }
+
+        fn soft_reset(&mut self) -> Result<(), RadioError> {
+            self.0.soft_reset()
+        }
+    }
+
+    fn settings(request: Value) -> Result<RadioConfig, String> {
+        match request {
+            Value::Object(n) => parse_settings(&n),
+            _ => panic!("not an object"),
+        }
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let config = settings(json!({"op": "set", "id": 4, "frequency": 915.5, "power": "-3", "modulation": "gfsk4"})).unwrap();
+
+        assert_eq!(config.frequency, Some(915.5));
+        assert_eq!(config.power, Some(-3.0));
+        assert_eq!(config.modulation, Some(ModulationFormat::GFSK4));
+        assert_eq!(config.deviation, None);
+
+        let json = Value::Object(config_json(&config));
+        assert_eq!(json["modulation"], "gfsk4");
+        assert_eq!(settings(json).unwrap(), config);
+    }
+
+    #[test]
+    fn malformed_settings() {
+        assert_eq!(settings(json!({"op": "set"})), Err("no settings given".to_string()));
+        assert!(settings(json!({"op": "set", "volume": 11})).is_err());
+        assert!(settings(json!({"op": "set", "frequency": true})).is_err());
+        assert!(settings(json!({"op": "set", "frequency": [915]})).is_err());
+        assert!(settings(json!({"op": "set", "frequency": "NaN"})).is_err());
+        assert!(settings(json!({"op": "set", "frequency": "1=2"})).is_err());
+        assert!(settings(json!({"op": "set", "modulation": "morse"})).is_err());
+    }
+
+    #[test]
+    fn malformed_requests() {
+        let daemon = Daemon::new(SharedRadio::new(MockRadio::new()), RadioConfig::default(), ConfigAccess::Anyone);
+        let (id, _pipe) = client(&daemon);
+        let error = |line: &str| daemon.handle_line(id, line)["error"].as_str().unwrap_or("").to_string();
+
+        assert_eq!(error("not json"), "request must be a json object");
+        assert_eq!(error("[1, 2]"), "request must be a json object");
+        assert_eq!(error("{}"), "missing op");
+        assert_eq!(error(r#"{"op": "dance"}"#), "unknown op dance");
+        assert_eq!(error(r#"{"op": "transmit"}"#), "missing payload");
+        assert_eq!(error(r#"{"op": "transmit", "payload": "", "encoding": "text"}"#), "payload must be 1 to 255 bytes");
+        assert_eq!(error(r#"{"op": "subscribe", "encoding": "morse"}"#), "encoding must be base64, hex or text");
+        assert_eq!(error(r#"{"op": "set"}"#), "no settings given");
+
+        assert_eq!(daemon.handle_line(id, r#"{"op": "nope", "id": "a"}"#)["id"], "a");
+        assert_eq!(daemon.stats().denied, 0);
+    }
+
+    #[test]
+    fn token_access() {
+        let daemon = Daemon::new(SharedRadio::new(MockRadio::new()), RadioConfig::default(), ConfigAccess::Token("secret".to_string()));
+        let (a, _pipe_a) = client(&daemon);
+        let (b, _pipe_b) = client(&daemon);
+        assert_eq!(daemon.clients(), 2);
+
+        let set = r#"{"op": "set", "power": 3}"#;
+        assert_eq!(daemon.handle_line(a, set)["error"], "not allowed to change settings");
+        assert_eq!(daemon.handle_line(a, r#"{"op": "auth", "token": "guess"}"#)["error"], "invalid token");
+        assert_eq!(daemon.handle_line(a, r#"{"op": "auth"}"#)["error"], "invalid token");
+        assert_eq!(daemon.config().power, None);
+
+        assert_eq!(daemon.handle_line(a, r#"{"op": "auth", "token": "secret"}"#)["ok"], true);
+        assert_eq!(daemon.handle_line(a, set)["config"], json!({"power": 3.0}));
+        assert_eq!(daemon.config().power, Some(3.0));
+        assert_eq!(daemon.radio.with(|r| r.config().power), Some(3.0));
+
+        // authorizing one client doesn't authorize the others
+        assert_eq!(daemon.handle_line(b, r#"{"op": "set", "power": 5}"#)["error"], "not allowed to change settings");
+        assert_eq!(daemon.config().power, Some(3.0));
+        assert_eq!(daemon.stats().denied, 4);
+    }
+
+    #[test]
+    fn read_only_access() {
+        let daemon = Daemon::new(SharedRadio::new(MockRadio::new()), RadioConfig::default(), ConfigAccess::Nobody);
+        let (id, _pipe) = client(&daemon);
+
+        assert_eq!(daemon.handle_line(id, r#"{"op": "auth", "token": ""}"#)["error"], "invalid token");
+        assert_eq!(daemon.handle_line(id, r#"{"op": "set", "power": 3}"#)["error"], "not allowed to change settings");
+        assert_eq!(daemon.handle_line(id, r#"{"op": "config"}"#)["config"], json!({}));
+        assert_eq!(daemon.stats().denied, 2);
+    }
+
+    #[test]
+    fn partly_applied_settings() {
+        let mut daemon = Daemon::new(SharedRadio::new(NoPower(MockRadio::new())), RadioConfig::default(), ConfigAccess::Anyone);
+
+        let saved = Arc::new(Mutex::new(vec![]));
+        let hook = saved.clone();
+        daemon.on_config_change(move |c| lock(&hook).push(*c));
+
+        let (id, _pipe) = client(&daemon);
+        let response = daemon.handle_line(id, r#"{"op": "set", "frequency": 915, "power": 3, "deviation": 20}"#);
+        assert_eq!(response["error"], "error setting value | InvalidArgument");
+
+        // only the frequency was sent before the Radio refused the power
+        let config = daemon.config();
+        assert_eq!((config.frequency, config.power, config.deviation), (Some(915.0), None, None));
+        assert_eq!(daemon.radio.with(|r| r.0.config().deviation), None);
+        assert_eq!(*lock(&saved), vec![config]);
+
+        // nothing applied, nothing saved
+        daemon.handle_line(id, r#"{"op": "set", "power": 3}"#);
+        assert_eq!(lock(&saved).len(), 1);
+    }
+
+    #[test]
+    fn transmit() {
+        let daemon = Daemon::new(SharedRadio::new(MockRadio::new()), RadioConfig::default(), ConfigAccess::Nobody);
+
+        let response = daemon.handle_line(0, r#"{"op": "transmit", "payload": "0102ff", "encoding": "hex", "id": 1}"#);
+        assert_eq!(response, json!({"ok": true, "id": 1}));
+        assert_eq!(daemon.radio.with(|r| r.take_transmitted()), vec![vec![1, 2, 0xFF]]);
+    }
+}
@@ -0,0 +1,124 @@
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// bytes shown on each line of a `hexdump`
+const DUMP_WIDTH: usize = 16;
+
+/// encodes `data` as lowercase hex
+pub fn hex_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len() * 2);
+
+    for byte in data {
+        out.push_str(&format!("{:02x}", byte));
+    }
+
+    return out;
+}
+
+/// decodes hex, whitespace and a leading `0x` are ignored
+pub fn hex_decode(msg: &str) -> Result<Vec<u8>, String> {
+    let msg = msg.trim();
+    let digits: Vec<u8> = msg.strip_prefix("0x").unwrap_or(msg).bytes()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+
+    if !digits.len().is_multiple_of(2) {
+        return Err("hex input has an odd number of digits".to_string());
+    }
+
+    let mut out: Vec<u8> = Vec::with_capacity(digits.len() / 2);
+
+    for pair in digits.chunks(2) {
+        let byte = match std::str::from_utf8(pair).ok().and_then(|s| u8::from_str_radix(s, 16).ok()) {
+            Some(n) => n,
+            None => {return Err(format!("invalid hex digits \"{}\"", String::from_utf8_lossy(pair)));},
+        };
+
+        out.push(byte);
+    }
+
+    return Ok(out);
+}
+
+/// encodes `data` as standard padded base64
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            }
+            else {
+                out.push('=');
+            }
+        }
+    }
+
+    return out;
+}
+
+/// decodes standard base64, padding and whitespace are optional
+pub fn base64_decode(msg: &str) -> Result<Vec<u8>, String> {
+    let mut out: Vec<u8> = vec![];
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+
+    for c in msg.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let val = match BASE64_CHARS.iter().position(|b| *b == c) {
+            Some(n) => n as u32,
+            None => {return Err(format!("invalid base64 character '{}'", c as char));},
+        };
+
+        acc = ((acc << 6) | val) & 0xFFFF;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+
+    return Ok(out);
+}
+
+/// formats `data` like `hexdump -C`, offsets, 16 hex bytes and an ascii column
+///
+/// ```text
+/// 00000000  48 65 6c 6c 6f 20 77 6f  72 6c 64 00 01 02 03 04  |Hello world.....|
+/// 00000010  05 20 74 61 69 6c 20 62  79 74 65 73 20 68 65 72  |. tail bytes her|
+/// 00000020  65 21                                             |e!|
+/// ```
+pub fn hexdump(data: &[u8]) -> String {
+    let mut out = String::new();
+
+    for (i, line) in data.chunks(DUMP_WIDTH).enumerate() {
+        out.push_str(&format!("{:08x} ", i * DUMP_WIDTH));
+
+        for j in 0..DUMP_WIDTH {
+            // extra gap between the two groups of 8
+            if j % 8 == 0 {
+                out.push(' ');
+            }
+
+            match line.get(j) {
+                Some(b) => out.push_str(&format!("{:02x} ", b)),
+                None => out.push_str("   "),
+            };
+        }
+
+        out.push_str(" |");
+        for b in line {
+            out.push(match b.is_ascii_graphic() || *b == b' ' {
+                true => *b as char,
+                false => '.',
+            });
+        }
+        out.push_str("|\n");
+    }
+
+    return out;
+}
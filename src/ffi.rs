@@ -0,0 +1,519 @@
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+use crate::protocol::ModulationFormat;
+use crate::radio_serial;
+
+#[cfg(all(feature = "i2c", target_os = "linux"))]
+use crate::radio_i2c::{self, LinuxRadio, ResetConfig};
+#[cfg(all(feature = "i2c", target_os = "linux"))]
+use crate::linux_hal::{LinuxGpioPin, LinuxI2c, StdDelay};
+
+/// size of the path buffer in `ArmlabDevice`, including the terminating NUL
+pub const ARMLAB_PATH_LEN: usize = 256;
+
+/// result of every `armlab_radio_*` call, `ARMLAB_OK` on success
+///
+/// the remaining codes map the `RadioError` variants of both backends
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArmlabStatus {
+    ArmlabOk = 0,
+    /// a required pointer argument was NULL
+    ArmlabNullPointer = -1,
+    ArmlabInvalidArgument = -2,
+    /// the port or bus could not be opened
+    ArmlabOpenError = -3,
+    /// no Radio answered the ident request
+    ArmlabDeviceDetectError = -4,
+    ArmlabWriteError = -5,
+    ArmlabReadError = -6,
+    ArmlabSyncTimeoutError = -7,
+    /// the system could not be scanned for ports / buses
+    ArmlabPortDetectError = -8,
+    ArmlabNoResetPin = -9,
+    ArmlabResetPinError = -10,
+    /// the output buffer is too small, see the functions length / count output
+    ArmlabBufferTooSmall = -11,
+    /// the operation is not available for this backend or build
+    ArmlabUnsupported = -12,
+}
+
+impl From<radio_serial::RadioError> for ArmlabStatus {
+    fn from(err: radio_serial::RadioError) -> ArmlabStatus {
+        use radio_serial::RadioError::*;
+
+        match err {
+            PortOpenError => ArmlabStatus::ArmlabOpenError,
+            DevciceDetectError => ArmlabStatus::ArmlabDeviceDetectError,
+            InvalidArgument => ArmlabStatus::ArmlabInvalidArgument,
+            ReadLenError | ReadError => ArmlabStatus::ArmlabReadError,
+            WriteError | WriteLenError => ArmlabStatus::ArmlabWriteError,
+            SyncTimeoutError => ArmlabStatus::ArmlabSyncTimeoutError,
+            PortDetectError => ArmlabStatus::ArmlabPortDetectError,
+        }
+    }
+}
+
+#[cfg(all(feature = "i2c", target_os = "linux"))]
+impl From<radio_i2c::RadioError> for ArmlabStatus {
+    fn from(err: radio_i2c::RadioError) -> ArmlabStatus {
+        use radio_i2c::RadioError::*;
+
+        match err {
+            I2CInitError => ArmlabStatus::ArmlabOpenError,
+            DeviceDetectError => ArmlabStatus::ArmlabDeviceDetectError,
+            TransmitMsgLen | TransmitMsg | TransmitError | RecieveCmd => ArmlabStatus::ArmlabWriteError,
+            RecieveReadLen | RecieveReadMsg | ReadLengthMismatch => ArmlabStatus::ArmlabReadError,
+            InvalidArgument => ArmlabStatus::ArmlabInvalidArgument,
+            BusDetectError => ArmlabStatus::ArmlabPortDetectError,
+            NoResetPin => ArmlabStatus::ArmlabNoResetPin,
+            ResetPinError => ArmlabStatus::ArmlabResetPinError,
+        }
+    }
+}
+
+/// a Radio found by `armlab_radio_discover_serial` / `armlab_radio_discover_i2c`
+#[repr(C)]
+pub struct ArmlabDevice {
+    /// NUL terminated port name or i2c bus path
+    pub path: [c_char; ARMLAB_PATH_LEN],
+    /// i2c address of the Radio, 0 for serial ports
+    pub address: u8,
+}
+
+enum Backend {
+    Serial(radio_serial::Radio),
+    #[cfg(all(feature = "i2c", target_os = "linux"))]
+    I2c(LinuxRadio),
+    #[cfg(all(feature = "i2c", target_os = "linux"))]
+    I2cWithReset(radio_i2c::Radio<LinuxI2c, StdDelay, LinuxGpioPin>),
+}
+
+/// opaque handle to an open Radio
+///
+/// created by `armlab_radio_open_serial` / `armlab_radio_open_i2c`,
+/// released with `armlab_radio_close`
+pub struct ArmlabRadio {
+    /// only empty while `armlab_radio_attach_reset_gpio` swaps the Radio
+    backend: Option<Backend>,
+}
+
+/// runs the same call against whichever backend the handle holds,
+/// converting the result into an `ArmlabStatus`
+macro_rules! dispatch {
+    ($handle:expr, $r:ident => $call:expr) => {{
+        let handle = match $handle.as_mut() {
+            Some(n) => n,
+            None => {return ArmlabStatus::ArmlabNullPointer;},
+        };
+
+        match handle.backend.as_mut() {
+            Some(Backend::Serial($r)) => status($call),
+            #[cfg(all(feature = "i2c", target_os = "linux"))]
+            Some(Backend::I2c($r)) => status($call),
+            #[cfg(all(feature = "i2c", target_os = "linux"))]
+            Some(Backend::I2cWithReset($r)) => status($call),
+            None => ArmlabStatus::ArmlabResetPinError,
+        }
+    }};
+}
+
+fn status<E: Into<ArmlabStatus>>(res: Result<(), E>) -> ArmlabStatus {
+    match res {
+        Ok(_) => ArmlabStatus::ArmlabOk,
+        Err(n) => n.into(),
+    }
+}
+
+/// reads a NUL terminated C string argument
+unsafe fn read_str<'a>(s: *const c_char) -> Result<&'a str, ArmlabStatus> {
+    if s.is_null() {
+        return Err(ArmlabStatus::ArmlabNullPointer);
+    }
+
+    match CStr::from_ptr(s).to_str() {
+        Ok(n) => Ok(n),
+        Err(_) => Err(ArmlabStatus::ArmlabInvalidArgument),
+    }
+}
+
+/// boxes `backend` into a handle and stores it in `out`
+unsafe fn open(backend: Backend, out: *mut *mut ArmlabRadio) -> ArmlabStatus {
+    *out = Box::into_raw(Box::new(ArmlabRadio { backend: Some(backend) }));
+    ArmlabStatus::ArmlabOk
+}
+
+/// opens the Radio on the given serial port, see `radio_serial::Radio::new`
+///
+/// on success `*out` holds a handle to be released with `armlab_radio_close`
+///
+/// # Safety
+/// `port` must be a NUL terminated string, `out` a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_open_serial(port: *const c_char, out: *mut *mut ArmlabRadio) -> ArmlabStatus {
+    if out.is_null() {
+        return ArmlabStatus::ArmlabNullPointer;
+    }
+    *out = ptr::null_mut();
+
+    let port = match read_str(port) {
+        Ok(n) => n,
+        Err(n) => {return n;},
+    };
+
+    match radio_serial::Radio::new(port) {
+        Ok(n) => open(Backend::Serial(n), out),
+        Err(n) => n.into(),
+    }
+}
+
+/// opens the Radio at `address` on the given i2c bus (e.g. "/dev/i2c-1"),
+/// see `radio_i2c::Radio::new_at`
+///
+/// `address` is one reported by `armlab_radio_discover_i2c`, or 0 to try the
+/// primary address then the backup address like `radio_i2c::Radio::new`
+///
+/// on success `*out` holds a handle to be released with `armlab_radio_close`
+///
+/// `ARMLAB_UNSUPPORTED` if the library was built without i2c support
+///
+/// # Safety
+/// `bus` must be a NUL terminated string, `out` a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_open_i2c(bus: *const c_char, address: u8, out: *mut *mut ArmlabRadio) -> ArmlabStatus {
+    if out.is_null() {
+        return ArmlabStatus::ArmlabNullPointer;
+    }
+    *out = ptr::null_mut();
+
+    let _bus = match read_str(bus) {
+        Ok(n) => n,
+        Err(n) => {return n;},
+    };
+
+    #[cfg(all(feature = "i2c", target_os = "linux"))]
+    {
+        let res = match address {
+            0 => radio_i2c::Radio::new(_bus),
+            n => radio_i2c::Radio::new_at(_bus, n),
+        };
+
+        match res {
+            Ok(n) => open(Backend::I2c(n), out),
+            Err(n) => n.into(),
+        }
+    }
+
+    #[cfg(not(all(feature = "i2c", target_os = "linux")))]
+    {
+        let _ = address;
+        ArmlabStatus::ArmlabUnsupported
+    }
+}
+
+/// closes the Radio and frees the handle, NULL is ignored
+///
+/// # Safety
+/// `radio` must come from an `armlab_radio_open_*` call and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_close(radio: *mut ArmlabRadio) {
+    if !radio.is_null() {
+        drop(Box::from_raw(radio));
+    }
+}
+
+/// transmits `len` bytes from `msg`, at most 255
+///
+/// # Safety
+/// `radio` must be an open handle, `msg` must point to `len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_transmit(radio: *mut ArmlabRadio, msg: *const u8, len: usize) -> ArmlabStatus {
+    if msg.is_null() && len != 0 {
+        return ArmlabStatus::ArmlabNullPointer;
+    }
+
+    let msg: &[u8] = match len {
+        0 => &[],
+        _ => std::slice::from_raw_parts(msg, len),
+    };
+
+    dispatch!(radio, r => r.transmit(msg))
+}
+
+/// gets the most recent packet stored on the Radio
+///
+/// copies the packet into `buf` and its length into `*len`, 0 if no packet
+/// was available, a 255 byte buffer always fits
+///
+/// `ARMLAB_BUFFER_TOO_SMALL` if the packet is longer than `cap`, `*len` still
+/// holds the packet length, the packet itself is lost
+///
+/// # Safety
+/// `radio` must be an open handle, `buf` must point to `cap` writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_get_packet(radio: *mut ArmlabRadio, buf: *mut u8, cap: usize, len: *mut usize) -> ArmlabStatus {
+    if len.is_null() || (buf.is_null() && cap != 0) {
+        return ArmlabStatus::ArmlabNullPointer;
+    }
+    *len = 0;
+
+    let mut packet: Vec<u8> = vec![];
+    let res = dispatch!(radio, r => r.get_packet().map(|n| {packet = n;}));
+    if res != ArmlabStatus::ArmlabOk {
+        return res;
+    }
+
+    *len = packet.len();
+    if packet.len() > cap {
+        return ArmlabStatus::ArmlabBufferTooSmall;
+    }
+
+    if !packet.is_empty() {
+        ptr::copy_nonoverlapping(packet.as_ptr(), buf, packet.len());
+    }
+
+    ArmlabStatus::ArmlabOk
+}
+
+/// attempts to set the frequency of the radio
+///
+/// # Safety
+/// `radio` must be an open handle
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_set_frequency(radio: *mut ArmlabRadio, frequency: f32) -> ArmlabStatus {
+    dispatch!(radio, r => r.set_frequency(frequency))
+}
+
+/// attempts to set the tx gain of the radio
+///
+/// # Safety
+/// `radio` must be an open handle
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_set_power(radio: *mut ArmlabRadio, power: f32) -> ArmlabStatus {
+    dispatch!(radio, r => r.set_power(power))
+}
+
+/// attempts to set the FSK bandwith of the radio
+///
+/// # Safety
+/// `radio` must be an open handle
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_set_deviation(radio: *mut ArmlabRadio, deviation: f32) -> ArmlabStatus {
+    dispatch!(radio, r => r.set_deviation(deviation))
+}
+
+/// attempts to set the symbol rate of the radio
+///
+/// # Safety
+/// `radio` must be an open handle
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_set_symbol_rate(radio: *mut ArmlabRadio, symbol_rate: f32) -> ArmlabStatus {
+    dispatch!(radio, r => r.set_symbol_rate(symbol_rate))
+}
+
+/// attempts to set the recieve filter of the radio
+///
+/// # Safety
+/// `radio` must be an open handle
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_set_rx_filter(radio: *mut ArmlabRadio, rx_filter: f32) -> ArmlabStatus {
+    dispatch!(radio, r => r.set_rx_filter(rx_filter))
+}
+
+/// sets the modulation mode of the radio
+///
+/// `mode` is the firmware value, FSK2 (0), GFSK2 (1), ASK (3), FSK4 (4), GFSK4 (5)
+///
+/// # Safety
+/// `radio` must be an open handle
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_set_modulation(radio: *mut ArmlabRadio, mode: u8) -> ArmlabStatus {
+    let mode = match ModulationFormat::try_from(mode) {
+        Ok(n) => n,
+        Err(_) => {return ArmlabStatus::ArmlabInvalidArgument;},
+    };
+
+    dispatch!(radio, r => r.set_modulation(mode))
+}
+
+/// sends a reset command to reset the onboard Radio chip
+///
+/// # Safety
+/// `radio` must be an open handle
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_radio_reset(radio: *mut ArmlabRadio) -> ArmlabStatus {
+    dispatch!(radio, r => r.radio_reset())
+}
+
+/// sends a reset command to perform a soft reset on the entire board
+///
+/// serial handles must be re-opened afterwards, the port re-enumerates
+///
+/// # Safety
+/// `radio` must be an open handle
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_soft_reset(radio: *mut ArmlabRadio) -> ArmlabStatus {
+    dispatch!(radio, r => r.soft_reset())
+}
+
+/// attaches the gpio wired to the boards reset line to an i2c handle,
+/// see `radio_i2c::Radio::with_reset_pin`
+///
+/// `chip` is the gpio character device (e.g. "/dev/gpiochip0"), `line` the
+/// active low line offset on it, the line is pulsed for `pulse_width` ms,
+/// then the board is given `post_reset_wait` ms to boot
+///
+/// `ARMLAB_UNSUPPORTED` for serial handles, `ARMLAB_RESET_PIN_ERROR` if the
+/// line can't be requested or driven, the handle is left as it was
+///
+/// # Safety
+/// `radio` must be an open handle, `chip` a NUL terminated string
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_attach_reset_gpio(radio: *mut ArmlabRadio, chip: *const c_char, line: u32, pulse_width: u32, post_reset_wait: u32) -> ArmlabStatus {
+    let handle = match radio.as_mut() {
+        Some(n) => n,
+        None => {return ArmlabStatus::ArmlabNullPointer;},
+    };
+
+    let chip = match read_str(chip) {
+        Ok(n) => n,
+        Err(n) => {return n;},
+    };
+
+    #[cfg(all(feature = "i2c", target_os = "linux"))]
+    {
+        if let Some(Backend::Serial(_)) = handle.backend {
+            return ArmlabStatus::ArmlabUnsupported;
+        }
+
+        let pin = match LinuxGpioPin::new(chip, line) {
+            Ok(n) => n,
+            Err(_) => {return ArmlabStatus::ArmlabResetPinError;},
+        };
+        let config = ResetConfig { pulse_width, post_reset_wait, active_low: true };
+
+        // `with_reset_pin` consumes the Radio, and hands it back if the line can't be driven
+        let (backend, res) = match handle.backend.take() {
+            Some(Backend::I2c(r)) => match r.with_reset_pin(pin, config) {
+                Ok(n) => (Backend::I2cWithReset(n), ArmlabStatus::ArmlabOk),
+                Err((r, n)) => (Backend::I2c(r), n.into()),
+            },
+            Some(Backend::I2cWithReset(r)) => match r.with_reset_pin(pin, config) {
+                Ok(n) => (Backend::I2cWithReset(n), ArmlabStatus::ArmlabOk),
+                Err((r, n)) => (Backend::I2cWithReset(r), n.into()),
+            },
+            other => {
+                handle.backend = other;
+                return ArmlabStatus::ArmlabResetPinError;
+            },
+        };
+
+        handle.backend = Some(backend);
+        res
+    }
+
+    #[cfg(not(all(feature = "i2c", target_os = "linux")))]
+    {
+        let _ = (handle, chip, line, pulse_width, post_reset_wait);
+        ArmlabStatus::ArmlabUnsupported
+    }
+}
+
+/// performs a hard reset using the gpio given to `armlab_radio_attach_reset_gpio`
+///
+/// `ARMLAB_NO_RESET_PIN` if none was attached, `ARMLAB_UNSUPPORTED` for serial handles
+///
+/// # Safety
+/// `radio` must be an open handle
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_reset(radio: *mut ArmlabRadio) -> ArmlabStatus {
+    let handle = match radio.as_mut() {
+        Some(n) => n,
+        None => {return ArmlabStatus::ArmlabNullPointer;},
+    };
+
+    match handle.backend.as_mut() {
+        Some(Backend::Serial(_)) => ArmlabStatus::ArmlabUnsupported,
+        #[cfg(all(feature = "i2c", target_os = "linux"))]
+        Some(Backend::I2c(r)) => status(r.reset()),
+        #[cfg(all(feature = "i2c", target_os = "linux"))]
+        Some(Backend::I2cWithReset(r)) => status(r.reset()),
+        None => ArmlabStatus::ArmlabResetPinError,
+    }
+}
+
+/// copies `path` into a device entry, truncating it to fit
+fn fill_device(device: &mut ArmlabDevice, path: &str, address: u8) {
+    let len = path.len().min(ARMLAB_PATH_LEN - 1);
+
+    for (i, byte) in path.as_bytes()[..len].iter().enumerate() {
+        device.path[i] = *byte as c_char;
+    }
+    device.path[len] = 0;
+    device.address = address;
+}
+
+/// writes every found device into `out`, and the number found into `*count`
+unsafe fn write_devices(devices: &[(String, u8)], out: *mut ArmlabDevice, cap: usize, count: *mut usize) -> ArmlabStatus {
+    *count = devices.len();
+
+    for (i, (path, address)) in devices.iter().take(cap).enumerate() {
+        fill_device(&mut *out.add(i), path, *address);
+    }
+
+    if devices.len() > cap {
+        return ArmlabStatus::ArmlabBufferTooSmall;
+    }
+
+    ArmlabStatus::ArmlabOk
+}
+
+/// finds every serial port with a Radio attached, see `radio_serial::get_radio_ports`
+///
+/// fills up to `cap` entries of `out` and writes the number of Radios found
+/// to `*count`, `ARMLAB_BUFFER_TOO_SMALL` if that is more than `cap`
+///
+/// # Safety
+/// `out` must point to `cap` writable entries, `count` must be a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_discover_serial(out: *mut ArmlabDevice, cap: usize, count: *mut usize) -> ArmlabStatus {
+    if count.is_null() || (out.is_null() && cap != 0) {
+        return ArmlabStatus::ArmlabNullPointer;
+    }
+    *count = 0;
+
+    let ports = match radio_serial::get_radio_ports() {
+        Ok(n) => n,
+        Err(n) => {return n.into();},
+    };
+    let devices: Vec<(String, u8)> = ports.into_iter().map(|n| (n, 0)).collect();
+
+    write_devices(&devices, out, cap, count)
+}
+
+/// finds every Radio on every i2c adapter, see `radio_i2c::discover`
+///
+/// fills up to `cap` entries of `out` and writes the number of Radios found
+/// to `*count`, `ARMLAB_BUFFER_TOO_SMALL` if that is more than `cap`
+///
+/// `ARMLAB_UNSUPPORTED` if the library was built without i2c support
+///
+/// # Safety
+/// `out` must point to `cap` writable entries, `count` must be a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn armlab_radio_discover_i2c(out: *mut ArmlabDevice, cap: usize, count: *mut usize) -> ArmlabStatus {
+    if count.is_null() || (out.is_null() && cap != 0) {
+        return ArmlabStatus::ArmlabNullPointer;
+    }
+    *count = 0;
+
+    #[cfg(all(feature = "i2c", target_os = "linux"))]
+    return match radio_i2c::discover() {
+        Ok(n) => write_devices(&n, out, cap, count),
+        Err(n) => n.into(),
+    };
+
+    #[cfg(not(all(feature = "i2c", target_os = "linux")))]
+    return ArmlabStatus::ArmlabUnsupported;
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::protocol::MAX_PACKET_LEN;
+
+/// bytes in front of every fragment, message id (u16 BE), index, count
+pub const FRAGMENT_HEADER_LEN: usize = 4;
+/// message bytes carried by one fragment
+pub const MAX_FRAGMENT_PAYLOAD: usize = MAX_PACKET_LEN - FRAGMENT_HEADER_LEN;
+/// most fragments a message can be split into
+pub const MAX_FRAGMENTS: usize = 255;
+/// longest message that can be fragmented
+pub const MAX_MESSAGE_LEN: usize = MAX_FRAGMENTS * MAX_FRAGMENT_PAYLOAD;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentError {
+    /// message is empty or longer than `MAX_MESSAGE_LEN`
+    InvalidLength,
+}
+
+/// splits messages longer than a single packet into numbered fragments
+#[derive(Debug, Default)]
+pub struct Fragmenter {
+    next_id: u16,
+}
+
+impl Fragmenter {
+    pub fn new() -> Fragmenter {
+        Fragmenter { next_id: 0 }
+    }
+
+    /// splits `message` into packets of at most `MAX_PACKET_LEN` bytes
+    ///
+    /// each message gets the next id, so a `Reassembler` can tell
+    /// fragments of consecutive messages apart
+    pub fn split(&mut self, message: &[u8]) -> Result<Vec<Vec<u8>>, FragmentError> {
+        if message.is_empty() || message.len() > MAX_MESSAGE_LEN {
+            return Err(FragmentError::InvalidLength);
+        }
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let count = message.len().div_ceil(MAX_FRAGMENT_PAYLOAD);
+        let fragments = message.chunks(MAX_FRAGMENT_PAYLOAD).enumerate().map(|(i, chunk)| {
+            let mut buf = Vec::with_capacity(FRAGMENT_HEADER_LEN + chunk.len());
+            buf.extend_from_slice(&id.to_be_bytes());
+            buf.push(i as u8);
+            buf.push(count as u8);
+            buf.extend_from_slice(chunk);
+            buf
+        }).collect();
+
+        return Ok(fragments);
+    }
+}
+
+/// counters for a `Reassembler`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReassemblyStats {
+    /// messages put back together
+    pub completed: usize,
+    /// messages given up on after the timeout, a fragment was lost
+    pub expired: usize,
+    /// packets too short for the header or with an impossible index
+    pub invalid: usize,
+}
+
+struct Partial {
+    fragments: Vec<Option<Vec<u8>>>,
+    received: usize,
+    started: Instant,
+}
+
+/// puts fragments from a `Fragmenter` back together
+///
+/// fragments may arrive in any order, a message missing fragments is
+/// dropped once `timeout` passes since its first fragment
+pub struct Reassembler {
+    partial: HashMap<u16, Partial>,
+    timeout: Duration,
+    stats: ReassemblyStats,
+}
+
+impl Reassembler {
+    pub fn new(timeout: Duration) -> Reassembler {
+        Reassembler {
+            partial: HashMap::new(),
+            timeout,
+            stats: ReassemblyStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> ReassemblyStats {
+        self.stats
+    }
+
+    /// messages waiting on more fragments
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+
+    /// adds a recieved packet
+    ///
+    /// ## Returns
+    /// the message once its last fragment arrives
+    pub fn push(&mut self, packet: &[u8]) -> Option<Vec<u8>> {
+        self.push_at(packet, Instant::now())
+    }
+
+    /// `push`, with the time the packet was recieved
+    pub fn push_at(&mut self, packet: &[u8], now: Instant) -> Option<Vec<u8>> {
+        self.expire(now);
+
+        if packet.len() < FRAGMENT_HEADER_LEN {
+            self.stats.invalid += 1;
+            return None;
+        }
+
+        let id = u16::from_be_bytes([packet[0], packet[1]]);
+        let index = packet[2] as usize;
+        let count = packet[3] as usize;
+
+        if count == 0 || index >= count {
+            self.stats.invalid += 1;
+            return None;
+        }
+
+        let partial = self.partial.entry(id).or_insert_with(|| Partial {
+            fragments: vec![None; count],
+            received: 0,
+            started: now,
+        });
+
+        // an id reused with a different count means the old message was lost
+        if partial.fragments.len() != count {
+            *partial = Partial { fragments: vec![None; count], received: 0, started: now };
+            self.stats.expired += 1;
+        }
+
+        if partial.fragments[index].is_none() {
+            partial.fragments[index] = Some(packet[FRAGMENT_HEADER_LEN..].to_vec());
+            partial.received += 1;
+        }
+
+        if partial.received < count {
+            return None;
+        }
+
+        let partial = self.partial.remove(&id)?;
+        self.stats.completed += 1;
+
+        return Some(partial.fragments.into_iter().flatten().flatten().collect());
+    }
+
+    /// drops messages older than the timeout
+    fn expire(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        let before = self.partial.len();
+
+        self.partial.retain(|_, p| now.saturating_duration_since(p.started) < timeout);
+        self.stats.expired += before - self.partial.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7) as u8).collect()
+    }
+
+    #[test]
+    fn split_sizes() {
+        let mut fragmenter = Fragmenter::new();
+
+        let msg = message(MAX_FRAGMENT_PAYLOAD * 2 + 1);
+        let fragments = fragmenter.split(&msg).unwrap();
+        assert_eq!(fragments.len(), 3);
+        assert!(fragments.iter().all(|f| f.len() <= MAX_PACKET_LEN));
+        assert_eq!(fragments[2], vec![0, 0, 2, 3, msg[MAX_FRAGMENT_PAYLOAD * 2]]);
+
+        // consecutive messages get consecutive ids
+        assert_eq!(fragmenter.split(b"x").unwrap(), vec![vec![0, 1, 0, 1, b'x']]);
+
+        assert_eq!(fragmenter.split(&[]), Err(FragmentError::InvalidLength));
+        assert_eq!(fragmenter.split(&message(MAX_MESSAGE_LEN + 1)), Err(FragmentError::InvalidLength));
+        assert_eq!(fragmenter.split(&message(MAX_MESSAGE_LEN)).unwrap().len(), MAX_FRAGMENTS);
+    }
+
+    #[test]
+    fn round_trip_out_of_order() {
+        let mut fragmenter = Fragmenter::new();
+        let mut reassembler = Reassembler::new(Duration::from_secs(5));
+
+        let first = message(1000);
+        let second = message(300);
+        let mut fragments = fragmenter.split(&first).unwrap();
+        fragments.reverse();
+
+        // interleaved with a second message and a duplicate
+        let other = fragmenter.split(&second).unwrap();
+        assert_eq!(reassembler.push(&other[1]), None);
+        assert_eq!(reassembler.push(&fragments[1]), None);
+        assert_eq!(reassembler.push(&fragments[1]), None);
+
+        let mut done = vec![];
+        for fragment in fragments.iter().chain(other.iter().take(1)) {
+            done.extend(reassembler.push(fragment));
+        }
+
+        assert_eq!(done, vec![first, second]);
+        assert_eq!(reassembler.pending(), 0);
+        assert_eq!(reassembler.stats().completed, 2);
+    }
+
+    #[test]
+    fn expiry() {
+        let mut reassembler = Reassembler::new(Duration::from_secs(1));
+        let fragments = Fragmenter::new().split(&message(600)).unwrap();
+        let start = Instant::now();
+
+        assert_eq!(reassembler.push_at(&fragments[0], start), None);
+        assert_eq!(reassembler.push_at(&fragments[1], start + Duration::from_secs(2)), None);
+        assert_eq!(reassembler.push_at(&fragments[2], start + Duration::from_secs(2)), None);
+
+        assert_eq!(reassembler.stats().expired, 1);
+        assert_eq!(reassembler.pending(), 1);
+    }
+
+    #[test]
+    fn malformed_fragments() {
+        let mut reassembler = Reassembler::new(Duration::from_secs(5));
+
+        assert_eq!(reassembler.push(&[]), None);
+        assert_eq!(reassembler.push(&[0, 1, 0]), None);
+        // no fragments, an index past the count
+        assert_eq!(reassembler.push(&[0, 1, 0, 0, 9]), None);
+        assert_eq!(reassembler.push(&[0, 1, 2, 2, 9]), None);
+        assert_eq!(reassembler.stats().invalid, 4);
+        assert_eq!(reassembler.pending(), 0);
+
+        // an id reused with a different count replaces the old message
+        assert_eq!(reassembler.push(&[0, 1, 0, 3, 9]), None);
+        assert_eq!(reassembler.push(&[0, 1, 0, 1, 9]), Some(vec![9]));
+        assert_eq!(reassembler.stats().expired, 1);
+
+        // a single header is an empty fragment
+        assert_eq!(reassembler.push(&[0, 2, 0, 1]), Some(vec![]));
+    }
+}
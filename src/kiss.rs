@@ -0,0 +1,693 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(all(feature = "serial", unix))]
+use serialport::{SerialPort, TTYPort};
+
+use crate::protocol::{ModulationFormat, MAX_PACKET_LEN};
+use crate::shared::SharedRadio;
+use crate::transceiver::{RadioConfig, Transceiver};
+
+/// frame delimiter
+pub const FEND: u8 = 0xC0;
+/// escapes a FEND or FESC inside a frame
+pub const FESC: u8 = 0xDB;
+/// escaped FEND
+pub const TFEND: u8 = 0xDC;
+/// escaped FESC
+pub const TFESC: u8 = 0xDD;
+
+/// longest frame the decoder keeps, anything longer is dropped
+///
+/// well above `MAX_PACKET_LEN` so oversized frames can still be reported
+const MAX_FRAME_LEN: usize = 4096;
+
+/// how long a write to a TCP client may block before the client is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// frames queued for each client, a client that falls further behind loses
+/// frames rather than holding up the others
+const CLIENT_QUEUE_LEN: usize = 64;
+
+/// the command in the low nibble of a KISS type byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KissCommand {
+    Data,
+    /// keyup delay, 10 ms units
+    TxDelay,
+    /// p-persistence, (p + 1) / 256
+    Persistence,
+    /// slot interval, 10 ms units
+    SlotTime,
+    /// time to hold after a frame, 10 ms units
+    TxTail,
+    FullDuplex,
+    /// device specific, here `name=value` to change a Radio setting
+    SetHardware,
+    /// leave KISS mode
+    Return,
+    Unknown(u8),
+}
+
+impl KissCommand {
+    fn from_type(byte: u8) -> KissCommand {
+        if byte == 0xFF {
+            return KissCommand::Return;
+        }
+
+        match byte & 0x0F {
+            0 => KissCommand::Data,
+            1 => KissCommand::TxDelay,
+            2 => KissCommand::Persistence,
+            3 => KissCommand::SlotTime,
+            4 => KissCommand::TxTail,
+            5 => KissCommand::FullDuplex,
+            6 => KissCommand::SetHardware,
+            n => KissCommand::Unknown(n),
+        }
+    }
+
+    fn code(&self) -> u8 {
+        match self {
+            KissCommand::Data => 0,
+            KissCommand::TxDelay => 1,
+            KissCommand::Persistence => 2,
+            KissCommand::SlotTime => 3,
+            KissCommand::TxTail => 4,
+            KissCommand::FullDuplex => 5,
+            KissCommand::SetHardware => 6,
+            KissCommand::Return => 0x0F,
+            KissCommand::Unknown(n) => n & 0x0F,
+        }
+    }
+}
+
+/// a single KISS frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KissFrame {
+    /// TNC port, the high nibble of the type byte
+    pub port: u8,
+    pub command: KissCommand,
+    pub data: Vec<u8>,
+}
+
+impl KissFrame {
+    /// a data frame carrying `payload` on port 0
+    pub fn data(payload: &[u8]) -> KissFrame {
+        KissFrame { port: 0, command: KissCommand::Data, data: payload.to_vec() }
+    }
+
+    /// the frame with its delimiters and escaping, ready to write to a client
+    pub fn encode(&self) -> Vec<u8> {
+        let type_byte = match self.command {
+            KissCommand::Return => 0xFF,
+            _ => (self.port << 4) | self.command.code(),
+        };
+
+        let mut out: Vec<u8> = Vec::with_capacity(self.data.len() + 4);
+        out.push(FEND);
+        out.push(type_byte);
+
+        for byte in &self.data {
+            match *byte {
+                FEND => out.extend_from_slice(&[FESC, TFEND]),
+                FESC => out.extend_from_slice(&[FESC, TFESC]),
+                n => out.push(n),
+            };
+        }
+
+        out.push(FEND);
+        return out;
+    }
+}
+
+/// splits a KISS byte stream into frames
+#[derive(Default)]
+pub struct KissDecoder {
+    buf: Vec<u8>,
+    escaped: bool,
+    /// the current frame grew past `MAX_FRAME_LEN` and is being skipped
+    overflow: bool,
+}
+
+impl KissDecoder {
+    pub fn new() -> KissDecoder {
+        KissDecoder::default()
+    }
+
+    /// decodes as much of `data` as possible
+    ///
+    /// ## Returns
+    /// every frame completed by `data`, partial frames are kept for the next call
+    pub fn feed(&mut self, data: &[u8]) -> Vec<KissFrame> {
+        let mut out: Vec<KissFrame> = vec![];
+
+        for byte in data {
+            if let Some(frame) = self.push(*byte) {
+                out.push(frame);
+            }
+        }
+
+        return out;
+    }
+
+    /// decodes a single byte, returning a frame if it completed one
+    pub fn push(&mut self, byte: u8) -> Option<KissFrame> {
+        if byte == FEND {
+            let frame = std::mem::take(&mut self.buf);
+            let overflow = self.overflow;
+            self.escaped = false;
+            self.overflow = false;
+
+            // back to back FENDs are just idle fill
+            if frame.is_empty() || overflow {
+                return None;
+            }
+
+            return Some(KissFrame {
+                port: frame[0] >> 4,
+                command: KissCommand::from_type(frame[0]),
+                data: frame[1..].to_vec(),
+            });
+        }
+
+        let byte = match (self.escaped, byte) {
+            (false, FESC) => {
+                self.escaped = true;
+                return None;
+            },
+            (true, TFEND) => FEND,
+            (true, TFESC) => FESC,
+            // invalid escape, keep the byte as is
+            (_, n) => n,
+        };
+        self.escaped = false;
+
+        if self.buf.len() >= MAX_FRAME_LEN {
+            self.overflow = true;
+            self.buf.clear();
+        }
+        if !self.overflow {
+            self.buf.push(byte);
+        }
+
+        return None;
+    }
+}
+
+/// the KISS timing parameters last set by a client
+///
+/// the Radio firmware does its own channel access, so these are kept
+/// for clients that read them back but don't change how the Radio transmits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KissParams {
+    pub tx_delay: u8,
+    pub persistence: u8,
+    pub slot_time: u8,
+    pub tx_tail: u8,
+    pub full_duplex: bool,
+}
+
+impl Default for KissParams {
+    /// ## Default
+    /// the defaults from the KISS spec, 500 ms tx delay, p = 0.25, 100 ms slots
+    fn default() -> Self {
+        KissParams {
+            tx_delay: 50,
+            persistence: 63,
+            slot_time: 10,
+            tx_tail: 0,
+            full_duplex: false,
+        }
+    }
+}
+
+/// parses a `SetHardware` payload, `name=value` with the names used by `armlab-radio config set`
+///
+/// e.g. `frequency=915e6`, `power=-5`, `modulation=gfsk2`
+///
+/// ## Returns
+/// a RadioConfig with just that setting, None if it wasn't understood
+pub fn parse_set_hardware(data: &[u8]) -> Option<RadioConfig> {
+    let text = std::str::from_utf8(data).ok()?;
+    let (name, value) = text.trim().split_once('=')?;
+    let (name, value) = (name.trim(), value.trim());
+
+    let mut config = RadioConfig::default();
+
+    if name == "modulation" {
+        config.modulation = Some(match value.to_ascii_lowercase().as_str() {
+            "fsk2" => ModulationFormat::FSK2,
+            "gfsk2" => ModulationFormat::GFSK2,
+            "ask" => ModulationFormat::ASK,
+            "fsk4" => ModulationFormat::FSK4,
+            "gfsk4" => ModulationFormat::GFSK4,
+            _ => {return None;},
+        });
+        return Some(config);
+    }
+
+    let value = value.parse::<f32>().ok().filter(|n| n.is_finite())?;
+    match name {
+        "frequency" => config.frequency = Some(value),
+        "power" => config.power = Some(value),
+        "deviation" => config.deviation = Some(value),
+        "symbol_rate" => config.symbol_rate = Some(value),
+        "rx_filter" => config.rx_filter = Some(value),
+        _ => {return None;},
+    };
+
+    return Some(config);
+}
+
+/// counters for a running `KissServer`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KissStats {
+    /// data frames from clients transmitted by the Radio
+    pub transmitted: usize,
+    /// packets recieved by the Radio and sent to clients
+    pub received: usize,
+    /// frames that couldn't be transmitted, too long or a Radio error
+    pub tx_errors: usize,
+    /// failed polls of the Radio
+    pub rx_errors: usize,
+    /// SetHardware frames that weren't understood or were rejected
+    pub bad_settings: usize,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(n) => n,
+        Err(n) => n.into_inner(),
+    }
+}
+
+type Clients = Arc<Mutex<Vec<(usize, SyncSender<Vec<u8>>)>>>;
+
+/// KISS TNC in front of a Radio, serving any number of clients
+///
+/// data frames from a client are transmitted, every packet the Radio
+/// recieves is sent to all clients as a data frame on port 0
+///
+/// clients attach over TCP (`listen_tcp`), a pseudo terminal (`open_pty`),
+/// or any stream (`attach`), `run` then polls the Radio until it returns
+pub struct KissServer<T> {
+    radio: SharedRadio<T>,
+    clients: Clients,
+    next_id: Arc<AtomicUsize>,
+    params: Arc<Mutex<KissParams>>,
+    stats: Arc<Mutex<KissStats>>,
+    poll_interval: Duration,
+}
+
+impl<T> Clone for KissServer<T> {
+    fn clone(&self) -> Self {
+        KissServer {
+            radio: self.radio.clone(),
+            clients: self.clients.clone(),
+            next_id: self.next_id.clone(),
+            params: self.params.clone(),
+            stats: self.stats.clone(),
+            poll_interval: self.poll_interval,
+        }
+    }
+}
+
+impl<T: Transceiver + Send + 'static> KissServer<T> {
+    pub fn new(radio: SharedRadio<T>) -> KissServer<T> {
+        KissServer {
+            radio,
+            clients: Arc::new(Mutex::new(vec![])),
+            next_id: Arc::new(AtomicUsize::new(0)),
+            params: Arc::new(Mutex::new(KissParams::default())),
+            stats: Arc::new(Mutex::new(KissStats::default())),
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+
+    /// sets the time between polls of the Radio
+    ///
+    /// ## Default
+    /// 10 ms
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    pub fn params(&self) -> KissParams {
+        *lock(&self.params)
+    }
+
+    pub fn stats(&self) -> KissStats {
+        *lock(&self.stats)
+    }
+
+    /// the number of clients currently attached
+    pub fn clients(&self) -> usize {
+        lock(&self.clients).len()
+    }
+
+    /// acts on a frame from a client
+    pub fn handle_frame(&self, frame: &KissFrame) {
+        // a single Radio, so only port 0 exists
+        if frame.port != 0 && frame.command != KissCommand::Return {
+            return;
+        }
+
+        let value = frame.data.first().copied().unwrap_or(0);
+        let mut params = lock(&self.params);
+
+        match frame.command {
+            KissCommand::Data => {
+                drop(params);
+
+                let res = match frame.data.len() <= MAX_PACKET_LEN {
+                    true => self.radio.with(|r| r.transmit(&frame.data)).is_ok(),
+                    false => false,
+                };
+
+                let mut stats = lock(&self.stats);
+                match res {
+                    true => stats.transmitted += 1,
+                    false => stats.tx_errors += 1,
+                };
+            },
+            KissCommand::TxDelay => params.tx_delay = value,
+            KissCommand::Persistence => params.persistence = value,
+            KissCommand::SlotTime => params.slot_time = value,
+            KissCommand::TxTail => params.tx_tail = value,
+            KissCommand::FullDuplex => params.full_duplex = value != 0,
+            KissCommand::SetHardware => {
+                drop(params);
+
+                let ok = match parse_set_hardware(&frame.data) {
+                    Some(config) => self.radio.with(|r| config.apply(r)).is_ok(),
+                    None => false,
+                };
+
+                if !ok {
+                    lock(&self.stats).bad_settings += 1;
+                }
+            },
+            // there is no non-KISS mode to return to
+            KissCommand::Return | KissCommand::Unknown(_) => {},
+        };
+    }
+
+    /// serves a client over a pair of streams until `reader` closes
+    ///
+    /// frames are read on a new thread, recieved packets are written to
+    /// `writer` from another, a client whose writes fail is dropped
+    /// 
+    /// ## Returns
+    /// an id for the client, unique for this server
+    pub fn attach<R: Read + Send + 'static, W: Write + Send + 'static>(&self, reader: R, writer: W) -> usize {
+        return self.attach_client(reader, writer, false);
+    }
+
+    /// `attach`, where `keep_on_timeout` keeps the client when a write times out
+    /// instead of dropping it, the frame is lost
+    fn attach_client<R, W>(&self, mut reader: R, mut writer: W, keep_on_timeout: bool) -> usize
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (queue, frames) = mpsc::sync_channel::<Vec<u8>>(CLIENT_QUEUE_LEN);
+        lock(&self.clients).push((id, queue));
+
+        // ends once the client is removed and its queue dropped, or a write fails
+        let clients = self.clients.clone();
+        thread::spawn(move || {
+            for frame in frames {
+                match writer.write_all(&frame).and_then(|_| writer.flush()) {
+                    Ok(_) => {},
+                    Err(e) if keep_on_timeout && (e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock) => {},
+                    Err(_) => {break;},
+                };
+            }
+
+            lock(&clients).retain(|(n, _)| *n != id);
+        });
+
+        let server = self.clone();
+        thread::spawn(move || {
+            let mut decoder = KissDecoder::new();
+            let mut buf = [0u8; 1024];
+
+            loop {
+                let len = match reader.read(&mut buf) {
+                    Ok(0) => {break;},
+                    Ok(n) => n,
+                    // pty and serial reads time out while idle
+                    Err(e) if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::Interrupted => {continue;},
+                    Err(_) => {break;},
+                };
+
+                for frame in decoder.feed(&buf[..len]) {
+                    server.handle_frame(&frame);
+                }
+            }
+
+            lock(&server.clients).retain(|(n, _)| *n != id);
+        });
+
+        return id;
+    }
+
+    /// accepts KISS clients on `addr` in the background, 8001 is the usual port
+    ///
+    /// ## Returns
+    /// the address being listened on, useful when binding to port 0
+    pub fn listen_tcp<A: ToSocketAddrs>(&self, addr: A) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let local = listener.local_addr()?;
+
+        let server = self.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(n) => n,
+                    Err(_) => {continue;},
+                };
+
+                let _ = stream.set_nodelay(true);
+                let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                if let Ok(writer) = stream.try_clone() {
+                    server.attach(stream, writer);
+                }
+            }
+        });
+
+        return Ok(local);
+    }
+
+    /// creates a pseudo terminal serving KISS, for tools that expect a serial TNC (e.g. kissattach)
+    ///
+    /// ## Returns
+    /// the path of the terminal to give those tools, e.g. /dev/pts/3
+    #[cfg(all(feature = "serial", unix))]
+    pub fn open_pty(&self) -> io::Result<String> {
+        let (mut master, slave) = TTYPort::pair()?;
+        master.set_timeout(Duration::from_millis(500))?;
+
+        let path = match slave.name() {
+            Some(n) => n,
+            None => {return Err(io::Error::other("pseudo terminal has no name"));},
+        };
+
+        let writer = master.try_clone_native()?;
+
+        // the slave end is kept open for as long as the server runs, otherwise
+        // reads on the master fail whenever no client has the terminal open
+        let clients = self.clients.clone();
+        // nothing drains a pty that no program has open, so its writes time out
+        // without the client being gone
+        let id = self.attach_client(master, writer, true);
+        thread::spawn(move || {
+            let _slave = slave;
+            while lock(&clients).iter().any(|(n, _)| *n == id) {
+                thread::sleep(Duration::from_secs(1));
+            }
+        });
+
+        return Ok(path);
+    }
+
+    /// queues a recieved packet for every client, never blocking on one
+    fn broadcast(&self, packet: &[u8]) {
+        let frame = KissFrame::data(packet).encode();
+
+        lock(&self.clients).retain(|(_, queue)| match queue.try_send(frame.clone()) {
+            Ok(_) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+
+    /// polls the Radio forever, forwarding every packet to the clients
+    pub fn run(&self) {
+        loop {
+            match self.poll() {
+                true => {},
+                false => thread::sleep(self.poll_interval),
+            };
+        }
+    }
+
+    /// polls the Radio once
+    ///
+    /// ## Returns
+    /// wheather or not a packet was recieved
+    pub fn poll(&self) -> bool {
+        let packet = match self.radio.with(|r| r.get_packet()) {
+            Ok(n) => n,
+            Err(_) => {
+                lock(&self.stats).rx_errors += 1;
+                return false;
+            },
+        };
+
+        if packet.is_empty() {
+            return false;
+        }
+
+        lock(&self.stats).received += 1;
+        self.broadcast(&packet);
+
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let frame = KissFrame { port: 2, command: KissCommand::Data, data: vec![1, FEND, 2, FESC, 3] };
+        let encoded = frame.encode();
+
+        assert_eq!(encoded, vec![FEND, 0x20, 1, FESC, TFEND, 2, FESC, TFESC, 3, FEND]);
+        assert_eq!(KissDecoder::new().feed(&encoded), vec![frame]);
+    }
+
+    #[test]
+    fn split_and_back_to_back_frames() {
+        let mut stream = KissFrame::data(b"one").encode();
+        stream.extend(KissFrame::data(&[FESC]).encode());
+        stream.extend([FEND, FEND]);
+        stream.extend(KissFrame { port: 0, command: KissCommand::Return, data: vec![] }.encode());
+
+        // fed a byte at a time, the escape is split across calls
+        let mut decoder = KissDecoder::new();
+        let frames: Vec<KissFrame> = stream.iter().flat_map(|b| decoder.feed(&[*b])).collect();
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].data, b"one");
+        assert_eq!(frames[1].data, vec![FESC]);
+        assert_eq!(frames[2].command, KissCommand::Return);
+    }
+
+    #[test]
+    fn malformed_input() {
+        let mut decoder = KissDecoder::new();
+
+        // an invalid escape keeps the byte, an unknown command is still a frame
+        assert_eq!(decoder.feed(&[FEND, 0x00, FESC, 0x41, FEND]), vec![KissFrame::data(b"A")]);
+        assert_eq!(decoder.feed(&[FEND, 0x1C, 7, FEND])[0].command, KissCommand::Unknown(0x0C));
+
+        // an oversized frame is dropped whole, the next one still decodes
+        let mut long = vec![FEND, 0x00];
+        long.extend(vec![0x55; MAX_FRAME_LEN + 10]);
+        long.extend(KissFrame::data(b"after").encode());
+
+        assert_eq!(decoder.feed(&long), vec![KissFrame::data(b"after")]);
+    }
+
+    #[test]
+    fn set_hardware() {
+        let config = parse_set_hardware(b" frequency = 915.5 ").unwrap();
+        assert_eq!(config.frequency, Some(915.5));
+
+        let config = parse_set_hardware(b"modulation=GFSK4").unwrap();
+        assert_eq!(config.modulation, Some(ModulationFormat::GFSK4));
+
+        assert_eq!(parse_set_hardware(b"frequency"), None);
+        assert_eq!(parse_set_hardware(b"power=NaN"), None);
+        assert_eq!(parse_set_hardware(b"volume=11"), None);
+        assert_eq!(parse_set_hardware(&[0xFF, b'=', b'1']), None);
+    }
+
+    /// blocks every write until the test ends
+    struct Stalled(mpsc::Receiver<()>);
+
+    impl Write for Stalled {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            let _ = self.0.recv();
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            return Ok(());
+        }
+    }
+
+    /// fails every write with a timeout
+    struct TimingOut;
+
+    impl Write for TimingOut {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            return Ok(());
+        }
+    }
+
+    #[test]
+    fn slow_clients() {
+        let mut radio = crate::mock::MockRadio::new();
+        radio.push_rx(b"one");
+        radio.push_rx(b"two");
+
+        let server = KissServer::new(SharedRadio::new(radio));
+
+        // readers that stay open without sending anything
+        let (idle_a, _keep_a) = io::pipe().unwrap();
+        let (idle_b, _keep_b) = io::pipe().unwrap();
+        let (idle_c, _keep_c) = io::pipe().unwrap();
+        let (idle_d, _keep_d) = io::pipe().unwrap();
+
+        let (_unstall, stall) = mpsc::channel();
+        let (mut output, writer) = io::pipe().unwrap();
+        server.attach(idle_a, Stalled(stall));
+        server.attach(idle_b, writer);
+        server.attach(idle_c, TimingOut);
+        server.attach_client(idle_d, TimingOut, true);
+
+        assert!(server.poll());
+        assert!(server.poll());
+
+        // the stalled client holds up neither the lock nor the other clients
+        let mut expected = KissFrame::data(b"one").encode();
+        expected.extend(KissFrame::data(b"two").encode());
+
+        let mut buf = vec![0u8; expected.len()];
+        output.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, expected);
+
+        // a timeout drops a client, unless it is kept like the pty
+        for _ in 0..100 {
+            if server.clients() == 3 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(server.clients(), 3);
+    }
+}
@@ -1,4 +1,69 @@
-#[cfg(feature="i2clib")]
+#![cfg_attr(not(feature="std"), no_std)]
+
+pub mod protocol;
+
+#[cfg(feature="std")]
+pub mod transceiver;
+
+#[cfg(feature="std")]
+pub mod shared;
+
+#[cfg(feature="std")]
+pub mod encoding;
+
+#[cfg(feature="std")]
+pub mod beacon;
+
+#[cfg(feature="std")]
+pub mod linktest;
+
+#[cfg(feature="std")]
+pub mod capture;
+
+#[cfg(feature="std")]
+pub mod mock;
+
+#[cfg(feature="std")]
+pub mod replay;
+
+#[cfg(feature="std")]
+pub mod linklog;
+
+#[cfg(feature="std")]
+pub mod kiss;
+
+#[cfg(feature="std")]
+pub mod fragment;
+
+#[cfg(feature="std")]
+pub mod uart;
+
+#[cfg(feature="std")]
+pub mod stream;
+
+#[cfg(feature="std")]
+pub mod udp;
+
+#[cfg(feature="daemon")]
+pub mod daemon;
+
+#[cfg(feature="std")]
+pub mod ax25;
+
+#[cfg(all(feature="tun", target_os="linux"))]
+pub mod tun;
+
+#[cfg(feature="i2c")]
 pub mod radio_i2c;
 
-pub mod radio_serial;
\ No newline at end of file
+#[cfg(all(feature="i2c", feature="std", target_os="linux"))]
+pub mod linux_hal;
+
+#[cfg(feature="serial")]
+pub mod radio_serial;
+
+#[cfg(feature="ffi")]
+pub mod ffi;
+
+#[cfg(feature="python")]
+pub mod python;
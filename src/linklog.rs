@@ -0,0 +1,651 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[cfg(feature = "serial")]
+use std::time::Duration;
+
+#[cfg(feature = "serial")]
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+#[cfg(feature = "i2c")]
+use embedded_hal::i2c::{self, Error as _, ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation, SevenBitAddress};
+
+/// first bytes of a link log, followed by the version and `Link`
+pub const LINKLOG_MAGIC: [u8; 7] = *b"ARMLINK";
+
+/// version 1 logs are still read, their errors have no kind and replay as `LinkError::Other`
+pub const LINKLOG_VERSION: u8 = 2;
+
+const TAG_WRITE: u8 = 0;
+const TAG_READ: u8 = 1;
+const TAG_ERROR: u8 = 2;
+const TAG_TRANSACTION: u8 = 3;
+
+/// which host link a log was recorded on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Link {
+    Serial = 0,
+    I2c = 1,
+}
+
+/// why a recorded call failed
+///
+/// mirrors `embedded_hal::i2c::ErrorKind` so a replayed bus fails the same way,
+/// `with_retry` in `radio_i2c` only retries NACKs, serial failures are always `Other`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkError {
+    Other = 0,
+    Bus = 1,
+    ArbitrationLoss = 2,
+    /// the address wasn't acknowledged
+    NoAcknowledgeAddress = 3,
+    /// a data byte wasn't acknowledged
+    NoAcknowledgeData = 4,
+    /// not acknowledged, the bus can't tell at which point
+    NoAcknowledgeUnknown = 5,
+    Overrun = 6,
+}
+
+impl LinkError {
+    fn from_byte(val: u8) -> Option<LinkError> {
+        match val {
+            0 => Some(LinkError::Other),
+            1 => Some(LinkError::Bus),
+            2 => Some(LinkError::ArbitrationLoss),
+            3 => Some(LinkError::NoAcknowledgeAddress),
+            4 => Some(LinkError::NoAcknowledgeData),
+            5 => Some(LinkError::NoAcknowledgeUnknown),
+            6 => Some(LinkError::Overrun),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "i2c")]
+impl From<ErrorKind> for LinkError {
+    fn from(kind: ErrorKind) -> LinkError {
+        match kind {
+            ErrorKind::Bus => LinkError::Bus,
+            ErrorKind::ArbitrationLoss => LinkError::ArbitrationLoss,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => LinkError::NoAcknowledgeAddress,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data) => LinkError::NoAcknowledgeData,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown) => LinkError::NoAcknowledgeUnknown,
+            ErrorKind::Overrun => LinkError::Overrun,
+            _ => LinkError::Other,
+        }
+    }
+}
+
+#[cfg(feature = "i2c")]
+impl From<LinkError> for ErrorKind {
+    fn from(err: LinkError) -> ErrorKind {
+        match err {
+            LinkError::Other => ErrorKind::Other,
+            LinkError::Bus => ErrorKind::Bus,
+            LinkError::ArbitrationLoss => ErrorKind::ArbitrationLoss,
+            LinkError::NoAcknowledgeAddress => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            LinkError::NoAcknowledgeData => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+            LinkError::NoAcknowledgeUnknown => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            LinkError::Overrun => ErrorKind::Overrun,
+        }
+    }
+}
+
+/// a single exchange on the host link, in the order the driver made them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// bytes the host wrote
+    Write(Vec<u8>),
+    /// bytes the host read, one event per read call
+    Read(Vec<u8>),
+    /// a read that timed out or failed (serial), or a failed transaction (i2c)
+    Error(LinkError),
+    /// start of an i2c transaction with the device at this address,
+    /// followed by a `Write` / `Read` per operation, or a single `Error`
+    Transaction(u8),
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(n) => n,
+        Err(n) => n.into_inner(),
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// writes link events to a log file
+///
+/// cloning shares the same log, so a port and its `try_clone` record into one file
+#[derive(Clone)]
+pub struct LinkRecorder {
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl LinkRecorder {
+    /// creates (or truncates) a log at `path`
+    pub fn create<P: AsRef<Path>>(path: P, link: Link) -> io::Result<LinkRecorder> {
+        LinkRecorder::new(BufWriter::new(File::create(path)?), link)
+    }
+
+    /// writes the log header to `out`
+    pub fn new<W: Write + Send + 'static>(mut out: W, link: Link) -> io::Result<LinkRecorder> {
+        out.write_all(&LINKLOG_MAGIC)?;
+        out.write_all(&[LINKLOG_VERSION, link as u8])?;
+        out.flush()?;
+
+        Ok(LinkRecorder { out: Arc::new(Mutex::new(Box::new(out))) })
+    }
+
+    /// appends an event, flushed straight away so a crash doesn't lose the bytes leading up to it
+    pub fn record(&self, event: &LinkEvent) -> io::Result<()> {
+        let (tag, data): (u8, &[u8]) = match event {
+            LinkEvent::Write(n) => (TAG_WRITE, n),
+            LinkEvent::Read(n) => (TAG_READ, n),
+            LinkEvent::Error(kind) => (TAG_ERROR, &[*kind as u8]),
+            LinkEvent::Transaction(addr) => (TAG_TRANSACTION, std::slice::from_ref(addr)),
+        };
+
+        let len = match u16::try_from(data.len()) {
+            Ok(n) => n,
+            Err(_) => {return Err(io::Error::new(io::ErrorKind::InvalidInput, "event longer than 65535 bytes"));},
+        };
+
+        let mut out = lock(&self.out);
+        out.write_all(&[tag])?;
+        out.write_all(&len.to_le_bytes())?;
+        out.write_all(data)?;
+
+        return out.flush();
+    }
+}
+
+/// reads every event from a log
+///
+/// ## Returns
+/// the link the log was recorded on and its events
+pub fn read_log<R: Read>(mut input: R) -> io::Result<(Link, Vec<LinkEvent>)> {
+    let mut head = [0u8; LINKLOG_MAGIC.len() + 2];
+    input.read_exact(&mut head).map_err(|_| invalid("not a link log"))?;
+
+    if head[..LINKLOG_MAGIC.len()] != LINKLOG_MAGIC {
+        return Err(invalid("not a link log"));
+    }
+    let version = head[7];
+    if version == 0 || version > LINKLOG_VERSION {
+        return Err(invalid("unsupported link log version"));
+    }
+
+    let link = match head[8] {
+        0 => Link::Serial,
+        1 => Link::I2c,
+        _ => {return Err(invalid("unknown link"));},
+    };
+
+    let mut events: Vec<LinkEvent> = vec![];
+
+    loop {
+        // a clean end of file can only happen between events
+        let mut tag = [0u8; 1];
+        if input.read(&mut tag)? == 0 {
+            break;
+        }
+
+        let mut len = [0u8; 2];
+        input.read_exact(&mut len)?;
+
+        let mut data = vec![0u8; u16::from_le_bytes(len) as usize];
+        input.read_exact(&mut data)?;
+
+        events.push(match (tag[0], data.len()) {
+            (TAG_WRITE, _) => LinkEvent::Write(data),
+            (TAG_READ, _) => LinkEvent::Read(data),
+            (TAG_ERROR, 0) if version == 1 => LinkEvent::Error(LinkError::Other),
+            (TAG_ERROR, 1) => match LinkError::from_byte(data[0]) {
+                Some(n) => LinkEvent::Error(n),
+                None => {return Err(invalid("unknown error kind"));},
+            },
+            (TAG_TRANSACTION, 1) => LinkEvent::Transaction(data[0]),
+            _ => {return Err(invalid("bad event"));},
+        });
+    }
+
+    return Ok((link, events));
+}
+
+/// reads every event from the log at `path`
+pub fn load_log<P: AsRef<Path>>(path: P) -> io::Result<(Link, Vec<LinkEvent>)> {
+    read_log(BufReader::new(File::open(path)?))
+}
+
+struct ReplayState {
+    events: Vec<LinkEvent>,
+    next: usize,
+    /// bytes left over from a `Read` event served over several smaller reads
+    #[cfg(any(feature = "serial", feature = "i2c"))]
+    partial: Vec<u8>,
+    mismatch: Option<String>,
+}
+
+// only the replay handles, `ReplayPort` and `ReplayI2c`, drive the state
+#[cfg(any(feature = "serial", feature = "i2c"))]
+impl ReplayState {
+    /// takes the next event, recording a mismatch if it isn't what the driver did
+    fn expect(&mut self, did: &str) -> Option<LinkEvent> {
+        if self.mismatch.is_some() {
+            return None;
+        }
+
+        match self.events.get(self.next).cloned() {
+            Some(n) => {
+                self.next += 1;
+                Some(n)
+            },
+            None => {
+                self.mismatch = Some(format!("driver {} after the end of the log", did));
+                None
+            },
+        }
+    }
+
+    fn diverge(&mut self, msg: String) {
+        if self.mismatch.is_none() {
+            self.mismatch = Some(format!("event {}: {}", self.next - 1, msg));
+        }
+    }
+
+    /// checks a write against the log
+    ///
+    /// ## Returns
+    /// the number of bytes the recorded write took, which may be a prefix of `data`
+    fn write(&mut self, data: &[u8]) -> Result<usize, ()> {
+        match self.expect("wrote") {
+            Some(LinkEvent::Write(n)) if !n.is_empty() && data.starts_with(&n) => Ok(n.len()),
+            Some(LinkEvent::Error(_)) => Err(()),
+            Some(other) => {
+                self.diverge(format!("driver wrote {:02X?}, log has {:?}", data, other));
+                Err(())
+            },
+            None => Err(()),
+        }
+    }
+
+    /// fills `buf` from the log, a recorded `Error` is returned as Err
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        if self.partial.is_empty() {
+            match self.expect("read") {
+                Some(LinkEvent::Read(n)) => {self.partial = n;},
+                Some(LinkEvent::Error(_)) => {return Err(());},
+                Some(other) => {
+                    self.diverge(format!("driver read, log has {:?}", other));
+                    return Err(());
+                },
+                None => {return Err(());},
+            };
+        }
+
+        let len = buf.len().min(self.partial.len());
+        buf[..len].copy_from_slice(&self.partial[..len]);
+        self.partial.drain(..len);
+
+        return Ok(len);
+    }
+}
+
+/// plays a link log back to a driver, handed out as a `ReplayPort` or `ReplayI2c`
+///
+/// every handle shares the same position, keep one to check the driver
+/// followed the log once it is done
+#[derive(Clone)]
+pub struct LinkReplay {
+    state: Arc<Mutex<ReplayState>>,
+}
+
+impl LinkReplay {
+    pub fn new(events: Vec<LinkEvent>) -> LinkReplay {
+        LinkReplay {
+            state: Arc::new(Mutex::new(ReplayState {
+                events,
+                next: 0,
+                #[cfg(any(feature = "serial", feature = "i2c"))]
+                partial: vec![],
+                mismatch: None,
+            })),
+        }
+    }
+
+    /// the number of events not yet played back
+    pub fn remaining(&self) -> usize {
+        let state = lock(&self.state);
+        state.events.len() - state.next
+    }
+
+    /// where the driver first did something the log doesn't have
+    ///
+    /// every call after a mismatch fails
+    pub fn mismatch(&self) -> Option<String> {
+        lock(&self.state).mismatch.clone()
+    }
+
+    /// Ok once the whole log has been played back without a mismatch
+    pub fn finish(&self) -> Result<(), String> {
+        if let Some(n) = self.mismatch() {
+            return Err(n);
+        }
+
+        match self.remaining() {
+            0 => Ok(()),
+            n => Err(format!("driver stopped with {} event(s) left in the log", n)),
+        }
+    }
+}
+
+/// a serial port that records every byte written and read through it
+#[cfg(feature = "serial")]
+pub struct RecordingPort {
+    port: Box<dyn SerialPort>,
+    recorder: LinkRecorder,
+}
+
+#[cfg(feature = "serial")]
+impl RecordingPort {
+    pub fn new(port: Box<dyn SerialPort>, recorder: LinkRecorder) -> RecordingPort {
+        RecordingPort { port, recorder }
+    }
+
+    /// a recording failure fails the call, a log with gaps can't be replayed
+    fn record(&self, event: LinkEvent) -> io::Result<()> {
+        self.recorder.record(&event)
+    }
+}
+
+#[cfg(feature = "serial")]
+impl Read for RecordingPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.port.read(buf) {
+            Ok(n) => {
+                self.record(LinkEvent::Read(buf[..n].to_vec()))?;
+                Ok(n)
+            },
+            Err(e) => {
+                self.record(LinkEvent::Error(LinkError::Other))?;
+                Err(e)
+            },
+        }
+    }
+}
+
+#[cfg(feature = "serial")]
+impl Write for RecordingPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.port.write(buf) {
+            Ok(n) => {
+                self.record(LinkEvent::Write(buf[..n].to_vec()))?;
+                Ok(n)
+            },
+            Err(e) => {
+                self.record(LinkEvent::Error(LinkError::Other))?;
+                Err(e)
+            },
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
+
+/// forwards every `SerialPort` setting to the wrapped port
+#[cfg(feature = "serial")]
+impl SerialPort for RecordingPort {
+    fn name(&self) -> Option<String> {self.port.name()}
+    fn baud_rate(&self) -> serialport::Result<u32> {self.port.baud_rate()}
+    fn data_bits(&self) -> serialport::Result<DataBits> {self.port.data_bits()}
+    fn flow_control(&self) -> serialport::Result<FlowControl> {self.port.flow_control()}
+    fn parity(&self) -> serialport::Result<Parity> {self.port.parity()}
+    fn stop_bits(&self) -> serialport::Result<StopBits> {self.port.stop_bits()}
+    fn timeout(&self) -> Duration {self.port.timeout()}
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {self.port.set_baud_rate(baud_rate)}
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {self.port.set_data_bits(data_bits)}
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {self.port.set_flow_control(flow_control)}
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {self.port.set_parity(parity)}
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {self.port.set_stop_bits(stop_bits)}
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {self.port.set_timeout(timeout)}
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {self.port.write_request_to_send(level)}
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {self.port.write_data_terminal_ready(level)}
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {self.port.read_clear_to_send()}
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {self.port.read_data_set_ready()}
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {self.port.read_ring_indicator()}
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {self.port.read_carrier_detect()}
+    fn bytes_to_read(&self) -> serialport::Result<u32> {self.port.bytes_to_read()}
+    fn bytes_to_write(&self) -> serialport::Result<u32> {self.port.bytes_to_write()}
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {self.port.clear(buffer_to_clear)}
+    fn set_break(&self) -> serialport::Result<()> {self.port.set_break()}
+    fn clear_break(&self) -> serialport::Result<()> {self.port.clear_break()}
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Ok(Box::new(RecordingPort { port: self.port.try_clone()?, recorder: self.recorder.clone() }))
+    }
+}
+
+/// a serial port that plays a recorded log back instead of talking to a device
+///
+/// writes are checked against the log, reads return the recorded bytes and
+/// recorded read failures come back as timeouts
+#[cfg(feature = "serial")]
+pub struct ReplayPort {
+    replay: LinkReplay,
+    timeout: Duration,
+}
+
+#[cfg(feature = "serial")]
+impl ReplayPort {
+    pub fn new(replay: LinkReplay) -> ReplayPort {
+        ReplayPort { replay, timeout: Duration::from_millis(100) }
+    }
+}
+
+#[cfg(feature = "serial")]
+impl Read for ReplayPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match lock(&self.replay.state).read(buf) {
+            Ok(n) => Ok(n),
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "replayed read failure")),
+        }
+    }
+}
+
+#[cfg(feature = "serial")]
+impl Write for ReplayPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match lock(&self.replay.state).write(buf) {
+            Ok(n) => Ok(n),
+            Err(_) => Err(io::Error::other("replayed write failure")),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// settings are accepted and ignored, only the byte stream is replayed
+#[cfg(feature = "serial")]
+impl SerialPort for ReplayPort {
+    fn name(&self) -> Option<String> {Some("replay".to_string())}
+    fn baud_rate(&self) -> serialport::Result<u32> {Ok(115200)}
+    fn data_bits(&self) -> serialport::Result<DataBits> {Ok(DataBits::Eight)}
+    fn flow_control(&self) -> serialport::Result<FlowControl> {Ok(FlowControl::None)}
+    fn parity(&self) -> serialport::Result<Parity> {Ok(Parity::None)}
+    fn stop_bits(&self) -> serialport::Result<StopBits> {Ok(StopBits::One)}
+    fn timeout(&self) -> Duration {self.timeout}
+    fn set_baud_rate(&mut self, _: u32) -> serialport::Result<()> {Ok(())}
+    fn set_data_bits(&mut self, _: DataBits) -> serialport::Result<()> {Ok(())}
+    fn set_flow_control(&mut self, _: FlowControl) -> serialport::Result<()> {Ok(())}
+    fn set_parity(&mut self, _: Parity) -> serialport::Result<()> {Ok(())}
+    fn set_stop_bits(&mut self, _: StopBits) -> serialport::Result<()> {Ok(())}
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {self.timeout = timeout; Ok(())}
+    fn write_request_to_send(&mut self, _: bool) -> serialport::Result<()> {Ok(())}
+    fn write_data_terminal_ready(&mut self, _: bool) -> serialport::Result<()> {Ok(())}
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {Ok(true)}
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {Ok(true)}
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {Ok(false)}
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {Ok(true)}
+    fn bytes_to_read(&self) -> serialport::Result<u32> {Ok(0)}
+    fn bytes_to_write(&self) -> serialport::Result<u32> {Ok(0)}
+    fn clear(&self, _: ClearBuffer) -> serialport::Result<()> {Ok(())}
+    fn set_break(&self) -> serialport::Result<()> {Ok(())}
+    fn clear_break(&self) -> serialport::Result<()> {Ok(())}
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Ok(Box::new(ReplayPort { replay: self.replay.clone(), timeout: self.timeout }))
+    }
+}
+
+/// an i2c bus that records every transaction made through it
+#[cfg(feature = "i2c")]
+pub struct RecordingI2c<I2C> {
+    i2c: I2C,
+    recorder: LinkRecorder,
+    /// the first recording failure, recording stops after one
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "i2c")]
+impl<I2C: I2c> RecordingI2c<I2C> {
+    pub fn new(i2c: I2C, recorder: LinkRecorder) -> RecordingI2c<I2C> {
+        RecordingI2c { i2c, recorder, error: None }
+    }
+
+    /// the error that stopped recording, if any
+    pub fn record_error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    fn record(&mut self, events: &[LinkEvent]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        for event in events {
+            if let Err(e) = self.recorder.record(event) {
+                self.error = Some(e);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "i2c")]
+impl<I2C: I2c> ErrorType for RecordingI2c<I2C> {
+    type Error = I2C::Error;
+}
+
+#[cfg(feature = "i2c")]
+impl<I2C: I2c> I2c<SevenBitAddress> for RecordingI2c<I2C> {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        let res = self.i2c.transaction(address, operations);
+
+        let mut events = vec![LinkEvent::Transaction(address)];
+        match res {
+            Ok(_) => {
+                events.extend(operations.iter().map(|op| match op {
+                    Operation::Write(n) => LinkEvent::Write(n.to_vec()),
+                    Operation::Read(n) => LinkEvent::Read(n.to_vec()),
+                }));
+            },
+            Err(ref e) => events.push(LinkEvent::Error(e.kind().into())),
+        };
+        self.record(&events);
+
+        return res;
+    }
+}
+
+/// error returned by `ReplayI2c`, a recorded failure keeps its kind,
+/// a mismatch with the log is `ErrorKind::Other`
+#[cfg(feature = "i2c")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayI2cError(pub ErrorKind);
+
+#[cfg(feature = "i2c")]
+impl i2c::Error for ReplayI2cError {
+    fn kind(&self) -> ErrorKind {
+        self.0
+    }
+}
+
+/// an i2c bus that plays a recorded log back, give it to `radio_i2c::Radio::from_bus`
+#[cfg(feature = "i2c")]
+#[derive(Clone)]
+pub struct ReplayI2c {
+    replay: LinkReplay,
+}
+
+#[cfg(feature = "i2c")]
+impl ReplayI2c {
+    pub fn new(replay: LinkReplay) -> ReplayI2c {
+        ReplayI2c { replay }
+    }
+}
+
+#[cfg(feature = "i2c")]
+impl ErrorType for ReplayI2c {
+    type Error = ReplayI2cError;
+}
+
+#[cfg(feature = "i2c")]
+impl I2c<SevenBitAddress> for ReplayI2c {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        let mut state = lock(&self.replay.state);
+
+        match state.expect("started a transaction") {
+            Some(LinkEvent::Transaction(n)) if n == address => {},
+            Some(other) => {
+                state.diverge(format!("driver addressed 0x{:02X}, log has {:?}", address, other));
+                return Err(ReplayI2cError(ErrorKind::Other));
+            },
+            None => {return Err(ReplayI2cError(ErrorKind::Other));},
+        };
+
+        // a failed transaction is recorded as a single Error in place of its operations
+        if let Some(LinkEvent::Error(kind)) = state.events.get(state.next) {
+            let kind = *kind;
+            state.next += 1;
+            return Err(ReplayI2cError(kind.into()));
+        }
+
+        for op in operations.iter_mut() {
+            match op {
+                Operation::Write(data) => {
+                    match state.write(data) {
+                        Ok(n) if n == data.len() => {},
+                        Ok(_) => {
+                            state.diverge(format!("driver wrote {:02X?}, log has a shorter write", data));
+                            return Err(ReplayI2cError(ErrorKind::Other));
+                        },
+                        Err(_) => {return Err(ReplayI2cError(ErrorKind::Other));},
+                    };
+                },
+                Operation::Read(buf) => {
+                    // i2c reads are recorded whole, a short one means the driver diverged
+                    match state.read(buf) {
+                        Ok(n) if n == buf.len() && state.partial.is_empty() => {},
+                        Ok(_) => {
+                            state.partial.clear();
+                            state.diverge(format!("driver read {} bytes, log has a different length", buf.len()));
+                            return Err(ReplayI2cError(ErrorKind::Other));
+                        },
+                        Err(_) => {return Err(ReplayI2cError(ErrorKind::Other));},
+                    };
+                },
+            };
+        }
+
+        return Ok(());
+    }
+}
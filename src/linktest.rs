@@ -0,0 +1,544 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::protocol::{ProtocolError, MAX_PACKET_LEN};
+use crate::transceiver::{RadioConfig, Transceiver};
+
+/// first bytes of every link test packet
+pub const LINKTEST_MAGIC: [u8; 2] = [0x4C, 0x54];
+
+/// magic, pattern, sequence number (u32), packet count (u32) and payload length, little endian
+pub const LINKTEST_HEADER_LEN: usize = 12;
+
+/// the payload every link test packet carries, known to both ends so
+/// bit errors can be counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// x^9 + x^5 + 1 sequence seeded with 0x1FF, as used by the CC1200 whitening
+    Pn9 = 0,
+    /// byte `i` of packet `seq` is `seq + i`
+    Counter = 1,
+}
+
+impl TryFrom<u8> for Pattern {
+    type Error = ProtocolError;
+
+    fn try_from(val: u8) -> Result<Pattern, ProtocolError> {
+        match val {
+            0 => Ok(Pattern::Pn9),
+            1 => Ok(Pattern::Counter),
+            _ => Err(ProtocolError::UnknownCommand),
+        }
+    }
+}
+
+impl Pattern {
+    /// the expected payload of packet `seq`
+    pub fn fill(&self, seq: u32, buf: &mut [u8]) {
+        match self {
+            Pattern::Pn9 => {
+                let mut state: u16 = 0x1FF;
+
+                for byte in buf.iter_mut() {
+                    *byte = (state & 0xFF) as u8;
+
+                    for _ in 0..8 {
+                        let bit = ((state >> 5) ^ state) & 1;
+                        state = (state >> 1) | (bit << 8);
+                    }
+                }
+            },
+            Pattern::Counter => {
+                for (i, byte) in buf.iter_mut().enumerate() {
+                    *byte = seq.wrapping_add(i as u32) as u8;
+                }
+            },
+        };
+    }
+
+    /// "pn9" or "counter"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::Pn9 => "pn9",
+            Pattern::Counter => "counter",
+        }
+    }
+}
+
+/// how a link test is sent
+#[derive(Debug, Clone, Copy)]
+pub struct LinkTestConfig {
+    pub pattern: Pattern,
+    /// packets to send
+    pub count: u32,
+    /// payload bytes after the header
+    pub payload_len: usize,
+    /// time between packets
+    pub interval: Duration,
+}
+
+impl Default for LinkTestConfig {
+    /// ## Default
+    /// PN9, 1000 packets of 64 bytes, 20 ms apart
+    fn default() -> Self {
+        LinkTestConfig {
+            pattern: Pattern::Pn9,
+            count: 1000,
+            payload_len: 64,
+            interval: Duration::from_millis(20),
+        }
+    }
+}
+
+/// builds link test packet `seq` of `count`
+pub fn encode(pattern: Pattern, seq: u32, count: u32, payload_len: usize) -> Result<Vec<u8>, ProtocolError> {
+    if LINKTEST_HEADER_LEN + payload_len > MAX_PACKET_LEN {
+        return Err(ProtocolError::PacketTooLong);
+    }
+
+    let mut out: Vec<u8> = vec![0; LINKTEST_HEADER_LEN + payload_len];
+    out[..2].copy_from_slice(&LINKTEST_MAGIC);
+    out[2] = pattern as u8;
+    out[3..7].copy_from_slice(&seq.to_le_bytes());
+    out[7..11].copy_from_slice(&count.to_le_bytes());
+    out[11] = payload_len as u8;
+    pattern.fill(seq, &mut out[LINKTEST_HEADER_LEN..]);
+
+    return Ok(out);
+}
+
+/// the pattern, sequence number, packet count and payload length of a link test packet
+fn parse_header(packet: &[u8]) -> Option<(Pattern, u32, u32, usize)> {
+    if packet.len() < LINKTEST_HEADER_LEN || packet[..2] != LINKTEST_MAGIC {
+        return None;
+    }
+
+    let pattern = Pattern::try_from(packet[2]).ok()?;
+    let seq = u32::from_le_bytes([packet[3], packet[4], packet[5], packet[6]]);
+    let count = u32::from_le_bytes([packet[7], packet[8], packet[9], packet[10]]);
+
+    return Some((pattern, seq, count, packet[11] as usize));
+}
+
+/// sends the packets of a link test
+pub struct LinkTestSender {
+    config: LinkTestConfig,
+    seq: u32,
+}
+
+impl LinkTestSender {
+    /// fails if the payload doesn't fit in a single packet
+    pub fn new(config: LinkTestConfig) -> Result<LinkTestSender, ProtocolError> {
+        if LINKTEST_HEADER_LEN + config.payload_len > MAX_PACKET_LEN {
+            return Err(ProtocolError::PacketTooLong);
+        }
+
+        Ok(LinkTestSender { config, seq: 0 })
+    }
+
+    /// the number of packets sent so far
+    pub fn sent(&self) -> u32 {
+        self.seq
+    }
+
+    /// sends the next packet, waiting `interval` after every packet but the first
+    ///
+    /// ## Returns
+    /// the sequence number sent, None once every packet has been sent
+    pub fn send_next<T: Transceiver>(&mut self, radio: &mut T) -> Result<Option<u32>, T::Error> {
+        if self.seq >= self.config.count {
+            return Ok(None);
+        }
+
+        if self.seq > 0 {
+            thread::sleep(self.config.interval);
+        }
+
+        let c = &self.config;
+        // length was checked in `new`
+        let packet = encode(c.pattern, self.seq, c.count, c.payload_len).unwrap_or_default();
+        radio.transmit(&packet)?;
+
+        self.seq += 1;
+        return Ok(Some(self.seq - 1));
+    }
+}
+
+/// signal strength over a link test (dBm)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RssiStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+}
+
+/// results of a link test, as seen by the receiver
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkTestReport {
+    /// None if no packet was recieved
+    pub pattern: Option<Pattern>,
+    pub payload_len: usize,
+    /// packets the sender announced
+    pub sent: u32,
+    /// distinct packets recieved
+    pub received: u32,
+    pub lost: u32,
+    pub duplicates: u32,
+    /// packets that were not part of the test
+    pub invalid: u32,
+    /// packet error rate, 0.0 - 1.0
+    pub per: f64,
+    /// payload bits compared against the pattern
+    pub bits: u64,
+    pub bit_errors: u64,
+    /// bit error rate over the recieved packets, 0.0 - 1.0
+    pub ber: f64,
+    /// the firmware does not report RSSI yet, so this is always None
+    pub rssi: Option<RssiStats>,
+    /// first to last recieved packet
+    pub duration: Duration,
+    /// recieved payload bits per second
+    pub throughput: f64,
+    /// the settings the Radio was using
+    pub config: RadioConfig,
+}
+
+/// header line matching `LinkTestReport::to_csv`
+pub const CSV_HEADER: &str = "pattern,payload_len,frequency,power,deviation,symbol_rate,rx_filter,modulation,\
+sent,received,lost,duplicates,invalid,per,bits,bit_errors,ber,rssi_min,rssi_max,rssi_mean,duration_s,throughput_bps";
+
+/// "" for None, so missing values are empty csv cells / json nulls
+fn opt<T: std::fmt::Display>(val: Option<T>) -> String {
+    val.map(|n| n.to_string()).unwrap_or_default()
+}
+
+impl LinkTestReport {
+    /// a single csv row, see `CSV_HEADER`
+    pub fn to_csv(&self) -> String {
+        let c = &self.config;
+        let r = self.rssi;
+
+        return format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.pattern.map(|p| p.name()).unwrap_or_default(), self.payload_len,
+            opt(c.frequency), opt(c.power), opt(c.deviation), opt(c.symbol_rate), opt(c.rx_filter),
+            opt(c.modulation.map(|m| format!("{:?}", m))),
+            self.sent, self.received, self.lost, self.duplicates, self.invalid, self.per,
+            self.bits, self.bit_errors, self.ber,
+            opt(r.map(|n| n.min)), opt(r.map(|n| n.max)), opt(r.map(|n| n.mean)),
+            self.duration.as_secs_f64(), self.throughput,
+        );
+    }
+
+    /// a single line json object with the same fields as `to_csv`
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{");
+
+        for (i, (key, val)) in CSV_HEADER.split(',').zip(self.to_csv().split(',')).enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            let _ = match (key, val) {
+                (_, "") => write!(out, "\"{}\":null", key),
+                ("pattern" | "modulation", _) => write!(out, "\"{}\":\"{}\"", key, val),
+                // rust prints non finite floats as NaN / inf, which json can't represent
+                (_, "NaN" | "inf" | "-inf") => write!(out, "\"{}\":null", key),
+                _ => write!(out, "\"{}\":{}", key, val),
+            };
+        }
+
+        out.push('}');
+        return out;
+    }
+}
+
+/// collects a link test and checks every payload against the pattern
+#[derive(Debug, Clone)]
+pub struct LinkTestReceiver {
+    pattern: Option<Pattern>,
+    payload_len: usize,
+    count: u32,
+    /// the first packet, held until a second one agrees on the test
+    candidate: Option<(Vec<u8>, Instant)>,
+    /// which sequence numbers have been recieved, sparse since `count` comes off the air
+    seen: BTreeSet<u32>,
+    received: u32,
+    duplicates: u32,
+    invalid: u32,
+    bits: u64,
+    bit_errors: u64,
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl Default for LinkTestReceiver {
+    fn default() -> Self {
+        LinkTestReceiver::new()
+    }
+}
+
+impl LinkTestReceiver {
+    /// the pattern, packet count and payload length are learned from the
+    /// first two packets that agree on them
+    pub fn new() -> LinkTestReceiver {
+        LinkTestReceiver {
+            pattern: None,
+            payload_len: 0,
+            count: 0,
+            candidate: None,
+            seen: BTreeSet::new(),
+            received: 0,
+            duplicates: 0,
+            invalid: 0,
+            bits: 0,
+            bit_errors: 0,
+            first: None,
+            last: None,
+        }
+    }
+
+    /// polls the Radio once, recording a packet if one was waiting
+    ///
+    /// ## Returns
+    /// the sequence number recieved, None if there was no link test packet
+    pub fn poll<T: Transceiver>(&mut self, radio: &mut T) -> Result<Option<u32>, T::Error> {
+        let packet = radio.get_packet()?;
+
+        if packet.is_empty() {
+            return Ok(None);
+        }
+
+        return Ok(self.receive_at(&packet, Instant::now()));
+    }
+
+    /// records a packet recieved at `at`
+    ///
+    /// ## Returns
+    /// the sequence number, None if the packet was not part of the test or
+    /// is the first of a test, which is only counted once a second packet
+    /// confirms it
+    pub fn receive_at(&mut self, packet: &[u8], at: Instant) -> Option<u32> {
+        let header = match parse_header(packet) {
+            Some(n) => n,
+            None => {
+                self.invalid += 1;
+                return None;
+            },
+        };
+
+        // a single corrupted header mustn't decide which test is being recieved,
+        // the first packet waits for another with the same pattern, count and length
+        if self.pattern.is_none() {
+            let (pattern, _, count, payload_len) = header;
+
+            match self.candidate.take() {
+                Some((first, first_at)) if parse_header(&first).is_some_and(|(p, _, c, l)| (p, c, l) == (pattern, count, payload_len)) => {
+                    self.start(pattern, count, payload_len);
+                    self.record(&first, first_at);
+                },
+                Some(_) => {
+                    self.invalid += 1;
+                    self.candidate = Some((packet.to_vec(), at));
+                    return None;
+                },
+                // nothing else is coming to confirm a single packet test
+                None if count == 1 => self.start(pattern, count, payload_len),
+                None => {
+                    self.candidate = Some((packet.to_vec(), at));
+                    return None;
+                },
+            };
+        }
+
+        return self.record(packet, at);
+    }
+
+    fn start(&mut self, pattern: Pattern, count: u32, payload_len: usize) {
+        self.pattern = Some(pattern);
+        self.count = count;
+        self.payload_len = payload_len;
+    }
+
+    /// checks a packet against the test being recieved
+    fn record(&mut self, packet: &[u8], at: Instant) -> Option<u32> {
+        let (pattern, seq, count, _) = match parse_header(packet) {
+            Some(n) => n,
+            None => {
+                self.invalid += 1;
+                return None;
+            },
+        };
+
+        if Some(pattern) != self.pattern || count != self.count || seq >= count {
+            self.invalid += 1;
+            return None;
+        }
+
+        if !self.seen.insert(seq) {
+            self.duplicates += 1;
+            return Some(seq);
+        }
+
+        self.received += 1;
+        self.first.get_or_insert(at);
+        self.last = Some(at);
+
+        // compare against the full expected payload, missing bytes count as errors
+        let mut expected: Vec<u8> = vec![0; self.payload_len];
+        pattern.fill(seq, &mut expected);
+
+        let payload = &packet[LINKTEST_HEADER_LEN..];
+        for (i, want) in expected.iter().enumerate() {
+            self.bit_errors += match payload.get(i) {
+                Some(got) => (got ^ want).count_ones() as u64,
+                None => 8,
+            };
+        }
+        self.bits += expected.len() as u64 * 8;
+
+        return Some(seq);
+    }
+
+    /// wheather or not the last packet of the test has been recieved
+    pub fn is_complete(&self) -> bool {
+        self.count > 0 && self.seen.contains(&(self.count - 1))
+    }
+
+    /// the time the most recent packet arrived
+    pub fn last_packet(&self) -> Option<Instant> {
+        match &self.candidate {
+            Some((_, at)) => self.last.max(Some(*at)),
+            None => self.last,
+        }
+    }
+
+    /// summarizes the test, `config` is recorded as the settings used
+    ///
+    /// a lone unconfirmed first packet is counted as the test, nothing disagreed with it
+    pub fn report(&self, config: RadioConfig) -> LinkTestReport {
+        if let Some((packet, at)) = &self.candidate {
+            let mut receiver = self.clone();
+            receiver.candidate = None;
+
+            if let Some((pattern, _, count, payload_len)) = parse_header(packet) {
+                receiver.start(pattern, count, payload_len);
+                receiver.record(packet, *at);
+            }
+
+            return receiver.report(config);
+        }
+
+        let duration = match (self.first, self.last) {
+            (Some(first), Some(last)) => last - first,
+            _ => Duration::ZERO,
+        };
+
+        let lost = self.count - self.received;
+
+        LinkTestReport {
+            pattern: self.pattern,
+            payload_len: self.payload_len,
+            sent: self.count,
+            received: self.received,
+            lost,
+            duplicates: self.duplicates,
+            invalid: self.invalid,
+            per: match self.count {
+                0 => 0.0,
+                n => lost as f64 / n as f64,
+            },
+            bits: self.bits,
+            bit_errors: self.bit_errors,
+            ber: match self.bits {
+                0 => 0.0,
+                n => self.bit_errors as f64 / n as f64,
+            },
+            rssi: None,
+            duration,
+            throughput: match duration.as_secs_f64() {
+                0.0 => 0.0,
+                secs => self.bits as f64 / secs,
+            },
+            config,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(seq: u32, count: u32) -> Vec<u8> {
+        encode(Pattern::Pn9, seq, count, 16).unwrap()
+    }
+
+    #[test]
+    fn complete_test() {
+        let mut receiver = LinkTestReceiver::new();
+        let now = Instant::now();
+
+        for seq in 0..10 {
+            receiver.receive_at(&packet(seq, 10), now);
+        }
+        receiver.receive_at(&packet(3, 10), now);
+
+        assert!(receiver.is_complete());
+
+        let report = receiver.report(RadioConfig::default());
+        assert_eq!((report.sent, report.received, report.lost, report.duplicates), (10, 10, 0, 1));
+        assert_eq!((report.bits, report.bit_errors), (10 * 16 * 8, 0));
+    }
+
+    #[test]
+    fn hostile_header_doesnt_decide_the_test() {
+        let mut receiver = LinkTestReceiver::new();
+        let now = Instant::now();
+
+        // a corrupted first header claiming billions of packets
+        let mut hostile = packet(7, 10);
+        hostile[7..11].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(receiver.receive_at(&hostile, now), None);
+
+        assert_eq!(receiver.receive_at(&packet(0, 10), now), None);
+        assert_eq!(receiver.receive_at(&packet(1, 10), now), Some(1));
+        assert_eq!(receiver.receive_at(&hostile, now), None);
+
+        let report = receiver.report(RadioConfig::default());
+        assert_eq!((report.sent, report.received, report.invalid), (10, 2, 2));
+    }
+
+    #[test]
+    fn lone_hostile_header() {
+        let mut receiver = LinkTestReceiver::new();
+
+        let mut hostile = packet(u32::MAX - 1, 10);
+        hostile[7..11].copy_from_slice(&u32::MAX.to_le_bytes());
+        receiver.receive_at(&hostile, Instant::now());
+
+        assert!(!receiver.is_complete());
+        assert!(receiver.last_packet().is_some());
+
+        let report = receiver.report(RadioConfig::default());
+        assert_eq!((report.sent, report.received), (u32::MAX, 1));
+    }
+
+    #[test]
+    fn malformed_packets() {
+        let mut receiver = LinkTestReceiver::new();
+        let now = Instant::now();
+
+        let mut unknown_pattern = packet(0, 2);
+        unknown_pattern[2] = 9;
+
+        assert_eq!(receiver.receive_at(&[], now), None);
+        assert_eq!(receiver.receive_at(&LINKTEST_MAGIC, now), None);
+        assert_eq!(receiver.receive_at(&unknown_pattern, now), None);
+        assert_eq!(receiver.receive_at(&packet(0, 1), now), Some(0));
+        assert_eq!(receiver.receive_at(&packet(1, 1), now), None);
+
+        assert_eq!(receiver.report(RadioConfig::default()).invalid, 4);
+    }
+}
@@ -0,0 +1,192 @@
+#![allow(dead_code)]
+
+use std::{fmt, path::{Path, PathBuf}, thread, time::Duration};
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::i2c::{self, ErrorKind, NoAcknowledgeSource, Operation, SevenBitAddress};
+use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+use i2cdev::core::{I2CMessage, I2CTransfer};
+use i2cdev::linux::{LinuxI2CDevice, LinuxI2CError, LinuxI2CMessage};
+
+/// embedded-hal I2c implementation for linux i2c adapters (/dev/i2c-*)
+///
+/// wraps an i2cdev device and re-targets it whenever the
+/// requested address changes
+pub struct LinuxI2c {
+    dev: LinuxI2CDevice,
+    path: PathBuf,
+    address: u16,
+}
+
+impl LinuxI2c {
+    /// opens the i2c adapter at the given path
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<LinuxI2c, LinuxI2cError> {
+        let dev = LinuxI2CDevice::new(&path, 0)?;
+
+        Ok(LinuxI2c { dev, path: path.as_ref().to_path_buf(), address: 0 })
+    }
+
+    /// the path of the adapter this bus was opened on
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn set_address(&mut self, address: u16) -> Result<(), LinuxI2cError> {
+        if self.address != address {
+            self.dev.set_slave_address(address)?;
+            self.address = address;
+        }
+
+        Ok(())
+    }
+}
+
+impl i2c::ErrorType for LinuxI2c {
+    type Error = LinuxI2cError;
+}
+
+impl i2c::I2c<SevenBitAddress> for LinuxI2c {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        self.set_address(address as u16)?;
+
+        let mut messages: Vec<LinuxI2CMessage> = operations.iter_mut().map(|op| match op {
+            Operation::Write(w) => LinuxI2CMessage::write(w),
+            Operation::Read(r) => LinuxI2CMessage::read(r),
+        }).collect();
+
+        self.dev.transfer(&mut messages)?;
+
+        Ok(())
+    }
+}
+
+/// error returned by `LinuxI2c`, carries the underlying i2cdev error
+#[derive(Debug)]
+pub struct LinuxI2cError(pub LinuxI2CError);
+
+impl From<LinuxI2CError> for LinuxI2cError {
+    fn from(err: LinuxI2CError) -> Self {
+        LinuxI2cError(err)
+    }
+}
+
+impl fmt::Display for LinuxI2cError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl i2c::Error for LinuxI2cError {
+    fn kind(&self) -> ErrorKind {
+        let errno = match &self.0 {
+            LinuxI2CError::Errno(n) => *n,
+            LinuxI2CError::Io(e) => match e.raw_os_error() {
+                Some(n) => n,
+                None => {return ErrorKind::Other;},
+            },
+        };
+
+        // see https://www.kernel.org/doc/html/latest/i2c/fault-codes.html
+        match errno {
+            libc::EBUSY | libc::EINVAL => ErrorKind::Bus,
+            libc::EAGAIN => ErrorKind::ArbitrationLoss,
+            // many adapters (the Raspberry Pi's included) report every NACK this way,
+            // so a busy board is retried instead of failing the transfer
+            libc::EREMOTEIO => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            // a fault of the adapter or bus, not the board being busy, so it isn't retried
+            libc::EIO => ErrorKind::Bus,
+            libc::ENODEV => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+            libc::ENXIO => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// embedded-hal DelayNs implementation that sleeps the current thread
+#[derive(Clone, Copy, Default)]
+pub struct StdDelay;
+
+impl DelayNs for StdDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        thread::sleep(Duration::from_nanos(ns as u64));
+    }
+}
+
+/// embedded-hal OutputPin implementation for a line on a linux gpio
+/// character device (/dev/gpiochip*)
+///
+/// useful for wiring the Radio boards reset line, see `Radio::with_reset_pin`
+pub struct LinuxGpioPin {
+    handle: LineHandle,
+}
+
+impl LinuxGpioPin {
+    /// requests `line` on the given gpio chip as an output
+    ///
+    /// the line starts high, which leaves an active low reset line released
+    pub fn new<P: AsRef<Path>>(chip_path: P, line: u32) -> Result<LinuxGpioPin, LinuxGpioError> {
+        let mut chip = Chip::new(chip_path)?;
+        let handle = chip.get_line(line)?.request(LineRequestFlags::OUTPUT, 1, "armlab-radio")?;
+
+        Ok(LinuxGpioPin { handle })
+    }
+}
+
+impl digital::ErrorType for LinuxGpioPin {
+    type Error = LinuxGpioError;
+}
+
+impl OutputPin for LinuxGpioPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.handle.set_value(0)?;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.handle.set_value(1)?;
+        Ok(())
+    }
+}
+
+/// error returned by `LinuxGpioPin`, carries the underlying gpio-cdev error
+#[derive(Debug)]
+pub struct LinuxGpioError(pub gpio_cdev::Error);
+
+impl From<gpio_cdev::Error> for LinuxGpioError {
+    fn from(err: gpio_cdev::Error) -> Self {
+        LinuxGpioError(err)
+    }
+}
+
+impl fmt::Display for LinuxGpioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl digital::Error for LinuxGpioError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::i2c::Error as _;
+
+    fn kind(errno: i32) -> ErrorKind {
+        LinuxI2cError(LinuxI2CError::Errno(errno)).kind()
+    }
+
+    #[test]
+    fn only_nacks_are_retried() {
+        assert_eq!(kind(libc::EREMOTEIO), ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown));
+        assert_eq!(kind(libc::ENXIO), ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+
+        assert_eq!(kind(libc::EIO), ErrorKind::Bus);
+        assert_eq!(kind(libc::EAGAIN), ErrorKind::ArbitrationLoss);
+        assert_eq!(kind(libc::ETIMEDOUT), ErrorKind::Other);
+    }
+}
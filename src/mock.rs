@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::protocol::{Command, ModulationFormat};
+use crate::transceiver::{RadioConfig, Transceiver};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RadioError {
+    /// the packet is longer than `MAX_PACKET_LEN`
+    InvalidArgument,
+    /// set with `MockRadio::set_connected(false)`
+    Disconnected,
+}
+
+type Queue = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+fn lock(queue: &Queue) -> MutexGuard<'_, VecDeque<Vec<u8>>> {
+    match queue.lock() {
+        Ok(n) => n,
+        Err(n) => n.into_inner(),
+    }
+}
+
+/// an in memory Radio for testing tooling without hardware
+///
+/// packets queued with `push_rx` are returned by `get_packet` in order,
+/// transmitted packets are kept for inspection, or only delivered to the
+/// other end when created with `MockRadio::pair`, so long running pairs
+/// don't grow without bound
+pub struct MockRadio {
+    rx: Queue,
+    /// the other end's rx queue, if paired
+    peer: Option<Queue>,
+    /// only recorded while unpaired
+    transmitted: Vec<Vec<u8>>,
+    config: RadioConfig,
+    connected: bool,
+}
+
+impl Default for MockRadio {
+    fn default() -> Self {
+        MockRadio::new()
+    }
+}
+
+impl MockRadio {
+    /// a Radio with nothing to recieve
+    pub fn new() -> MockRadio {
+        MockRadio {
+            rx: Arc::new(Mutex::new(VecDeque::new())),
+            peer: None,
+            transmitted: vec![],
+            config: RadioConfig::default(),
+            connected: true,
+        }
+    }
+
+    /// two Radios on the same channel, each recieves what the other transmits
+    ///
+    /// both ends can be moved to different threads
+    pub fn pair() -> (MockRadio, MockRadio) {
+        let mut a = MockRadio::new();
+        let mut b = MockRadio::new();
+
+        a.peer = Some(b.rx.clone());
+        b.peer = Some(a.rx.clone());
+
+        return (a, b);
+    }
+
+    /// queues a packet to be returned by `get_packet`
+    pub fn push_rx(&mut self, packet: &[u8]) {
+        lock(&self.rx).push_back(packet.to_vec());
+    }
+
+    /// the number of packets waiting to be recieved
+    pub fn pending_rx(&self) -> usize {
+        lock(&self.rx).len()
+    }
+
+    /// every packet transmitted so far, oldest first, always empty for a paired Radio
+    pub fn transmitted(&self) -> &[Vec<u8>] {
+        &self.transmitted
+    }
+
+    /// removes and returns every packet transmitted so far
+    pub fn take_transmitted(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.transmitted)
+    }
+
+    /// the settings applied so far
+    pub fn config(&self) -> &RadioConfig {
+        &self.config
+    }
+
+    /// while disconnected every call fails with `RadioError::Disconnected`
+    pub fn set_connected(&mut self, connected: bool) {
+        self.connected = connected;
+    }
+
+    fn check(&self) -> Result<(), RadioError> {
+        match self.connected {
+            true => Ok(()),
+            false => Err(RadioError::Disconnected),
+        }
+    }
+}
+
+impl Transceiver for MockRadio {
+    type Error = RadioError;
+
+    fn transmit(&mut self, msg: &[u8]) -> Result<(), RadioError> {
+        self.check()?;
+
+        match Command::transmit(msg) {
+            Ok(_) => {},
+            Err(_) => {return Err(RadioError::InvalidArgument);},
+        };
+
+        match &self.peer {
+            Some(peer) => lock(peer).push_back(msg.to_vec()),
+            None => self.transmitted.push(msg.to_vec()),
+        };
+
+        return Ok(());
+    }
+
+    fn get_packet(&mut self) -> Result<Vec<u8>, RadioError> {
+        self.check()?;
+
+        return Ok(lock(&self.rx).pop_front().unwrap_or_default());
+    }
+
+    fn set_frequency(&mut self, frequency: f32) -> Result<(), RadioError> {
+        self.check()?;
+        self.config.frequency = Some(frequency);
+        return Ok(());
+    }
+
+    fn set_power(&mut self, power: f32) -> Result<(), RadioError> {
+        self.check()?;
+        self.config.power = Some(power);
+        return Ok(());
+    }
+
+    fn set_deviation(&mut self, deviation: f32) -> Result<(), RadioError> {
+        self.check()?;
+        self.config.deviation = Some(deviation);
+        return Ok(());
+    }
+
+    fn set_symbol_rate(&mut self, symbol_rate: f32) -> Result<(), RadioError> {
+        self.check()?;
+        self.config.symbol_rate = Some(symbol_rate);
+        return Ok(());
+    }
+
+    fn set_rx_filter(&mut self, rx_filter: f32) -> Result<(), RadioError> {
+        self.check()?;
+        self.config.rx_filter = Some(rx_filter);
+        return Ok(());
+    }
+
+    fn set_modulation(&mut self, mode: ModulationFormat) -> Result<(), RadioError> {
+        self.check()?;
+        self.config.modulation = Some(mode);
+        return Ok(());
+    }
+
+    /// clears the settings and anything waiting to be recieved
+    fn radio_reset(&mut self) -> Result<(), RadioError> {
+        self.check()?;
+        self.config = RadioConfig::default();
+        lock(&self.rx).clear();
+        return Ok(());
+    }
+
+    fn soft_reset(&mut self) -> Result<(), RadioError> {
+        self.radio_reset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::MAX_PACKET_LEN;
+
+    #[test]
+    fn unpaired_keeps_transmitted() {
+        let mut radio = MockRadio::new();
+        radio.transmit(b"one").unwrap();
+        radio.transmit(b"two").unwrap();
+
+        assert_eq!(radio.transmitted(), &[b"one".to_vec(), b"two".to_vec()]);
+        assert_eq!(radio.take_transmitted().len(), 2);
+        assert!(radio.transmitted().is_empty());
+        assert!(radio.get_packet().unwrap().is_empty());
+    }
+
+    #[test]
+    fn pair_delivers_without_recording() {
+        let (mut a, mut b) = MockRadio::pair();
+
+        for _ in 0..1000 {
+            a.transmit(b"ping").unwrap();
+            assert_eq!(b.get_packet().unwrap(), b"ping");
+        }
+
+        assert!(a.transmitted().is_empty());
+        assert_eq!(b.pending_rx(), 0);
+    }
+
+    #[test]
+    fn errors() {
+        let mut radio = MockRadio::new();
+
+        assert_eq!(radio.transmit(&[0; MAX_PACKET_LEN + 1]), Err(RadioError::InvalidArgument));
+
+        radio.set_connected(false);
+        assert_eq!(radio.transmit(b"x"), Err(RadioError::Disconnected));
+        assert_eq!(radio.get_packet(), Err(RadioError::Disconnected));
+        assert!(radio.transmitted().is_empty());
+    }
+}
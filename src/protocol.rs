@@ -0,0 +1,293 @@
+use core::ops::Deref;
+
+/// message the Radio firmware answers an ident request with
+pub const IDENT_MSG: &str = "ArmLabCC1200";
+
+/// every command sent to the Radio is exactly this many bytes
+pub const CMD_LEN: usize = 5;
+
+/// the largest payload the Radio will transmit or return in a single packet
+pub const MAX_PACKET_LEN: usize = u8::MAX as usize;
+
+pub const CMD_IDENT: u8 = 0x00;
+pub const CMD_TRANSMIT: u8 = 0x01;
+pub const CMD_GET_PACKET: u8 = 0x02;
+pub const CMD_FREQUENCY: u8 = 0x03;
+pub const CMD_POWER: u8 = 0x04;
+pub const CMD_DEVIATION: u8 = 0x05;
+pub const CMD_SYMBOL_RATE: u8 = 0x06;
+pub const CMD_RX_FILTER: u8 = 0x07;
+pub const CMD_MODULATION: u8 = 0x08;
+pub const CMD_RADIO_RESET: u8 = 0x09;
+pub const CMD_SOFT_RESET: u8 = 0x0A;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// payload is longer than `MAX_PACKET_LEN`
+    PacketTooLong,
+    /// command id or argument is not part of the protocol
+    UnknownCommand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModulationFormat {
+    FSK2 = 0x0,
+    GFSK2 = 0x1,
+    ASK = 0x3,
+    FSK4 = 0x4,
+    GFSK4 = 0x5,
+}
+
+impl TryFrom<u8> for ModulationFormat {
+    type Error = ProtocolError;
+
+    fn try_from(val: u8) -> Result<ModulationFormat, ProtocolError> {
+        match val {
+            0x0 => Ok(ModulationFormat::FSK2),
+            0x1 => Ok(ModulationFormat::GFSK2),
+            0x3 => Ok(ModulationFormat::ASK),
+            0x4 => Ok(ModulationFormat::FSK4),
+            0x5 => Ok(ModulationFormat::GFSK4),
+            _ => Err(ProtocolError::UnknownCommand),
+        }
+    }
+}
+
+/// a single command frame understood by the Radio firmware
+///
+/// frames are `CMD_LEN` bytes: a command id followed by up to 4 argument bytes,
+/// f32 arguments are sent little endian (matching the STM32)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Ident,
+    /// announces a payload of the given length will follow
+    Transmit(u8),
+    /// requests the length of the next packet, followed by the packet
+    GetPacket,
+    Frequency(f32),
+    Power(f32),
+    Deviation(f32),
+    SymbolRate(f32),
+    RxFilter(f32),
+    Modulation(ModulationFormat),
+    RadioReset,
+    SoftReset,
+}
+
+impl Command {
+    /// creates the transmit command announcing `msg`
+    pub fn transmit(msg: &[u8]) -> Result<Command, ProtocolError> {
+        if msg.len() > MAX_PACKET_LEN {
+            return Err(ProtocolError::PacketTooLong);
+        }
+
+        Ok(Command::Transmit(msg.len() as u8))
+    }
+
+    /// the command id byte for this command
+    pub fn id(&self) -> u8 {
+        match self {
+            Command::Ident => CMD_IDENT,
+            Command::Transmit(_) => CMD_TRANSMIT,
+            Command::GetPacket => CMD_GET_PACKET,
+            Command::Frequency(_) => CMD_FREQUENCY,
+            Command::Power(_) => CMD_POWER,
+            Command::Deviation(_) => CMD_DEVIATION,
+            Command::SymbolRate(_) => CMD_SYMBOL_RATE,
+            Command::RxFilter(_) => CMD_RX_FILTER,
+            Command::Modulation(_) => CMD_MODULATION,
+            Command::RadioReset => CMD_RADIO_RESET,
+            Command::SoftReset => CMD_SOFT_RESET,
+        }
+    }
+
+    /// encodes the command into the frame sent over the wire
+    pub fn encode(&self) -> [u8; CMD_LEN] {
+        let mut buf: [u8; CMD_LEN] = [self.id(), 0x00, 0x00, 0x00, 0x00];
+
+        match *self {
+            Command::Transmit(len) => {buf[1] = len;},
+            Command::Modulation(mode) => {buf[1] = mode as u8;},
+            Command::Frequency(val) |
+            Command::Power(val) |
+            Command::Deviation(val) |
+            Command::SymbolRate(val) |
+            Command::RxFilter(val) => {
+                buf[1..].copy_from_slice(&val.to_le_bytes());
+            },
+            _ => {},
+        };
+
+        return buf;
+    }
+
+    /// decodes a frame produced by `Command::encode`
+    pub fn decode(buf: &[u8; CMD_LEN]) -> Result<Command, ProtocolError> {
+        let val = f32::from_le_bytes([buf[1], buf[2], buf[3], buf[4]]);
+
+        let cmd = match buf[0] {
+            CMD_IDENT => Command::Ident,
+            CMD_TRANSMIT => Command::Transmit(buf[1]),
+            CMD_GET_PACKET => Command::GetPacket,
+            CMD_FREQUENCY => Command::Frequency(val),
+            CMD_POWER => Command::Power(val),
+            CMD_DEVIATION => Command::Deviation(val),
+            CMD_SYMBOL_RATE => Command::SymbolRate(val),
+            CMD_RX_FILTER => Command::RxFilter(val),
+            CMD_MODULATION => Command::Modulation(ModulationFormat::try_from(buf[1])?),
+            CMD_RADIO_RESET => Command::RadioReset,
+            CMD_SOFT_RESET => Command::SoftReset,
+            _ => {return Err(ProtocolError::UnknownCommand);},
+        };
+
+        return Ok(cmd);
+    }
+}
+
+/// fixed capacity buffer holding a single packet
+///
+/// allocation free, so it can be used on targets without a heap
+#[derive(Clone)]
+pub struct Packet {
+    buf: [u8; MAX_PACKET_LEN],
+    len: usize,
+}
+
+impl Packet {
+    /// creates an empty packet
+    pub const fn new() -> Packet {
+        Packet { buf: [0u8; MAX_PACKET_LEN], len: 0 }
+    }
+
+    /// creates a packet holding a copy of `data`
+    pub fn from_slice(data: &[u8]) -> Result<Packet, ProtocolError> {
+        let mut packet = Packet::new();
+        packet.resize(data.len())?.copy_from_slice(data);
+
+        return Ok(packet);
+    }
+
+    /// the number of bytes in the packet
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// wheather or not the packet holds any bytes
+    ///
+    /// the Radio reports an empty packet when nothing has been recieved
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// the byte data of the packet
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// empties the packet
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// sets the length of the packet
+    ///
+    /// ## Returns
+    /// the packet contents, for filling in place
+    pub fn resize(&mut self, len: usize) -> Result<&mut [u8], ProtocolError> {
+        if len > MAX_PACKET_LEN {
+            return Err(ProtocolError::PacketTooLong);
+        }
+
+        self.len = len;
+        return Ok(&mut self.buf[..len]);
+    }
+}
+
+impl Default for Packet {
+    fn default() -> Packet {
+        Packet::new()
+    }
+}
+
+impl Deref for Packet {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl PartialEq for Packet {
+    fn eq(&self, other: &Packet) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl core::fmt::Debug for Packet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("Packet").field(&self.as_slice()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_round_trip() {
+        let commands = [
+            Command::Ident,
+            Command::Transmit(0),
+            Command::Transmit(255),
+            Command::GetPacket,
+            Command::Frequency(915.0),
+            Command::Power(-10.5),
+            Command::Deviation(25.0),
+            Command::SymbolRate(38.4),
+            Command::RxFilter(100.0),
+            Command::Modulation(ModulationFormat::GFSK4),
+            Command::RadioReset,
+            Command::SoftReset,
+        ];
+
+        for cmd in commands {
+            assert_eq!(Command::decode(&cmd.encode()), Ok(cmd));
+        }
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(Command::Transmit(12).encode(), [CMD_TRANSMIT, 12, 0, 0, 0]);
+        assert_eq!(Command::Modulation(ModulationFormat::ASK).encode(), [CMD_MODULATION, 3, 0, 0, 0]);
+
+        // f32 arguments are little endian
+        assert_eq!(Command::Frequency(1.0).encode(), [CMD_FREQUENCY, 0x00, 0x00, 0x80, 0x3F]);
+    }
+
+    #[test]
+    fn malformed_commands() {
+        assert_eq!(Command::decode(&[0x0B, 0, 0, 0, 0]), Err(ProtocolError::UnknownCommand));
+        assert_eq!(Command::decode(&[0xFF, 0, 0, 0, 0]), Err(ProtocolError::UnknownCommand));
+
+        // 0x2 is not a modulation format
+        assert_eq!(Command::decode(&[CMD_MODULATION, 2, 0, 0, 0]), Err(ProtocolError::UnknownCommand));
+    }
+
+    #[test]
+    fn transmit_length() {
+        assert_eq!(Command::transmit(&[0; MAX_PACKET_LEN]), Ok(Command::Transmit(255)));
+        assert_eq!(Command::transmit(&[0; MAX_PACKET_LEN + 1]), Err(ProtocolError::PacketTooLong));
+    }
+
+    #[test]
+    fn packet() {
+        let mut packet = Packet::from_slice(&[1, 2, 3]).unwrap();
+        assert_eq!(packet.as_slice(), &[1, 2, 3]);
+
+        assert_eq!(packet.resize(MAX_PACKET_LEN + 1), Err(ProtocolError::PacketTooLong));
+        assert_eq!(packet.len(), 3);
+
+        packet.clear();
+        assert!(packet.is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::protocol;
+use crate::radio_serial;
+use crate::shared::SharedRadio;
+
+#[cfg(all(feature = "i2c", target_os = "linux"))]
+use crate::radio_i2c::{self, LinuxRadio};
+
+create_exception!(armlab_radio, RadioError, PyException, "base class of every Radio error");
+create_exception!(armlab_radio, OpenError, RadioError, "the port or bus could not be opened");
+create_exception!(armlab_radio, DeviceDetectError, RadioError, "no Radio answered the ident request");
+create_exception!(armlab_radio, WriteError, RadioError, "a command could not be written to the Radio");
+create_exception!(armlab_radio, ReadError, RadioError, "a response could not be read from the Radio");
+create_exception!(armlab_radio, SyncTimeoutError, RadioError, "the serial command queue could not be synchronized");
+create_exception!(armlab_radio, PortDetectError, RadioError, "the system could not be scanned for ports / buses");
+create_exception!(armlab_radio, ResetError, RadioError, "the hardware reset line is missing or could not be driven");
+
+/// converts a serial RadioError into the matching python exception
+fn serial_err(err: radio_serial::RadioError) -> PyErr {
+    use radio_serial::RadioError::*;
+
+    let msg = format!("{:?}", err);
+    match err {
+        PortOpenError => OpenError::new_err(msg),
+        DevciceDetectError => DeviceDetectError::new_err(msg),
+        InvalidArgument => PyValueError::new_err(msg),
+        ReadLenError | ReadError => self::ReadError::new_err(msg),
+        WriteError | WriteLenError => self::WriteError::new_err(msg),
+        SyncTimeoutError => self::SyncTimeoutError::new_err(msg),
+        PortDetectError => self::PortDetectError::new_err(msg),
+    }
+}
+
+/// converts an i2c RadioError into the matching python exception
+#[cfg(all(feature = "i2c", target_os = "linux"))]
+fn i2c_err(err: radio_i2c::RadioError) -> PyErr {
+    use radio_i2c::RadioError::*;
+
+    let msg = format!("{:?}", err);
+    match err {
+        I2CInitError => OpenError::new_err(msg),
+        DeviceDetectError => self::DeviceDetectError::new_err(msg),
+        TransmitMsgLen | TransmitMsg | TransmitError | RecieveCmd => WriteError::new_err(msg),
+        RecieveReadLen | RecieveReadMsg | ReadLengthMismatch => ReadError::new_err(msg),
+        InvalidArgument => PyValueError::new_err(msg),
+        BusDetectError => PortDetectError::new_err(msg),
+        NoResetPin | ResetPinError => ResetError::new_err(msg),
+    }
+}
+
+/// modulation formats supported by the Radio
+#[pyclass(name = "ModulationFormat", eq, eq_int)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum PyModulationFormat {
+    FSK2 = 0x0,
+    GFSK2 = 0x1,
+    ASK = 0x3,
+    FSK4 = 0x4,
+    GFSK4 = 0x5,
+}
+
+impl From<PyModulationFormat> for protocol::ModulationFormat {
+    fn from(mode: PyModulationFormat) -> protocol::ModulationFormat {
+        match mode {
+            PyModulationFormat::FSK2 => protocol::ModulationFormat::FSK2,
+            PyModulationFormat::GFSK2 => protocol::ModulationFormat::GFSK2,
+            PyModulationFormat::ASK => protocol::ModulationFormat::ASK,
+            PyModulationFormat::FSK4 => protocol::ModulationFormat::FSK4,
+            PyModulationFormat::GFSK4 => protocol::ModulationFormat::GFSK4,
+        }
+    }
+}
+
+/// implements a Radio class, the methods shared by both backends followed
+/// by any backend specific ones, releasing the GIL for every command
+macro_rules! radio_methods {
+    ($class:ident, $err:ident, $($extra:tt)*) => {
+        #[pymethods]
+        impl $class {
+            $($extra)*
+
+            /// transmits the given message, at most 255 bytes
+            fn transmit(&self, py: Python<'_>, msg: &[u8]) -> PyResult<()> {
+                py.allow_threads(|| self.radio.with(|r| r.transmit(msg))).map_err($err)
+            }
+
+            /// gets the most recent packet stored on the Radio, b"" if there was none
+            fn get_packet<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+                let packet = py.allow_threads(|| self.radio.with(|r| r.get_packet())).map_err($err)?;
+                Ok(PyBytes::new(py, &packet))
+            }
+
+            /// attempts to set the frequency of the radio (Hz)
+            fn set_frequency(&self, py: Python<'_>, frequency: f32) -> PyResult<()> {
+                py.allow_threads(|| self.radio.with(|r| r.set_frequency(frequency))).map_err($err)
+            }
+
+            /// attempts to set the tx gain of the radio
+            fn set_power(&self, py: Python<'_>, power: f32) -> PyResult<()> {
+                py.allow_threads(|| self.radio.with(|r| r.set_power(power))).map_err($err)
+            }
+
+            /// attempts to set the FSK bandwith of the radio
+            fn set_deviation(&self, py: Python<'_>, deviation: f32) -> PyResult<()> {
+                py.allow_threads(|| self.radio.with(|r| r.set_deviation(deviation))).map_err($err)
+            }
+
+            /// attempts to set the symbol rate of the radio
+            fn set_symbol_rate(&self, py: Python<'_>, symbol_rate: f32) -> PyResult<()> {
+                py.allow_threads(|| self.radio.with(|r| r.set_symbol_rate(symbol_rate))).map_err($err)
+            }
+
+            /// attempts to set the recieve filter of the radio
+            fn set_rx_filter(&self, py: Python<'_>, rx_filter: f32) -> PyResult<()> {
+                py.allow_threads(|| self.radio.with(|r| r.set_rx_filter(rx_filter))).map_err($err)
+            }
+
+            /// sets the modulation mode of the radio
+            fn set_modulation(&self, py: Python<'_>, mode: PyModulationFormat) -> PyResult<()> {
+                py.allow_threads(|| self.radio.with(|r| r.set_modulation(mode.into()))).map_err($err)
+            }
+
+            /// sends a reset command to reset the onboard Radio chip
+            fn radio_reset(&self, py: Python<'_>) -> PyResult<()> {
+                py.allow_threads(|| self.radio.with(|r| r.radio_reset())).map_err($err)
+            }
+
+            /// sends a reset command to perform a soft reset on the entire board
+            fn soft_reset(&self, py: Python<'_>) -> PyResult<()> {
+                py.allow_threads(|| self.radio.with(|r| r.soft_reset())).map_err($err)
+            }
+
+            /// queries the radio and checks if it is available
+            fn is_device_available(&self, py: Python<'_>) -> bool {
+                py.allow_threads(|| self.radio.with(|r| r.is_device_available()))
+            }
+        }
+    };
+}
+
+/// Radio connected over serial, see `radio_serial::Radio`
+///
+/// SerialRadio(port, bare=False), `bare` skips the existence
+/// check and synchronization
+#[pyclass(name = "SerialRadio")]
+pub struct PySerialRadio {
+    radio: SharedRadio<radio_serial::Radio>,
+}
+
+radio_methods!(PySerialRadio, serial_err,
+    #[new]
+    #[pyo3(signature = (port, bare=false))]
+    fn new(py: Python<'_>, port: &str, bare: bool) -> PyResult<PySerialRadio> {
+        let radio = py.allow_threads(|| match bare {
+            true => radio_serial::Radio::new_bare(port),
+            false => radio_serial::Radio::new(port),
+        }).map_err(serial_err)?;
+
+        Ok(PySerialRadio { radio: SharedRadio::new(radio) })
+    }
+
+    /// ensures the Radio command queue is in sync, returns the number of bytes sent
+    #[pyo3(signature = (timeout_iter=6))]
+    fn sync(&self, py: Python<'_>, timeout_iter: usize) -> PyResult<usize> {
+        py.allow_threads(|| self.radio.with(|r| r.sync(timeout_iter))).map_err(serial_err)
+    }
+);
+
+/// Radio connected over a linux i2c bus, see `radio_i2c::Radio`
+///
+/// I2cRadio(bus="/dev/i2c-1")
+#[cfg(all(feature = "i2c", target_os = "linux"))]
+#[pyclass(name = "I2cRadio")]
+pub struct PyI2cRadio {
+    radio: SharedRadio<LinuxRadio>,
+}
+
+#[cfg(all(feature = "i2c", target_os = "linux"))]
+radio_methods!(PyI2cRadio, i2c_err,
+    #[new]
+    #[pyo3(signature = (bus="/dev/i2c-1"))]
+    fn new(py: Python<'_>, bus: &str) -> PyResult<PyI2cRadio> {
+        let radio = py.allow_threads(|| radio_i2c::Radio::new(bus)).map_err(i2c_err)?;
+
+        Ok(PyI2cRadio { radio: SharedRadio::new(radio) })
+    }
+
+    /// the i2c address the Radio is being addressed at
+    fn address(&self) -> u8 {
+        self.radio.with(|r| r.address())
+    }
+
+    /// measures the boards command latency and adapts the i2c delays to it
+    ///
+    /// returns (min, max, mean) latency in us
+    #[pyo3(signature = (samples=8))]
+    fn calibrate(&self, py: Python<'_>, samples: u32) -> PyResult<(u32, u32, u32)> {
+        let report = py.allow_threads(|| self.radio.with(|r| r.calibrate(samples))).map_err(i2c_err)?;
+
+        Ok((report.min_latency, report.max_latency, report.mean_latency))
+    }
+);
+
+/// names of every serial port on the system
+#[pyfunction]
+fn get_open_ports() -> PyResult<Vec<String>> {
+    radio_serial::get_open_ports().map_err(serial_err)
+}
+
+/// names of every serial port with a Radio attached
+#[pyfunction]
+fn get_radio_ports() -> PyResult<Vec<String>> {
+    radio_serial::get_radio_ports().map_err(serial_err)
+}
+
+/// (bus, address) of every Radio on every i2c adapter
+#[cfg(all(feature = "i2c", target_os = "linux"))]
+#[pyfunction]
+fn discover_i2c(py: Python<'_>) -> PyResult<Vec<(String, u8)>> {
+    py.allow_threads(radio_i2c::discover).map_err(i2c_err)
+}
+
+#[pymodule]
+fn armlab_radio(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+
+    m.add_class::<PyModulationFormat>()?;
+    m.add_class::<PySerialRadio>()?;
+    m.add_function(wrap_pyfunction!(get_open_ports, m)?)?;
+    m.add_function(wrap_pyfunction!(get_radio_ports, m)?)?;
+
+    #[cfg(all(feature = "i2c", target_os = "linux"))]
+    {
+        m.add_class::<PyI2cRadio>()?;
+        m.add_function(wrap_pyfunction!(discover_i2c, m)?)?;
+    }
+
+    m.add("RadioError", py.get_type::<RadioError>())?;
+    m.add("OpenError", py.get_type::<OpenError>())?;
+    m.add("DeviceDetectError", py.get_type::<DeviceDetectError>())?;
+    m.add("WriteError", py.get_type::<WriteError>())?;
+    m.add("ReadError", py.get_type::<ReadError>())?;
+    m.add("SyncTimeoutError", py.get_type::<SyncTimeoutError>())?;
+    m.add("PortDetectError", py.get_type::<PortDetectError>())?;
+    m.add("ResetError", py.get_type::<ResetError>())?;
+
+    Ok(())
+}
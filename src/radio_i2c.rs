@@ -1,20 +1,32 @@
 #![allow(dead_code)]
 
-use std::{time::Duration, thread};
-use i2cdev::linux::LinuxI2CDevice as I2c;
+use core::convert::Infallible;
 
-const IDENT_MSG: &str = "ArmLabCC1200";
-const ADDR: u16 = 0x34;
-const BACKUP_ADDR: u16 = 0x35;
+use embedded_hal::i2c::{I2c, Error as _, ErrorKind};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, OutputPin};
 
+#[cfg(all(feature = "std", target_os = "linux"))]
+use std::fs;
+#[cfg(all(feature = "std", target_os = "linux"))]
+use crate::linux_hal::{LinuxI2c, StdDelay};
+
+use crate::protocol::{Command, Packet, IDENT_MSG};
+#[cfg(feature = "std")]
+use crate::transceiver::{Transceiver, impl_transceiver};
+pub use crate::protocol::ModulationFormat;
+
+const ADDR: u8 = 0x34;
+const BACKUP_ADDR: u8 = 0x35;
+
+/// every address a Radio can be configured to respond on
+const CANDIDATE_ADDRS: [u8; 2] = [ADDR, BACKUP_ADDR];
+
+/// first wait after the board NACKs a step, doubled on every retry (us)
+const RETRY_BACKOFF_MIN: u32 = 50;
+/// longest single wait between retries (us)
+const RETRY_BACKOFF_MAX: u32 = 5_000;
 
-pub enum ModulationFormat {
-    FSK2 = 0x0,
-    GFSK2 = 0x1,
-    ASK = 0x3,
-    FSK4 = 0x4,
-    GFSK4 = 0x5,
-}
 
 #[derive(Debug)]
 pub enum RadioError {
@@ -28,105 +40,290 @@ pub enum RadioError {
     RecieveReadMsg,
     ReadLengthMismatch,
     InvalidArgument,
+    BusDetectError,
+    NoResetPin,
+    ResetPinError,
+}
+
+/// timing and polarity of the boards reset line
+/// 
+/// see `Radio::with_reset_pin`
+#[derive(Debug, Clone, Copy)]
+pub struct ResetConfig {
+    /// how long the reset line is held active (ms)
+    pub pulse_width: u32,
+    /// how long the board is given to boot after the line is released (ms)
+    pub post_reset_wait: u32,
+    /// wheather the line resets the board when driven low (NRST on the STM32)
+    pub active_low: bool,
+}
+
+impl Default for ResetConfig {
+    /// 10 ms active low pulse, followed by 250 ms for the board to boot
+    fn default() -> ResetConfig {
+        ResetConfig { pulse_width: 10, post_reset_wait: 250, active_low: true }
+    }
+}
+
+/// delays and retry policy used between the two halves of a transaction
+/// 
+/// see `Radio::set_timing` and `Radio::calibrate`
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    /// delay between requesting a packet and reading its length (us)
+    pub packet_gather_delay: u32,
+    /// delay between announcing a transmit and writing its payload (us)
+    pub write_wait_delay: u32,
+    /// how long a step keeps being retried while the board NACKs (us)
+    pub retry_timeout: u32,
+    /// wheather a delay is raised to the measured latency whenever
+    /// a step had to be retried, and lowered by an eighth whenever
+    /// it didn't, so it follows the board both ways
+    pub adaptive: bool,
+    /// lowest an adaptive delay is lowered to (us), so the board isn't
+    /// routinely caught busy, delays set lower than this are left alone
+    pub min_delay: u32,
+}
+
+impl Default for Timing {
+    /// 10 ms delays, retried for up to 50 ms, adapted down to 1 ms
+    ///
+    /// see `Radio::calibrate` to measure the delays instead
+    fn default() -> Timing {
+        Timing { packet_gather_delay: 10_000, write_wait_delay: 10_000, retry_timeout: 50_000, adaptive: true, min_delay: 1_000 }
+    }
+}
+
+/// latencies measured by `Radio::calibrate`, all in us
+/// 
+/// latency is the time between a command being written and the board
+/// acknowledging the follow up read, to the resolution of the retry backoff
+#[derive(Debug, Clone, Copy)]
+pub struct TimingReport {
+    pub samples: u32,
+    pub min_latency: u32,
+    pub max_latency: u32,
+    pub mean_latency: u32,
+    /// the timing the Radio was left with
+    pub timing: Timing,
+}
+
+/// placeholder reset pin for Radios without a wired reset line
+/// 
+/// can never be constructed, `Radio::reset` returns `RadioError::NoResetPin`
+pub enum NoResetPin {}
+
+impl digital::ErrorType for NoResetPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoResetPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        match *self {}
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        match *self {}
+    }
 }
 
 /// Radio (I2C) is a driver for interfacing with STM32 based radios over I2C
-/// This library supports I2C communication on all linux based platforms
 /// 
-/// `Radio::new("I2C_PATH")` to get started
+/// The driver is generic over the embedded-hal `I2c` and `DelayNs` traits, so
+/// the same code runs on linux hosts and bare-metal microcontrollers
+/// 
+/// `Radio::new("I2C_PATH")` to get started on linux
 /// 
 /// or
 /// 
 /// `Radio::new_rpi()` for raspberry pi os computers
-pub struct Radio {
-    pub i2c: I2c,
-    packet_wait_delay: u64,
-    write_wait_delay: u64,
-    
+/// 
+/// or
+/// 
+/// `Radio::from_bus(i2c, delay)` with any embedded-hal implementation
+/// 
+/// `Radio::with_reset_pin` attaches a gpio wired to the boards reset line
+pub struct Radio<I2C, D, RST = NoResetPin> {
+    pub i2c: I2C,
+    delay: D,
+    address: u8,
+    timing: Timing,
+    reset_pin: Option<RST>,
+    reset_config: ResetConfig,
 }
 
-// init
-impl Radio {    
+/// Radio on a linux i2c adapter
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub type LinuxRadio = Radio<LinuxI2c, StdDelay>;
+
+// init (linux)
+#[cfg(all(feature = "std", target_os = "linux"))]
+impl Radio<LinuxI2c, StdDelay> {
     /// creates a new Radio object on the given i2c bus and checks
     /// that the device detected is a Radio device
-    pub fn new(i2c_path: &str) -> Result<Radio, RadioError> {
-        let mut i2c = match I2c::new(i2c_path, ADDR) {
-            Ok(n) => {n},
+    pub fn new(i2c_path: &str) -> Result<LinuxRadio, RadioError> {
+        let i2c = match LinuxI2c::new(i2c_path) {
+            Ok(n) => n,
             Err(_) => {
-                return Err(RadioError::I2CInitError);                
+                return Err(RadioError::I2CInitError);
             }
         };
 
-        if !Radio::check_for_device(&mut i2c) {
-            i2c = match I2c::new(i2c_path, BACKUP_ADDR) {
-                Ok(n) => n,
-                Err(_) => {
-                    return Err(RadioError::I2CInitError);   
-                }
-            };
+        return Radio::from_bus(i2c, StdDelay);
+    }
 
-            if !Radio::check_for_device(&mut i2c) {
-                return Err(RadioError::DeviceDetectError);
+    /// creates a new Radio object at `address` on the given i2c bus and
+    /// checks that the device there is a Radio device
+    /// 
+    /// for picking one of several Radios found by `discover`
+    pub fn new_at(i2c_path: &str, address: u8) -> Result<LinuxRadio, RadioError> {
+        let i2c = match LinuxI2c::new(i2c_path) {
+            Ok(n) => n,
+            Err(_) => {
+                return Err(RadioError::I2CInitError);
             }
-        }
+        };
 
-        Ok(Radio { i2c, packet_wait_delay: 10, write_wait_delay: 10})
+        return Radio::from_bus_at(i2c, StdDelay, address);
     }
 
     /// creates a new Radio object on the default rpi i2c bus and
     /// checks that the device detected is a Radio device
-    pub fn new_rpi() -> Result<Radio, RadioError> {
+    pub fn new_rpi() -> Result<LinuxRadio, RadioError> {
         return Radio::new("/dev/i2c-1")
     }
+}
+
+// init
+impl<I2C: I2c, D: DelayNs> Radio<I2C, D> {
+    /// creates a new Radio object on an embedded-hal i2c bus and checks
+    /// that the device detected is a Radio device
+    /// 
+    /// the primary address is tried first, then the backup address
+    /// 
+    /// the delays start at `Timing::default`, nothing but the ident is sent
+    /// to the board, see `Radio::calibrate` to measure them
+    pub fn from_bus(mut i2c: I2C, delay: D) -> Result<Radio<I2C, D>, RadioError> {
+        let address = if check_for_device(&mut i2c, ADDR) {
+            ADDR
+        }
+        else if check_for_device(&mut i2c, BACKUP_ADDR) {
+            BACKUP_ADDR
+        }
+        else {
+            return Err(RadioError::DeviceDetectError);
+        };
+
+        Ok(Radio::at_address(i2c, delay, address))
+    }
+
+    /// creates a new Radio object at `address` on an embedded-hal i2c bus and
+    /// checks that the device there is a Radio device
+    /// 
+    /// no other address is tried, see `Radio::from_bus` for the rest
+    pub fn from_bus_at(mut i2c: I2C, delay: D, address: u8) -> Result<Radio<I2C, D>, RadioError> {
+        if !check_for_device(&mut i2c, address) {
+            return Err(RadioError::DeviceDetectError);
+        }
+
+        Ok(Radio::at_address(i2c, delay, address))
+    }
+
+    /// a Radio at an address already known to answer
+    fn at_address(i2c: I2C, delay: D, address: u8) -> Radio<I2C, D> {
+        Radio {
+            i2c,
+            delay,
+            address,
+            timing: Timing::default(),
+            reset_pin: None,
+            reset_config: ResetConfig::default(),
+        }
+    }
+}
+
+impl<I2C: I2c, D: DelayNs, RST: OutputPin> Radio<I2C, D, RST> {
+    /// attaches a gpio wired to the boards reset line, used by `Radio::reset`
+    /// 
+    /// the line is driven to its inactive level immediately
+    /// 
+    /// ## Returns
+    /// the Radio unchanged alongside `RadioError::ResetPinError` if the line
+    /// can't be driven, so the bus isn't lost with it
+    #[allow(clippy::type_complexity)]
+    pub fn with_reset_pin<P: OutputPin>(self, mut pin: P, config: ResetConfig) -> Result<Radio<I2C, D, P>, (Radio<I2C, D, RST>, RadioError)> {
+        if let Err(n) = set_reset_line(&mut pin, &config, false) {
+            return Err((self, n));
+        }
+
+        Ok(Radio {
+            i2c: self.i2c,
+            delay: self.delay,
+            address: self.address,
+            timing: self.timing,
+            reset_pin: Some(pin),
+            reset_config: config,
+        })
+    }
+
+    /// sets the pulse width, post reset wait and polarity used by `Radio::reset`
+    pub fn set_reset_config(&mut self, config: ResetConfig) {
+        self.reset_config = config;
+    }
+
+    /// releases the i2c bus, delay provider and reset pin held by the Radio
+    pub fn release(self) -> (I2C, D, Option<RST>) {
+        (self.i2c, self.delay, self.reset_pin)
+    }
+
+    /// the i2c address the Radio is being addressed at
+    pub fn address(&self) -> u8 {
+        self.address
+    }
 
     /// switches to the pre-programmed backup address for the Radio
     pub fn use_alt_address(&mut self) -> Result<(), RadioError> {
-        self.i2c = match I2c::new("/dev/i2c-1", BACKUP_ADDR) {
-            Ok(n) => n,
-            Err(_) => {
-                return Err(RadioError::I2CInitError);
-            }
-        };
+        self.address = BACKUP_ADDR;
 
         Ok(())
     }
 
     /// sets the delay between requesting a packet from the Radio
-    /// and expecting the packet to come through
+    /// and expecting the packet to come through (ms)
     /// 
     /// this gives the Radio time to gather the packet before i2c times out
     /// on non clock-stetching devices
     /// 
+    /// see `Radio::calibrate` to measure this instead
+    /// 
     /// ## Default
-    /// 10 ms
+    /// measured by `Radio::from_bus`, 10 ms if that fails
     pub fn set_packet_gather_delay(&mut self, delay: u64) {
-        self.packet_wait_delay = delay;
+        self.timing.packet_gather_delay = u32::try_from(delay.saturating_mul(1000)).unwrap_or(u32::MAX);
     }
 
     /// sets the delay between requesting to write a packet to the radio
-    /// and sending the packet to the radido
+    /// and sending the packet to the radido (ms)
     /// 
     /// this gives the Radio time to ready itself for recieve before i2c times out
     /// on non clock-stetching devices
+    /// 
+    /// see `Radio::calibrate` to measure this instead
+    /// 
     /// ## Default
-    /// 10 ms
+    /// measured by `Radio::from_bus`, 10 ms if that fails
     pub fn set_write_wait_delay(&mut self, delay: u64) {
-        self.write_wait_delay = delay;
+        self.timing.write_wait_delay = u32::try_from(delay.saturating_mul(1000)).unwrap_or(u32::MAX);
     }
 
-    /// queries the port to check if the radio is available
-    /// 
-    /// useful for in-constructor checks
-    fn check_for_device(i2c: &mut I2c) -> bool {
-        let mut buf: [u8; IDENT_MSG.len()] = [0u8; IDENT_MSG.len()];
-        match i2c.read(&mut buf) {
-            Ok(_) => {},
-            Err(_) => {
-                return false;
-            },
-        };
+    /// sets the delays and retry policy used between the two halves of a transaction
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
 
-        return buf == IDENT_MSG.as_bytes();
+    /// the delays currently in use, including any adaptive adjustments
+    pub fn timing(&self) -> Timing {
+        self.timing
     }
 
     /// queries the radio and checks if it is available
@@ -134,193 +331,503 @@ impl Radio {
     /// ## Returns
     /// wheather or not a device was found
     pub fn is_device_available(&mut self) -> bool{
-        return Radio::check_for_device(&mut self.i2c);
+        return check_for_device(&mut self.i2c, self.address);
     }
 
 
 }
 
-// transmit / recieve / reset
-impl Radio {
-    /// transmits the given message
-    pub fn transmit(&mut self, msg: &[u8]) -> Result<(), RadioError> {
+/// queries the bus to check if a radio is available at the given address
+/// 
+/// useful for in-constructor checks
+fn check_for_device<I2C: I2c>(i2c: &mut I2C, address: u8) -> bool {
+    let mut buf: [u8; IDENT_MSG.len()] = [0u8; IDENT_MSG.len()];
+    match i2c.read(address, &mut buf) {
+        Ok(_) => {},
+        Err(_) => {
+            return false;
+        },
+    };
+
+    return buf == IDENT_MSG.as_bytes();
+}
+
+/// the next value of an adaptive delay after a step that waited `waited` us
+/// 
+/// a step that had to be retried raises the delay to its latency, one that
+/// succeeded first time lowers it by an eighth, but not below `floor`
+fn adapt_delay(delay: u32, waited: u32, floor: u32) -> u32 {
+    match waited > delay {
+        true => waited,
+        false => (delay - delay / 8).max(floor.min(delay)),
+    }
+}
+
+/// drives the reset line to its active (resetting) or inactive level
+fn set_reset_line<P: OutputPin>(pin: &mut P, config: &ResetConfig, active: bool) -> Result<(), RadioError> {
+    // active low lines are driven low to reset
+    let res = match active != config.active_low {
+        true => pin.set_high(),
+        false => pin.set_low(),
+    };
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(_) => Err(RadioError::ResetPinError),
+    }
+}
+
+// timing
+impl<I2C: I2c, D: DelayNs, RST: OutputPin> Radio<I2C, D, RST> {
+    /// runs `op` after waiting `initial` us, retrying with a growing backoff
+    /// for as long as the board NACKs and `retry_timeout` allows
+    /// 
+    /// ## Returns
+    /// the result of `op` and the total time waited before it succeeded (us)
+    fn with_retry<T, F>(&mut self, initial: u32, mut op: F) -> Result<(T, u32), ()>
+    where F: FnMut(&mut I2C, u8) -> Result<T, I2C::Error> {
+        self.delay.delay_us(initial);
+
+        let mut waited = initial;
+        let mut backoff = RETRY_BACKOFF_MIN;
+
+        loop {
+            match op(&mut self.i2c, self.address) {
+                Ok(n) => {
+                    return Ok((n, waited));
+                },
+                Err(e) => {
+                    let nack = matches!(e.kind(), ErrorKind::NoAcknowledge(_));
+                    if !nack || waited >= self.timing.retry_timeout {
+                        return Err(());
+                    }
+                },
+            };
 
-        if msg.len() > u8::MAX as usize {
-            return Err(RadioError::InvalidArgument);
+            self.delay.delay_us(backoff);
+            waited = waited.saturating_add(backoff);
+            backoff = (backoff * 2).min(RETRY_BACKOFF_MAX);
         }
+    }
 
-        let mut buf: [u8; 5] = [0x01, msg.len() as u8, 0x00, 0x00, 0x00];
+    /// measures how long the board takes to answer a command, and sets
+    /// both delays to the longest latency seen
+    /// 
+    /// runs `samples` packet requests with no delay, relying on the board
+    /// NACKing while it is busy, so packets pending on the Radio are discarded
+    /// 
+    /// call before starting to recieve
+    pub fn calibrate(&mut self, samples: u32) -> Result<TimingReport, RadioError> {
+        let samples = samples.max(1);
 
+        let mut min_latency = u32::MAX;
+        let mut max_latency = 0;
+        let mut total: u64 = 0;
 
-        // transmit "transmit" signal 0x01 and number of bytes to expect
-        match self.i2c.write(&buf) {
-            Ok(_) => {},
+        let mut packet = Packet::new();
+
+        for _ in 0..samples {
+            self.send_command(Command::GetPacket, RadioError::RecieveCmd)?;
+
+            let mut buf: [u8; 1] = [0u8; 1];
+            let latency = match self.with_retry(0, |i2c, addr| i2c.read(addr, &mut buf)) {
+                Ok((_, n)) => n,
+                Err(_) => {
+                    return Err(RadioError::RecieveReadLen);
+                },
+            };
+
+            // drain the packet so the board is back to idle
+            if buf[0] != 0 {
+                let buf2 = match packet.resize(buf[0] as usize) {
+                    Ok(n) => n,
+                    Err(_) => {
+                        return Err(RadioError::ReadLengthMismatch);
+                    },
+                };
+
+                if self.with_retry(0, |i2c, addr| i2c.read(addr, buf2)).is_err() {
+                    return Err(RadioError::RecieveReadMsg);
+                }
+            }
+
+            min_latency = min_latency.min(latency);
+            max_latency = max_latency.max(latency);
+            total += latency as u64;
+        }
+
+        self.timing.packet_gather_delay = max_latency;
+        self.timing.write_wait_delay = max_latency;
+
+        Ok(TimingReport {
+            samples,
+            min_latency,
+            max_latency,
+            mean_latency: (total / samples as u64) as u32,
+            timing: self.timing,
+        })
+    }
+}
+
+// transmit / recieve / reset
+impl<I2C: I2c, D: DelayNs, RST: OutputPin> Radio<I2C, D, RST> {
+    /// helper function to write a single command frame over i2c
+    fn send_command(&mut self, cmd: Command, err: RadioError) -> Result<(), RadioError> {
+        match self.i2c.write(self.address, &cmd.encode()) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(err),
+        }
+    }
+
+    /// transmits the given message
+    pub fn transmit(&mut self, msg: &[u8]) -> Result<(), RadioError> {
+        let cmd = match Command::transmit(msg) {
+            Ok(n) => n,
             Err(_) => {
-                return Err(RadioError::TransmitMsgLen);
+                return Err(RadioError::InvalidArgument);
             },
         };
 
-        thread::sleep(Duration::from_millis(self.write_wait_delay));
+        // transmit "transmit" signal 0x01 and number of bytes to expect
+        self.send_command(cmd, RadioError::TransmitMsgLen)?;
 
-        // transmit message
-        match self.i2c.write(&msg) {
-            Ok(_) => {},
+        // transmit message, once the radio is ready for it
+        let waited = match self.with_retry(self.timing.write_wait_delay, |i2c, addr| i2c.write(addr, msg)) {
+            Ok((_, n)) => n,
             Err(_) => {
                 return Err(RadioError::TransmitMsg);
             },
+        };
+
+        if self.timing.adaptive {
+            self.timing.write_wait_delay = adapt_delay(self.timing.write_wait_delay, waited, self.timing.min_delay);
         }
         
         Ok(())
     }
 
-    /// gets the most recent packet stored on the Radio
+    /// gets the most recent packet stored on the Radio into `packet`
     /// 
-    /// ## Returns
-    /// Vec/<u8/> with the byte data of the packet
+    /// allocation free version of `get_packet`
     /// 
-    /// empty Vec/<u8/> if no available packet was found
-    pub fn get_packet(&mut self) -> Result<Vec<u8>, RadioError> {
-        // send read command
-        let msg: [u8; 5] = [0x02, 0x00, 0x00, 0x00, 0x00];
-        match self.i2c.write(&msg) {
-            Ok(_) => {},
-            Err(_) => {
-                return Err(RadioError::RecieveCmd);
-            },
-        };
+    /// `packet` is left empty if no available packet was found
+    pub fn get_packet_into(&mut self, packet: &mut Packet) -> Result<(), RadioError> {
+        packet.clear();
 
-        // give radio time to collect packets
-        thread::sleep(Duration::from_millis(self.packet_wait_delay));
+        // send read command
+        self.send_command(Command::GetPacket, RadioError::RecieveCmd)?;
 
-        // read number of expected bytes
+        // read number of expected bytes, giving the radio time to collect packets
         let mut buf: [u8; 1] = [0u8; 1];
-        match self.i2c.read(&mut buf) {
-            Ok(_) => {},
+        let waited = match self.with_retry(self.timing.packet_gather_delay, |i2c, addr| i2c.read(addr, &mut buf)) {
+            Ok((_, n)) => n,
             Err(_) => {
                 return Err(RadioError::RecieveReadLen);
             },
         };
+
+        if self.timing.adaptive {
+            self.timing.packet_gather_delay = adapt_delay(self.timing.packet_gather_delay, waited, self.timing.min_delay);
+        }
+
         let msg_size: usize = buf[0] as usize; 
 
         if msg_size == 0 {
-            let out: Vec<u8> = Vec::new();
-            return Ok(out);
+            return Ok(());
         }
 
-        // initialize &[u8] on the heap
-        // this allows for a &[u8] with size msg_size which is not known at compile time
-        let mut buf2: Box<[u8]> = vec![0; msg_size].into_boxed_slice();
-
-        match self.i2c.read(&mut buf2) {
-            Ok(_) => {},
+        // a u8 length always fits in a Packet
+        let buf2 = match packet.resize(msg_size) {
+            Ok(n) => n,
             Err(_) => {
-                return Err(RadioError::RecieveReadMsg);
+                return Err(RadioError::ReadLengthMismatch);
             },
         };
 
-        let mut out: Vec<u8> = Vec::new();
-        out.extend_from_slice(&buf2);
+        if self.with_retry(0, |i2c, addr| i2c.read(addr, buf2)).is_err() {
+            packet.clear();
+            return Err(RadioError::RecieveReadMsg);
+        }
 
-        Ok(out)
+        Ok(())
+    }
+
+    /// gets the most recent packet stored on the Radio
+    /// 
+    /// ## Returns
+    /// Vec/<u8/> with the byte data of the packet
+    /// 
+    /// empty Vec/<u8/> if no available packet was found
+    #[cfg(feature = "std")]
+    pub fn get_packet(&mut self) -> Result<Vec<u8>, RadioError> {
+        let mut packet = Packet::new();
+        self.get_packet_into(&mut packet)?;
+
+        Ok(packet.to_vec())
     }
 
     /// sends a reset command to reset the onboard Radio chip
     pub fn radio_reset(&mut self) -> Result<(), RadioError> {
-        match self.i2c.write(&[9,0,0,0,0]) {
-            Ok(_) => {},
-            Err(_) => {
-                return Err(RadioError::TransmitMsgLen);
-            },
-        };
-        Ok(())
+        return self.send_command(Command::RadioReset, RadioError::TransmitMsgLen);
     }
 
     /// sends a reset command to perform a soft reset on the entire board
     pub fn soft_reset(&mut self) -> Result<(), RadioError> {
-        match self.i2c.write(&[10,0,0,0,0]) {
-            Ok(_) => {},
-            Err(_) => {
-                return Err(RadioError::TransmitMsgLen);
-            },
-        };
-        Ok(())
+        return self.send_command(Command::SoftReset, RadioError::TransmitMsgLen);
     }
 
     /// performs a hard reset using the boards reset pin
+    /// 
+    /// pulses the pin given to `Radio::with_reset_pin`, waits for the board
+    /// to boot, then checks that the Radio answers its ident again
     pub fn reset(&mut self) -> Result<(), RadioError> {
-        println!("placeholder function, no action occured");
-        Ok(())
-    }
-
-}
+        let config = self.reset_config;
 
-// change radio settings
-impl Radio {
-    /// helper function to write the byte data of a f32 over i2c
-    fn set_float_val(&mut self, cmd: u8, val: f32) -> Result<(), RadioError> {
-        let bytes = val.to_ne_bytes();
-        let mut buf: [u8; 5] = [cmd, 0x00, 0x00, 0x00, 0x00];
-        buf[1] = bytes[0];
-        buf[2] = bytes[1];
-        buf[3] = bytes[2];
-        buf[4] = bytes[3];
-
-        match self.i2c.write(&buf) {
-            Ok(_) => {},
-            Err(_) => {
-                return Err(RadioError::TransmitError);
+        let pin = match self.reset_pin.as_mut() {
+            Some(n) => n,
+            None => {
+                return Err(RadioError::NoResetPin);
             },
         };
 
-        return Ok(());
+        set_reset_line(pin, &config, true)?;
+        self.delay.delay_ms(config.pulse_width);
+
+        set_reset_line(pin, &config, false)?;
+        self.delay.delay_ms(config.post_reset_wait);
+
+        if !check_for_device(&mut self.i2c, self.address) {
+            return Err(RadioError::DeviceDetectError);
+        }
+
+        Ok(())
     }
 
+}
+
+// change radio settings
+impl<I2C: I2c, D: DelayNs, RST: OutputPin> Radio<I2C, D, RST> {
     /// attempts to set the frequency of the radio
     /// 
     /// there is a chance the radio rejects the value if it is invalid
     pub fn set_frequency(&mut self, frequency: f32) -> Result<(), RadioError> {
-        return self.set_float_val(0x03, frequency);
+        return self.send_command(Command::Frequency(frequency), RadioError::TransmitError);
     }
 
     /// attempts to set the tx gain of the radio
     /// 
     /// there is a chance the radio rejects the value if it is invalid
     pub fn set_power(&mut self, power: f32) -> Result<(), RadioError> {
-        return self.set_float_val(0x04, power);
+        return self.send_command(Command::Power(power), RadioError::TransmitError);
     }
 
     /// attempts to set the FSK bandwith of the radio
     /// 
     /// there is a chance the radio rejects the value if it is invalid
     pub fn set_deviation(&mut self, deviation: f32) -> Result<(), RadioError> {
-        return self.set_float_val(0x05, deviation);
+        return self.send_command(Command::Deviation(deviation), RadioError::TransmitError);
     }
 
     /// attempts to set the symbol rate of the radio
     /// 
     /// there is a chance the radio rejects the value if it is invalid
     pub fn set_symbol_rate(&mut self, symbol_rate: f32) -> Result<(), RadioError> {
-        return self.set_float_val(0x06, symbol_rate);
+        return self.send_command(Command::SymbolRate(symbol_rate), RadioError::TransmitError);
     }
 
     /// attempts to set the recieve filter of the radio
     /// 
     /// there is a chance the radio rejects the value if it is invalid
     pub fn set_rx_filter(&mut self, rx_filter: f32) -> Result<(), RadioError> {
-        return self.set_float_val(0x07, rx_filter);
+        return self.send_command(Command::RxFilter(rx_filter), RadioError::TransmitError);
     }
 
     /// sets the modulation mode of the radio
     pub fn set_modulation(&mut self, mode: ModulationFormat) -> Result<(), RadioError> {
-        let buf: [u8; 5] = [0x08, mode as u8, 0x00, 0x00, 0x00];
+        return self.send_command(Command::Modulation(mode), RadioError::TransmitError);
+    }
+}
 
-        match self.i2c.write(&buf) {
-            Ok(_) => {},
-            Err(_) => {
-                return Err(RadioError::TransmitError);
-            },
+#[cfg(feature = "std")]
+impl<I2C: I2c, D: DelayNs, RST: OutputPin> Transceiver for Radio<I2C, D, RST> {
+    impl_transceiver!(RadioError);
+}
+
+/// gets a Vector containing the names of all i2c adapters on the system
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub fn get_i2c_buses() -> Result<Vec<String>, RadioError> {
+    let entries = match fs::read_dir("/dev") {
+        Ok(n) => n,
+        Err(_) => {return Err(RadioError::BusDetectError);},
+    };
+
+    let mut buses: Vec<(u32, String)> = vec![];
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        let index: u32 = match name.strip_prefix("i2c-").map(|n| n.parse::<u32>()) {
+            Some(Ok(n)) => n,
+            _ => {continue;},
+        };
+
+        buses.push((index, format!("/dev/{}", name)));
+    }
+
+    // sort by adapter number so /dev/i2c-10 comes after /dev/i2c-2
+    buses.sort();
+
+    return Ok(buses.into_iter().map(|(_, path)| path).collect());
+}
+
+/// scans every i2c adapter on the system for Radio devices
+///
+/// each candidate address is probed with the same ident read used by `Radio::new`
+///
+/// ## Returns
+/// Vec of (bus path, address) for every Radio found
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub fn discover() -> Result<Vec<(String, u8)>, RadioError> {
+    let mut out: Vec<(String, u8)> = vec![];
+
+    for bus in get_i2c_buses()? {
+        // adapters we lack permission for simply don't have Radios
+        let mut i2c = match LinuxI2c::new(&bus) {
+            Ok(n) => n,
+            Err(_) => {continue;},
         };
 
-        return Ok(());
+        for addr in CANDIDATE_ADDRS {
+            if check_for_device(&mut i2c, addr) {
+                out.push((bus.clone(), addr));
+            }
+        }
+    }
+
+    return Ok(out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the tests run on the host, also when the library is built without `std`
+    extern crate std;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_hal::i2c::Operation;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Event {
+        High,
+        Low,
+        DelayMs(u32),
+    }
+
+    type Log = Rc<RefCell<Vec<Event>>>;
+
+    /// a board that always answers with its ident
+    struct IdentBus;
+
+    impl embedded_hal::i2c::ErrorType for IdentBus {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for IdentBus {
+        fn transaction(&mut self, _address: u8, operations: &mut [Operation<'_>]) -> Result<(), ErrorKind> {
+            for op in operations {
+                if let Operation::Read(buf) = op {
+                    let len = buf.len().min(IDENT_MSG.len());
+                    buf[..len].copy_from_slice(&IDENT_MSG.as_bytes()[..len]);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    struct LogPin(Log);
+
+    impl digital::ErrorType for LogPin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for LogPin {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Event::Low);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Event::High);
+            Ok(())
+        }
+    }
+
+    struct LogDelay(Log);
+
+    impl DelayNs for LogDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+
+        fn delay_ms(&mut self, ms: u32) {
+            self.0.borrow_mut().push(Event::DelayMs(ms));
+        }
+    }
+
+    fn radio(log: &Log) -> Radio<IdentBus, LogDelay> {
+        Radio {
+            i2c: IdentBus,
+            delay: LogDelay(log.clone()),
+            address: ADDR,
+            timing: Timing::default(),
+            reset_pin: None,
+            reset_config: ResetConfig::default(),
+        }
+    }
+
+    #[test]
+    fn reset_pulse() {
+        let log: Log = Rc::new(RefCell::new(vec![]));
+
+        assert!(matches!(radio(&log).reset(), Err(RadioError::NoResetPin)));
+
+        // active low, released high when attached
+        let config = ResetConfig { pulse_width: 10, post_reset_wait: 250, active_low: true };
+        let mut radio = match radio(&log).with_reset_pin(LogPin(log.clone()), config) {
+            Ok(n) => n,
+            Err((_, n)) => panic!("{:?}", n),
+        };
+        assert_eq!(log.take(), vec![Event::High]);
+
+        radio.reset().unwrap();
+        assert_eq!(log.take(), vec![Event::Low, Event::DelayMs(10), Event::High, Event::DelayMs(250)]);
+
+        // active high
+        let config = ResetConfig { pulse_width: 5, post_reset_wait: 100, active_low: false };
+        radio.set_reset_config(config);
+        radio.reset().unwrap();
+        assert_eq!(log.take(), vec![Event::High, Event::DelayMs(5), Event::Low, Event::DelayMs(100)]);
+    }
+
+    #[test]
+    fn adaptive_delay() {
+        // retried, raised to the latency
+        assert_eq!(adapt_delay(1_000, 1_500, 500), 1_500);
+
+        // first time, stepped down to the floor and held there
+        let mut delay = 10_000;
+        for _ in 0..20 {
+            delay = adapt_delay(delay, delay, 1_000);
+        }
+        assert_eq!(delay, 1_000);
+        assert_eq!(adapt_delay(delay, delay, 1_000), 1_000);
+        assert_eq!(adapt_delay(delay, 1_200, 1_000), 1_200);
+
+        // a delay set below the floor isn't raised to it, nor lowered further
+        assert_eq!(adapt_delay(200, 200, 1_000), 200);
+        assert_eq!(adapt_delay(0, 0, 1_000), 0);
     }
 }
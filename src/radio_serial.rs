@@ -4,15 +4,9 @@ use serialport::{SerialPort, available_ports};
 
 use std::{time::Duration, vec};
 
-const IDENT_MSG: &str = "ArmLabCC1200";
-
-pub enum ModulationFormat {
-    FSK2 = 0x0,
-    GFSK2 = 0x1,
-    ASK = 0x3,
-    FSK4 = 0x4,
-    GFSK4 = 0x5,
-}
+use crate::protocol::{Command, IDENT_MSG};
+use crate::transceiver::{Transceiver, impl_transceiver};
+pub use crate::protocol::ModulationFormat;
 
 #[derive(Debug)]
 pub enum RadioError {
@@ -46,31 +40,14 @@ impl Radio {
     /// 
     /// see `Radio::new_bare` for a constructor without overhead
     pub fn new(path: &str) -> Result<Radio, RadioError> {
-        let mut port = match serialport::new(path, 115200)
+        let port = match serialport::new(path, 115200)
             .timeout(Duration::from_millis(100))
             .open() {
                 Ok(n) => n,
                 Err(_) => {return Err(RadioError::PortOpenError)}
         };
 
-        match port.write_data_terminal_ready(true) {
-            Ok(_) => {},
-            Err(_) => {return Err(RadioError::WriteError);},
-        };
-
-
-        match Radio::sync_serial(&mut port, 6) {
-            Ok(n) => {println!("synced radio, after {} bytes | device was {} step(s) ahead", n, 6-n);},
-            Err(n) => {return Err(n);},
-        };
-
-        if !Radio::check_for_device(&mut port) {
-            return Err(RadioError::DevciceDetectError);
-        }
-        println!("found device");
-        
-        
-        Ok(Radio {port, port_path: path.to_string()})
+        return Radio::from_port(port, path);
     }
 
     /// creates a new Radio object on the given port
@@ -80,19 +57,44 @@ impl Radio {
     /// not recommended for the general use case
     /// see `Radio::new_bare` for implemented existence and synchronization
     pub fn new_bare(path: &str) -> Result<Radio, RadioError> {
-        let mut port = match serialport::new(path, 115200)
+        let port = match serialport::new(path, 115200)
             .timeout(Duration::from_millis(100))
             .open() {
                 Ok(n) => n,
                 Err(_) => {return Err(RadioError::PortOpenError)}
         };
 
+        return Radio::from_port_bare(port, path);
+    }
+
+    /// creates a new Radio object on an already open port, `path` is only used as its name
+    /// 
+    /// runs the same existence checks and synchronization as `Radio::new`,
+    /// useful with `linklog::RecordingPort` and `linklog::ReplayPort`
+    pub fn from_port(port: Box<dyn SerialPort>, path: &str) -> Result<Radio, RadioError> {
+        let mut radio = Radio::from_port_bare(port, path)?;
+
+        match Radio::sync_serial(&mut radio.port, 6) {
+            Ok(n) => {println!("synced radio, after {} bytes | device was {} step(s) ahead", n, 6-n);},
+            Err(n) => {return Err(n);},
+        };
+
+        if !Radio::check_for_device(&mut radio.port) {
+            return Err(RadioError::DevciceDetectError);
+        }
+        println!("found device");
+
+        Ok(radio)
+    }
+
+    /// creates a new Radio object on an already open port
+    /// without checking for existence or attempting to synchronize
+    pub fn from_port_bare(mut port: Box<dyn SerialPort>, path: &str) -> Result<Radio, RadioError> {
         match port.write_data_terminal_ready(true) {
             Ok(_) => {},
             Err(_) => {return Err(RadioError::WriteError)},
         }
 
-
         Ok(Radio {port, port_path: path.to_string()})
     }
 
@@ -114,7 +116,8 @@ impl Radio {
     /// 
     /// useful for in-constructor checks
     fn check_for_device(port: &mut Box<dyn SerialPort>) -> bool {
-        let cmd: [u8; 6] = [0,0,0,0,0, '\n' as u8];
+        let mut cmd: Vec<u8> = Command::Ident.encode().to_vec();
+        cmd.push(b'\n');
         match port.write_all(&cmd) {
             Ok(_) => {},
             Err(_) => {
@@ -167,8 +170,8 @@ impl Radio {
         }
         
         if buf3 != IDENT_MSG.as_bytes() {
-            for i in 0..IDENT_MSG.len() {
-                println!("{}, {}", buf3[i], IDENT_MSG.as_bytes()[i]);
+            for (got, expected) in buf3.iter().zip(IDENT_MSG.as_bytes()) {
+                println!("{}, {}", got, expected);
             }
         }
 
@@ -193,9 +196,8 @@ impl Radio {
             };
             
             let mut buf: [u8; IDENT_MSG.len()] = [0; IDENT_MSG.len()];
-            match port.read_exact(&mut buf) {
-                Ok(_) => {return Ok(i)},
-                Err(_) => {}
+            if port.read_exact(&mut buf).is_ok() {
+                return Ok(i);
             }
 
             if i == timeout_iter {
@@ -224,12 +226,12 @@ impl Radio {
         // create temp vector to hold both message and newline
         let mut middle: Vec<u8> = vec![0; 0];
         middle.extend_from_slice(data);
-        middle.push('\n' as u8);
+        middle.push(b'\n');
 
         // code should always be safe, we are trimming to the data that was added
         // plus we check for the condition where it would fail
         unsafe {
-            if data.len() + 1 <= middle.capacity() {
+            if data.len() < middle.capacity() {
                 middle.set_len(data.len() + 1);
             }
         }
@@ -244,18 +246,18 @@ impl Radio {
 
     /// transmits the given message
     pub fn transmit(&mut self, msg: &[u8]) -> Result<(), RadioError> {
-        if msg.len() > u8::MAX as usize {
-            return Err(RadioError::InvalidArgument);
-        }
+        let cmd = match Command::transmit(msg) {
+            Ok(n) => n,
+            Err(_) => {return Err(RadioError::InvalidArgument)},
+        };
 
-        let cmd:[u8; 5] = [0x1, msg.len() as u8, 0x0, 0x0, 0x0];
-        match self.write_bytes(&cmd) {
+        match self.write_bytes(&cmd.encode()) {
             Ok(_) => {},
             Err(_) => {return Err(RadioError::WriteError)},
         };
 
         
-        match self.write_bytes(&msg) {
+        match self.write_bytes(msg) {
             Ok(_) => {},
             Err(_) => {return Err(RadioError::WriteError)},
         };
@@ -284,8 +286,7 @@ impl Radio {
     /// 
     /// empty Vec/<u8/> if no available packet was found
     pub fn get_packet(&mut self) -> Result<Vec<u8>, RadioError> {
-        let cmd: [u8; 5] = [0x2, 0x0, 0x0, 0x0, 0x0];
-        match self.write_bytes(&cmd) {
+        match self.write_bytes(&Command::GetPacket.encode()) {
             Ok(_) => {},
             Err(_) => {return Err(RadioError::WriteError)},
         };
@@ -318,27 +319,20 @@ impl Radio {
 
     /// sends a reset command to reset the onboard Radio chip
     pub fn radio_reset(&mut self) -> Result<(), RadioError> {
-        return self.write_bytes(&[9,0,0,0,0]);
+        return self.write_bytes(&Command::RadioReset.encode());
     }
 
     /// sends a reset command to perform a soft reset on the entire board
     pub fn soft_reset(&mut self) -> Result<(), RadioError> {
-        return self.write_bytes(&[10,0,0,0,0]);
+        return self.write_bytes(&Command::SoftReset.encode());
     }
 
 }
 
 impl Radio {
-    /// helper function to write the byte data of a f32 to the serial port
-    fn set_float_val(&mut self, cmd: u8, val: f32) -> Result<(), RadioError> {
-        let bytes = val.to_ne_bytes();
-        let mut buf: [u8; 5] = [cmd, 0x00, 0x00, 0x00, 0x00];
-        buf[1] = bytes[0];
-        buf[2] = bytes[1];
-        buf[3] = bytes[2];
-        buf[4] = bytes[3];
-
-        match self.write_bytes(&buf) {
+    /// helper function to write a single command frame to the serial port
+    fn send_command(&mut self, cmd: Command) -> Result<(), RadioError> {
+        match self.write_bytes(&cmd.encode()) {
             Ok(_) => {},
             Err(_) => {
                 return Err(RadioError::WriteError);
@@ -352,52 +346,47 @@ impl Radio {
     /// 
     /// there is a chance the radio rejects the value if it is invalid
     pub fn set_frequency(&mut self, frequency: f32) -> Result<(), RadioError> {
-        return self.set_float_val(0x03, frequency);
+        return self.send_command(Command::Frequency(frequency));
     }
 
     /// attempts to set the tx gain of the radio
     /// 
     /// there is a chance the radio rejects the value if it is invalid
     pub fn set_power(&mut self, power: f32) -> Result<(), RadioError> {
-        return self.set_float_val(0x04, power);
+        return self.send_command(Command::Power(power));
     }
 
     /// attempts to set the FSK bandwith of the radio
     /// 
     /// there is a chance the radio rejects the value if it is invalid
     pub fn set_deviation(&mut self, deviation: f32) -> Result<(), RadioError> {
-        return self.set_float_val(0x05, deviation);
+        return self.send_command(Command::Deviation(deviation));
     }
 
     /// attempts to set the symbol rate of the radio
     /// 
     /// there is a chance the radio rejects the value if it is invalid
     pub fn set_symbol_rate(&mut self, symbol_rate: f32) -> Result<(), RadioError> {
-        return self.set_float_val(0x06, symbol_rate);
+        return self.send_command(Command::SymbolRate(symbol_rate));
     }
 
     /// attempts to set the recieve filter of the radio
     /// 
     /// there is a chance the radio rejects the value if it is invalid
     pub fn set_rx_filter(&mut self, rx_filter: f32) -> Result<(), RadioError> {
-        return self.set_float_val(0x07, rx_filter);
+        return self.send_command(Command::RxFilter(rx_filter));
     }
 
     /// sets the modulation mode of the radio
     pub fn set_modulation(&mut self, mode: ModulationFormat) -> Result<(), RadioError> {
-        let buf: [u8; 5] = [0x08, mode as u8, 0x00, 0x00, 0x00];
-
-        match self.write_bytes(&buf) {
-            Ok(_) => {},
-            Err(_) => {
-                return Err(RadioError::WriteError);
-            },
-        };
-
-        return Ok(());
+        return self.send_command(Command::Modulation(mode));
     }
 }
 
+impl Transceiver for Radio {
+    impl_transceiver!(RadioError);
+}
+
 /// gets a Vector containing the names of all connected serial ports on the system
 pub fn get_open_ports() -> Result<Vec<String>, RadioError> {
     let res = match available_ports() {
@@ -461,8 +450,8 @@ pub fn prompt_port() -> String{
             println!("Found one radio on {}", radios[0]);
             radios[0].clone()
         }
-        0 | _ => {
-            if radios.len() == 0 {
+        _ => {
+            if radios.is_empty() {
                 println!("Radio could not be automatically detected");
                 radios = get_open_ports().unwrap();
             }
@@ -471,10 +460,8 @@ pub fn prompt_port() -> String{
             }
 
             println!("Please select a port: ");
-            let mut i: usize = 0;
-            for port in &radios {
+            for (i, port) in radios.iter().enumerate() {
                 println!("\t{}. {}", i, port);
-                i += 1;
             }
 
             loop {
@@ -0,0 +1,133 @@
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::capture::{CaptureReader, CaptureRecord, Direction};
+use crate::mock::MockRadio;
+use crate::transceiver::Transceiver;
+
+/// how a capture is replayed
+#[derive(Debug, Clone, Copy)]
+pub struct ReplayConfig {
+    /// playback rate, 1.0 keeps the original timing, 2.0 is twice as fast, 0.0 sends back to back
+    pub speed: f64,
+    /// only replay packets that went this way, None for both
+    pub direction: Option<Direction>,
+    /// retune the Radio to each packet's recorded frequency and modulation before sending it
+    pub retune: bool,
+}
+
+impl Default for ReplayConfig {
+    /// ## Default
+    /// original timing, every packet, no retuning
+    fn default() -> Self {
+        ReplayConfig {
+            speed: 1.0,
+            direction: None,
+            retune: false,
+        }
+    }
+}
+
+/// plays a recorded session back through a Radio, or into a `MockRadio`
+pub struct Replayer {
+    records: Vec<CaptureRecord>,
+    config: ReplayConfig,
+}
+
+impl Replayer {
+    /// replays `records`, keeping those that match `config.direction`
+    pub fn new(records: Vec<CaptureRecord>, config: ReplayConfig) -> Replayer {
+        let records = records.into_iter()
+            .filter(|r| config.direction.is_none_or(|d| d == r.direction))
+            .collect();
+
+        Replayer { records, config }
+    }
+
+    /// reads every packet from a capture written by `capture::PcapngWriter`
+    pub fn open<P: AsRef<Path>>(path: P, config: ReplayConfig) -> io::Result<Replayer> {
+        let records = CaptureReader::open(path)?.collect::<io::Result<Vec<CaptureRecord>>>()?;
+        return Ok(Replayer::new(records, config));
+    }
+
+    /// the packets that will be replayed, in order
+    pub fn records(&self) -> &[CaptureRecord] {
+        &self.records
+    }
+
+    /// time between the first and last packet at the configured speed
+    pub fn duration(&self) -> Duration {
+        match (self.records.first(), self.records.last()) {
+            (Some(first), Some(last)) => self.scale(last.timestamp.saturating_sub(first.timestamp)),
+            _ => Duration::ZERO,
+        }
+    }
+
+    fn scale(&self, offset: Duration) -> Duration {
+        match self.config.speed > 0.0 && self.config.speed.is_finite() {
+            true => offset.div_f64(self.config.speed),
+            false => Duration::ZERO,
+        }
+    }
+
+    /// transmits every packet, blocking until the last one is sent
+    ///
+    /// packets are scheduled against the start of the replay, so slow
+    /// transmits don't add up over a long session
+    ///
+    /// ## Returns
+    /// the number of packets sent, stops at the first error
+    pub fn transmit<T: Transceiver>(&self, radio: &mut T) -> Result<usize, T::Error> {
+        self.transmit_with(radio, |_, _| {})
+    }
+
+    /// `transmit`, calling `sent(index, record)` after each packet
+    pub fn transmit_with<T: Transceiver, F: FnMut(usize, &CaptureRecord)>(&self, radio: &mut T, mut sent: F) -> Result<usize, T::Error> {
+        let first = match self.records.first() {
+            Some(n) => n.timestamp,
+            None => {return Ok(0);},
+        };
+
+        let start = Instant::now();
+        let mut frequency: Option<f32> = None;
+        let mut modulation = None;
+
+        for (i, record) in self.records.iter().enumerate() {
+            let due = start + self.scale(record.timestamp.saturating_sub(first));
+            let now = Instant::now();
+            if due > now {
+                thread::sleep(due - now);
+            }
+
+            if self.config.retune {
+                if let Some(freq) = record.frequency.filter(|f| Some(*f) != frequency) {
+                    radio.set_frequency(freq)?;
+                    frequency = Some(freq);
+                }
+                if let Some(mode) = record.modulation.filter(|m| Some(*m) != modulation) {
+                    radio.set_modulation(mode)?;
+                    modulation = Some(mode);
+                }
+            }
+
+            radio.transmit(&record.payload)?;
+            sent(i, record);
+        }
+
+        return Ok(self.records.len());
+    }
+
+    /// queues every packet to be returned by the mock's `get_packet`, ignoring timing
+    ///
+    /// ## Returns
+    /// the number of packets queued
+    pub fn feed(&self, radio: &mut MockRadio) -> usize {
+        for record in &self.records {
+            radio.push_rx(&record.payload);
+        }
+
+        return self.records.len();
+    }
+}
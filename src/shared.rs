@@ -0,0 +1,241 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[cfg(feature = "i2c")]
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+
+use crate::protocol::ModulationFormat;
+use crate::transceiver::Transceiver;
+
+/// locks `mutex`, recovering it if another thread panicked while holding it
+/// 
+/// a panic can't leave the Radio in a worse state than a failed command would
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(n) => n,
+        Err(n) => n.into_inner(),
+    }
+}
+
+/// cloneable, thread safe handle to a single Radio
+/// 
+/// every call holds the Radio for the whole command transaction, so a
+/// `set_frequency` on one thread can never land between the length and
+/// payload reads of a `get_packet` on another
+/// 
+/// `SharedRadio::with` holds it across several commands
+pub struct SharedRadio<R> {
+    radio: Arc<Mutex<R>>,
+}
+
+impl<R> Clone for SharedRadio<R> {
+    fn clone(&self) -> Self {
+        SharedRadio { radio: self.radio.clone() }
+    }
+}
+
+impl<R: Transceiver> SharedRadio<R> {
+    /// wraps a Radio so it can be shared between threads
+    pub fn new(radio: R) -> SharedRadio<R> {
+        SharedRadio { radio: Arc::new(Mutex::new(radio)) }
+    }
+
+    /// runs `f` with exclusive access to the Radio
+    /// 
+    /// no other handle can issue commands until `f` returns, useful for
+    /// sequences that must not be interleaved, e.g. retune then transmit
+    pub fn with<T, F: FnOnce(&mut R) -> T>(&self, f: F) -> T {
+        let mut radio = lock(&self.radio);
+        f(&mut radio)
+    }
+}
+
+impl<R: Transceiver> Transceiver for SharedRadio<R> {
+    type Error = R::Error;
+
+    fn transmit(&mut self, msg: &[u8]) -> Result<(), R::Error> {
+        self.with(|r| r.transmit(msg))
+    }
+
+    fn get_packet(&mut self) -> Result<Vec<u8>, R::Error> {
+        self.with(|r| r.get_packet())
+    }
+
+    fn set_frequency(&mut self, frequency: f32) -> Result<(), R::Error> {
+        self.with(|r| r.set_frequency(frequency))
+    }
+
+    fn set_power(&mut self, power: f32) -> Result<(), R::Error> {
+        self.with(|r| r.set_power(power))
+    }
+
+    fn set_deviation(&mut self, deviation: f32) -> Result<(), R::Error> {
+        self.with(|r| r.set_deviation(deviation))
+    }
+
+    fn set_symbol_rate(&mut self, symbol_rate: f32) -> Result<(), R::Error> {
+        self.with(|r| r.set_symbol_rate(symbol_rate))
+    }
+
+    fn set_rx_filter(&mut self, rx_filter: f32) -> Result<(), R::Error> {
+        self.with(|r| r.set_rx_filter(rx_filter))
+    }
+
+    fn set_modulation(&mut self, mode: ModulationFormat) -> Result<(), R::Error> {
+        self.with(|r| r.set_modulation(mode))
+    }
+
+    fn radio_reset(&mut self) -> Result<(), R::Error> {
+        self.with(|r| r.radio_reset())
+    }
+
+    fn soft_reset(&mut self) -> Result<(), R::Error> {
+        self.with(|r| r.soft_reset())
+    }
+}
+
+/// i2c bus shared between the Radio and other devices, e.g. sensors
+/// 
+/// `SharedBus::device` hands out embedded-hal `I2c` handles which lock the
+/// bus for each transaction, one can be given to `radio_i2c::Radio::from_bus`
+/// and the rest to other drivers
+#[cfg(feature = "i2c")]
+pub struct SharedBus<I2C> {
+    bus: Arc<Mutex<I2C>>,
+}
+
+#[cfg(feature = "i2c")]
+impl<I2C> Clone for SharedBus<I2C> {
+    fn clone(&self) -> Self {
+        SharedBus { bus: self.bus.clone() }
+    }
+}
+
+#[cfg(feature = "i2c")]
+impl<I2C: I2c> SharedBus<I2C> {
+    /// wraps an i2c bus so it can be shared between drivers and threads
+    pub fn new(i2c: I2C) -> SharedBus<I2C> {
+        SharedBus { bus: Arc::new(Mutex::new(i2c)) }
+    }
+
+    /// creates a new handle to the bus
+    pub fn device(&self) -> BusDevice<I2C> {
+        BusDevice { bus: self.bus.clone() }
+    }
+}
+
+/// handle to a `SharedBus`
+#[cfg(feature = "i2c")]
+pub struct BusDevice<I2C> {
+    bus: Arc<Mutex<I2C>>,
+}
+
+#[cfg(feature = "i2c")]
+impl<I2C: I2c> ErrorType for BusDevice<I2C> {
+    type Error = I2C::Error;
+}
+
+#[cfg(feature = "i2c")]
+impl<I2C: I2c> I2c<SevenBitAddress> for BusDevice<I2C> {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        lock(&self.bus).transaction(address, operations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    use crate::mock::MockRadio;
+
+    #[test]
+    fn radio_from_two_threads() {
+        let radio = SharedRadio::new(MockRadio::new());
+
+        let threads: Vec<_> = [433e6, 915e6].into_iter().enumerate().map(|(tag, frequency)| {
+            let mut radio = radio.clone();
+            thread::spawn(move || {
+                for n in 0..200u8 {
+                    // the other thread's settings never land inside `with`
+                    radio.with(|r| {
+                        r.set_frequency(frequency).unwrap();
+                        thread::yield_now();
+                        assert_eq!(r.config().frequency, Some(frequency));
+                        r.transmit(&[tag as u8, n]).unwrap();
+                    });
+
+                    // nor inside a single command through the Transceiver impl
+                    radio.transmit(&[tag as u8, n]).unwrap();
+                }
+            })
+        }).collect();
+
+        for n in threads {
+            n.join().unwrap();
+        }
+
+        let transmitted = radio.with(|r| r.take_transmitted());
+        assert_eq!(transmitted.len(), 800);
+
+        // each thread's packets arrive whole and in order
+        for tag in 0..2u8 {
+            let seq: Vec<u8> = transmitted.iter().filter(|n| n[0] == tag).map(|n| n[1]).collect();
+            let expected: Vec<u8> = (0..200u8).flat_map(|n| [n, n]).collect();
+            assert_eq!(seq, expected);
+        }
+    }
+
+    /// records every operation as (address, index in its transaction, transaction length)
+    #[cfg(feature = "i2c")]
+    struct LogBus(Arc<Mutex<Vec<(u8, usize, usize)>>>);
+
+    #[cfg(feature = "i2c")]
+    impl ErrorType for LogBus {
+        type Error = core::convert::Infallible;
+    }
+
+    #[cfg(feature = "i2c")]
+    impl I2c<SevenBitAddress> for LogBus {
+        fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+            for n in 0..operations.len() {
+                lock(&self.0).push((address, n, operations.len()));
+                thread::yield_now();
+            }
+            return Ok(());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "i2c")]
+    fn bus_devices_take_turns() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let bus = SharedBus::new(LogBus(log.clone()));
+
+        let threads: Vec<_> = [0x34u8, 0x48].into_iter().map(|address| {
+            let mut device = bus.device();
+            thread::spawn(move || {
+                for _ in 0..200 {
+                    let mut buf = [0u8; 2];
+                    device.write_read(address, &[0x01], &mut buf).unwrap();
+                    device.write(address, &[0x02, 0x03]).unwrap();
+                }
+            })
+        }).collect();
+
+        for n in threads {
+            n.join().unwrap();
+        }
+
+        // a write_read is one transaction, so its two operations are never split
+        let log = lock(&log);
+        assert_eq!(log.len(), 1200);
+
+        let mut n = 0;
+        while n < log.len() {
+            let (address, _, len) = log[n];
+            let expected: Vec<_> = (0..len).map(|i| (address, i, len)).collect();
+            assert_eq!(log[n..n + len], expected);
+            n += len;
+        }
+    }
+}
@@ -0,0 +1,536 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::protocol::MAX_PACKET_LEN;
+use crate::transceiver::Transceiver;
+
+/// bytes in front of every stream packet, kind, stream id, sequence number (u16 BE)
+pub const STREAM_HEADER_LEN: usize = 4;
+/// stream bytes carried by one packet
+pub const MAX_STREAM_PAYLOAD: usize = MAX_PACKET_LEN - STREAM_HEADER_LEN;
+
+const KIND_DATA: u8 = 1;
+const KIND_ACK: u8 = 2;
+/// end of stream, sequenced like data so it arrives after the last byte
+const KIND_FIN: u8 = 3;
+
+/// how a `RadioStream` sends and waits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamConfig {
+    /// both ends must use the same id, packets for other streams are ignored
+    pub stream_id: u8,
+    /// packets sent before waiting for an acknowledgement
+    pub window: usize,
+    /// how long to wait for an acknowledgement before sending the window again
+    pub retransmit_timeout: Duration,
+    /// retransmissions without progress before giving up with `io::ErrorKind::TimedOut`
+    pub max_retries: u32,
+    /// how long `read` waits for data, None blocks until data arrives
+    pub read_timeout: Option<Duration>,
+    /// time between polls of the Radio while waiting
+    pub poll_interval: Duration,
+}
+
+impl Default for StreamConfig {
+    /// ## Default
+    /// stream 0, a window of 4 packets, 250 ms retransmit timeout, 20 retries,
+    /// blocking reads, polling every 2 ms
+    fn default() -> Self {
+        StreamConfig {
+            stream_id: 0,
+            window: 4,
+            retransmit_timeout: Duration::from_millis(250),
+            max_retries: 20,
+            read_timeout: None,
+            poll_interval: Duration::from_millis(2),
+        }
+    }
+}
+
+/// counters for a `RadioStream`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamStats {
+    /// data packets sent for the first time
+    pub packets_sent: usize,
+    /// data packets sent again after a timeout
+    pub retransmits: usize,
+    /// data packets delivered in order
+    pub packets_received: usize,
+    /// data packets already delivered, or ahead of a lost one
+    pub out_of_order: usize,
+    /// packets that aren't for this stream, or acknowledge data never sent
+    pub ignored: usize,
+}
+
+fn radio_error<E: Debug>(e: E) -> io::Error {
+    io::Error::other(format!("Radio error | {:?}", e))
+}
+
+/// `seq` comes before `other`, allowing for wrap around
+fn before(seq: u16, other: u16) -> bool {
+    (other.wrapping_sub(seq) as i16) > 0
+}
+
+/// a reliable, ordered byte stream between two Radios
+///
+/// written bytes are collected into packets, numbered and sent up to
+/// `window` at a time, the other end acknowledges what it recieved in
+/// order and anything unacknowledged is sent again after the retransmit
+/// timeout (go-back-N)
+///
+/// there is no background thread, the Radio is only polled inside `read`,
+/// `write` and `flush`, so a writer that never reads still needs `flush`
+/// to find out its data arrived, and the other end has to be reading (or
+/// writing) for packets to be acknowledged
+///
+/// like `BufWriter`, bytes are only sent once a packet fills up or on
+/// `flush`, `close` sends an end of stream after which the other end's
+/// `read` returns 0
+///
+/// unlike `BufWriter` nothing is flushed on drop, as that would block until
+/// the other end acknowledges, bytes not yet sent are discarded and sent
+/// packets aren't retransmitted, so `flush` or `close` before dropping
+pub struct RadioStream<T> {
+    radio: T,
+    config: StreamConfig,
+    stats: StreamStats,
+
+    /// bytes waiting to fill a packet
+    pending: Vec<u8>,
+    next_seq: u16,
+    /// sent packets not yet acknowledged, oldest first
+    unacked: VecDeque<(u16, Vec<u8>)>,
+    /// when the oldest unacknowledged packet was last sent
+    last_send: Instant,
+    retries: u32,
+    closed: bool,
+    /// a failed send of a full packet, `write` had already accepted the bytes
+    /// so the error is returned by the next `write`, `flush` or `close`
+    error: Option<io::Error>,
+
+    /// next sequence number to deliver
+    expected: u16,
+    received: VecDeque<u8>,
+    eof: bool,
+    /// the end of stream was acknowledged until the link went quiet
+    lingered: bool,
+}
+
+impl<T: Transceiver> RadioStream<T> {
+    /// both ends have to start their streams fresh, sequence numbers start at 0
+    pub fn new(radio: T, config: StreamConfig) -> RadioStream<T> {
+        RadioStream {
+            radio,
+            config,
+            stats: StreamStats::default(),
+            pending: Vec::with_capacity(MAX_STREAM_PAYLOAD),
+            next_seq: 0,
+            unacked: VecDeque::new(),
+            last_send: Instant::now(),
+            retries: 0,
+            closed: false,
+            error: None,
+            expected: 0,
+            received: VecDeque::new(),
+            eof: false,
+            lingered: false,
+        }
+    }
+
+    pub fn config(&self) -> StreamConfig {
+        self.config
+    }
+
+    /// changes how long `read` waits, see `StreamConfig::read_timeout`
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.config.read_timeout = timeout;
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        self.stats
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.radio
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.radio
+    }
+
+    /// the Radio, anything unsent or unacknowledged is dropped
+    pub fn into_inner(self) -> T {
+        self.radio
+    }
+
+    /// sends everything written so far followed by an end of stream,
+    /// waiting until the other end has acknowledged it
+    ///
+    /// writes fail afterwards, reading still works, the other end's `read`
+    /// keeps acknowledging briefly after returning 0 in case the final
+    /// acknowledgement is lost
+    pub fn close(&mut self) -> io::Result<()> {
+        self.take_error()?;
+
+        if !self.closed {
+            self.flush_pending()?;
+            self.send_packet(KIND_FIN, &[])?;
+            self.closed = true;
+        }
+
+        return self.flush();
+    }
+
+    fn header(&self, kind: u8, seq: u16) -> Vec<u8> {
+        let seq = seq.to_be_bytes();
+        return vec![kind, self.config.stream_id, seq[0], seq[1]];
+    }
+
+    /// sends a sequenced packet, waiting for room in the window first
+    fn send_packet(&mut self, kind: u8, payload: &[u8]) -> io::Result<()> {
+        while self.unacked.len() >= self.config.window.max(1) {
+            if !self.service()? {
+                thread::sleep(self.config.poll_interval);
+            }
+        }
+
+        let seq = self.next_seq;
+        let mut packet = self.header(kind, seq);
+        packet.extend_from_slice(payload);
+
+        self.radio.transmit(&packet).map_err(radio_error)?;
+
+        if self.unacked.is_empty() {
+            self.last_send = Instant::now();
+        }
+
+        self.unacked.push_back((seq, packet));
+        self.next_seq = seq.wrapping_add(1);
+        self.stats.packets_sent += 1;
+
+        return Ok(());
+    }
+
+    /// sends the bytes waiting to fill a packet, they are kept if sending fails
+    fn flush_pending(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let payload = std::mem::take(&mut self.pending);
+        match self.send_packet(KIND_DATA, &payload) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                self.pending = payload;
+                Err(e)
+            },
+        }
+    }
+
+    /// returns the error deferred by an earlier `write`
+    fn take_error(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// polls the Radio once and retransmits if the timeout has passed
+    ///
+    /// ## Returns
+    /// wheather or not a packet was recieved
+    fn service(&mut self) -> io::Result<bool> {
+        let packet = self.radio.get_packet().map_err(radio_error)?;
+        let got = !packet.is_empty();
+
+        if got {
+            self.handle(&packet)?;
+        }
+
+        if !self.unacked.is_empty() && self.last_send.elapsed() >= self.config.retransmit_timeout {
+            self.retries += 1;
+            if self.retries > self.config.max_retries {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "other end stopped acknowledging"));
+            }
+
+            for (_, packet) in &self.unacked {
+                self.radio.transmit(packet).map_err(radio_error)?;
+                self.stats.retransmits += 1;
+            }
+
+            self.last_send = Instant::now();
+        }
+
+        return Ok(got);
+    }
+
+    /// keeps acknowledging after the end of stream until nothing arrives for
+    /// two retransmit timeouts, so a lost acknowledgement doesn't leave the
+    /// other end's `close` retrying after this end stopped reading
+    fn linger(&mut self) -> io::Result<()> {
+        if self.lingered {
+            return Ok(());
+        }
+
+        let mut quiet = Instant::now();
+        while quiet.elapsed() < self.config.retransmit_timeout * 2 {
+            match self.service()? {
+                true => quiet = Instant::now(),
+                false => thread::sleep(self.config.poll_interval),
+            };
+        }
+
+        self.lingered = true;
+        return Ok(());
+    }
+
+    fn handle(&mut self, packet: &[u8]) -> io::Result<()> {
+        if packet.len() < STREAM_HEADER_LEN || packet[1] != self.config.stream_id {
+            self.stats.ignored += 1;
+            return Ok(());
+        }
+
+        let seq = u16::from_be_bytes([packet[2], packet[3]]);
+
+        match packet[0] {
+            KIND_ACK => {
+                // acknowledges a packet that was never sent, stale or corrupted
+                if before(self.next_seq, seq) {
+                    self.stats.ignored += 1;
+                    return Ok(());
+                }
+
+                // cumulative, `seq` is the next packet the other end expects
+                let mut progress = false;
+                while self.unacked.front().is_some_and(|(n, _)| before(*n, seq)) {
+                    self.unacked.pop_front();
+                    progress = true;
+                }
+
+                if progress {
+                    self.retries = 0;
+                    self.last_send = Instant::now();
+                }
+            },
+            KIND_DATA | KIND_FIN => {
+                match seq == self.expected {
+                    true => {
+                        match packet[0] {
+                            KIND_FIN => self.eof = true,
+                            _ => self.received.extend(&packet[STREAM_HEADER_LEN..]),
+                        };
+
+                        self.expected = self.expected.wrapping_add(1);
+                        self.stats.packets_received += 1;
+                    },
+                    false => self.stats.out_of_order += 1,
+                };
+
+                // acknowledge duplicates too, the previous ack may have been lost
+                let ack = self.header(KIND_ACK, self.expected);
+                self.radio.transmit(&ack).map_err(radio_error)?;
+            },
+            _ => self.stats.ignored += 1,
+        };
+
+        return Ok(());
+    }
+}
+
+impl<T: Transceiver> Read for RadioStream<T> {
+    /// ## Returns
+    /// 0 once the other end has closed the stream and every byte was read,
+    /// `io::ErrorKind::TimedOut` if nothing arrives within the read timeout
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let deadline = self.config.read_timeout.map(|n| Instant::now() + n);
+
+        loop {
+            if !self.received.is_empty() {
+                let len = buf.len().min(self.received.len());
+                for (dst, src) in buf.iter_mut().zip(self.received.drain(..len)) {
+                    *dst = src;
+                }
+
+                return Ok(len);
+            }
+
+            if self.eof {
+                self.linger()?;
+                return Ok(0);
+            }
+
+            if !self.service()? {
+                if deadline.is_some_and(|n| Instant::now() >= n) {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "no data recieved"));
+                }
+
+                thread::sleep(self.config.poll_interval);
+            }
+        }
+    }
+}
+
+impl<T: Transceiver> Write for RadioStream<T> {
+    /// buffers up to a packet, sending it once full
+    ///
+    /// the bytes are accepted even if sending the full packet fails, the
+    /// error is returned by the next call and the packet is sent again then
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.closed {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "stream is closed"));
+        }
+
+        self.take_error()?;
+
+        // still full after a failed send, nothing is accepted until it goes out
+        if self.pending.len() == MAX_STREAM_PAYLOAD {
+            self.flush_pending()?;
+        }
+
+        let len = buf.len().min(MAX_STREAM_PAYLOAD - self.pending.len());
+        self.pending.extend_from_slice(&buf[..len]);
+
+        if self.pending.len() == MAX_STREAM_PAYLOAD {
+            if let Err(e) = self.flush_pending() {
+                self.error = Some(e);
+            }
+        }
+
+        return Ok(len);
+    }
+
+    /// sends any partial packet and waits until everything is acknowledged
+    fn flush(&mut self) -> io::Result<()> {
+        self.take_error()?;
+        self.flush_pending()?;
+
+        while !self.unacked.is_empty() {
+            if !self.service()? {
+                thread::sleep(self.config.poll_interval);
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockRadio;
+
+    fn ack(seq: u16) -> Vec<u8> {
+        let seq = seq.to_be_bytes();
+        vec![KIND_ACK, 0, seq[0], seq[1]]
+    }
+
+    #[test]
+    fn ignores_acks_for_unsent_data() {
+        let mut stream = RadioStream::new(MockRadio::new(), StreamConfig::default());
+        stream.write_all(&[1; MAX_STREAM_PAYLOAD]).unwrap();
+
+        stream.get_mut().push_rx(&ack(5));
+        stream.service().unwrap();
+        assert_eq!(stream.unacked.len(), 1);
+        assert_eq!(stream.stats().ignored, 1);
+
+        stream.get_mut().push_rx(&ack(1));
+        stream.service().unwrap();
+        assert!(stream.unacked.is_empty());
+    }
+
+    #[test]
+    fn write_defers_send_errors() {
+        let mut stream = RadioStream::new(MockRadio::new(), StreamConfig::default());
+        stream.get_mut().set_connected(false);
+
+        // the full packet fails to send, but its bytes were taken
+        assert_eq!(stream.write(&[1; MAX_STREAM_PAYLOAD + 10]).unwrap(), MAX_STREAM_PAYLOAD);
+        assert!(stream.write(&[2]).is_err());
+
+        stream.get_mut().set_connected(true);
+        assert_eq!(stream.write(&[2]).unwrap(), 1);
+
+        let sent = stream.get_mut().take_transmitted();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0][STREAM_HEADER_LEN..], [1; MAX_STREAM_PAYLOAD]);
+
+        // the error is surfaced by flush too, the ack covers the byte flush sends
+        stream.get_mut().push_rx(&ack(2));
+        stream.flush().unwrap();
+        stream.get_mut().set_connected(false);
+        stream.write_all(&[3; MAX_STREAM_PAYLOAD]).unwrap();
+        assert!(stream.flush().is_err());
+    }
+
+    fn config() -> StreamConfig {
+        StreamConfig {
+            retransmit_timeout: Duration::from_millis(50),
+            read_timeout: Some(Duration::from_secs(5)),
+            ..StreamConfig::default()
+        }
+    }
+
+    #[test]
+    fn round_trip_over_pair() {
+        let (a, b) = MockRadio::pair();
+        let data: Vec<u8> = (0..20_000).map(|i| (i % 251) as u8).collect();
+
+        let expected = data.clone();
+        let reader = thread::spawn(move || {
+            let mut stream = RadioStream::new(b, config());
+            let mut out = vec![];
+            stream.read_to_end(&mut out).unwrap();
+            (out, stream.stats())
+        });
+
+        let mut stream = RadioStream::new(a, config());
+        stream.write_all(&data).unwrap();
+        stream.close().unwrap();
+        assert!(stream.write(b"x").is_err());
+
+        let (out, stats) = reader.join().unwrap();
+        assert_eq!(out, expected);
+        assert_eq!(stats.packets_received, data.len().div_ceil(MAX_STREAM_PAYLOAD) + 1);
+    }
+
+    #[test]
+    fn malformed_packets() {
+        let mut stream = RadioStream::new(MockRadio::new(), config());
+
+        let radio = stream.get_mut();
+        // too short, another stream, an unknown kind
+        radio.push_rx(&[KIND_DATA, 0, 0]);
+        radio.push_rx(&[KIND_DATA, 9, 0, 0, 1]);
+        radio.push_rx(&[7, 0, 0, 0, 1]);
+        // ahead of the expected packet, then the expected one
+        radio.push_rx(&[KIND_DATA, 0, 0, 1, 2]);
+        radio.push_rx(&[KIND_DATA, 0, 0, 0, 1]);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(stream.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 1);
+
+        let stats = stream.stats();
+        assert_eq!((stats.ignored, stats.out_of_order, stats.packets_received), (3, 1, 1));
+
+        // every data packet is acknowledged with the next sequence number expected
+        assert_eq!(stream.get_mut().take_transmitted(), vec![ack(0), ack(1)]);
+    }
+
+    #[test]
+    fn read_timeout() {
+        let mut stream = RadioStream::new(MockRadio::new(), config());
+        stream.set_read_timeout(Some(Duration::from_millis(20)));
+
+        let err = stream.read(&mut [0u8; 8]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
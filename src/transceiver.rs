@@ -0,0 +1,132 @@
+use crate::protocol::ModulationFormat;
+
+/// common interface to a Radio, regardless of the link it is driven over
+/// 
+/// implemented by both `radio_serial::Radio` and `radio_i2c::Radio`, so
+/// tooling can be written once for either
+pub trait Transceiver {
+    type Error: core::fmt::Debug;
+
+    /// transmits the given message
+    fn transmit(&mut self, msg: &[u8]) -> Result<(), Self::Error>;
+
+    /// gets the most recent packet stored on the Radio
+    /// 
+    /// empty Vec/<u8/> if no available packet was found
+    fn get_packet(&mut self) -> Result<Vec<u8>, Self::Error>;
+
+    /// attempts to set the frequency of the radio
+    fn set_frequency(&mut self, frequency: f32) -> Result<(), Self::Error>;
+
+    /// attempts to set the tx gain of the radio
+    fn set_power(&mut self, power: f32) -> Result<(), Self::Error>;
+
+    /// attempts to set the FSK bandwith of the radio
+    fn set_deviation(&mut self, deviation: f32) -> Result<(), Self::Error>;
+
+    /// attempts to set the symbol rate of the radio
+    fn set_symbol_rate(&mut self, symbol_rate: f32) -> Result<(), Self::Error>;
+
+    /// attempts to set the recieve filter of the radio
+    fn set_rx_filter(&mut self, rx_filter: f32) -> Result<(), Self::Error>;
+
+    /// sets the modulation mode of the radio
+    fn set_modulation(&mut self, mode: ModulationFormat) -> Result<(), Self::Error>;
+
+    /// sends a reset command to reset the onboard Radio chip
+    fn radio_reset(&mut self) -> Result<(), Self::Error>;
+
+    /// sends a reset command to perform a soft reset on the entire board
+    fn soft_reset(&mut self) -> Result<(), Self::Error>;
+}
+
+/// a set of Radio settings, None leaves that setting unchanged
+/// 
+/// the Radio can't report its settings, so tooling keeps one of these
+/// alongside the Radio to know what it is configured for
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RadioConfig {
+    pub frequency: Option<f32>,
+    pub power: Option<f32>,
+    pub deviation: Option<f32>,
+    pub symbol_rate: Option<f32>,
+    pub rx_filter: Option<f32>,
+    pub modulation: Option<ModulationFormat>,
+}
+
+impl RadioConfig {
+    /// sends every setting that is Some to the Radio, stopping at the first error
+    pub fn apply<T: Transceiver + ?Sized>(&self, radio: &mut T) -> Result<(), T::Error> {
+        if let Some(val) = self.frequency {
+            radio.set_frequency(val)?;
+        }
+        if let Some(val) = self.power {
+            radio.set_power(val)?;
+        }
+        if let Some(val) = self.deviation {
+            radio.set_deviation(val)?;
+        }
+        if let Some(val) = self.symbol_rate {
+            radio.set_symbol_rate(val)?;
+        }
+        if let Some(val) = self.rx_filter {
+            radio.set_rx_filter(val)?;
+        }
+        if let Some(val) = self.modulation {
+            radio.set_modulation(val)?;
+        }
+
+        return Ok(());
+    }
+}
+
+/// implements `Transceiver` by forwarding to the inherent methods of the same name
+#[cfg(any(feature="serial", feature="i2c"))]
+macro_rules! impl_transceiver {
+    ($error:ty) => {
+        type Error = $error;
+
+        fn transmit(&mut self, msg: &[u8]) -> Result<(), $error> {
+            Self::transmit(self, msg)
+        }
+
+        fn get_packet(&mut self) -> Result<Vec<u8>, $error> {
+            Self::get_packet(self)
+        }
+
+        fn set_frequency(&mut self, frequency: f32) -> Result<(), $error> {
+            Self::set_frequency(self, frequency)
+        }
+
+        fn set_power(&mut self, power: f32) -> Result<(), $error> {
+            Self::set_power(self, power)
+        }
+
+        fn set_deviation(&mut self, deviation: f32) -> Result<(), $error> {
+            Self::set_deviation(self, deviation)
+        }
+
+        fn set_symbol_rate(&mut self, symbol_rate: f32) -> Result<(), $error> {
+            Self::set_symbol_rate(self, symbol_rate)
+        }
+
+        fn set_rx_filter(&mut self, rx_filter: f32) -> Result<(), $error> {
+            Self::set_rx_filter(self, rx_filter)
+        }
+
+        fn set_modulation(&mut self, mode: crate::protocol::ModulationFormat) -> Result<(), $error> {
+            Self::set_modulation(self, mode)
+        }
+
+        fn radio_reset(&mut self) -> Result<(), $error> {
+            Self::radio_reset(self)
+        }
+
+        fn soft_reset(&mut self) -> Result<(), $error> {
+            Self::soft_reset(self)
+        }
+    };
+}
+
+#[cfg(any(feature="serial", feature="i2c"))]
+pub(crate) use impl_transceiver;
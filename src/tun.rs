@@ -0,0 +1,354 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::net::Ipv4Addr;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::fragment::{Fragmenter, ReassemblyStats, Reassembler, MAX_MESSAGE_LEN};
+use crate::shared::SharedRadio;
+use crate::transceiver::Transceiver;
+
+/// interface MTU used by `armlab-radio tun`, the smallest IPv6 allows
+///
+/// a full sized packet is sent as 6 fragments
+pub const DEFAULT_MTU: usize = 1280;
+
+/// largest MTU the bridge can carry, every packet must fit in one fragmented message
+pub const MAX_MTU: usize = MAX_MESSAGE_LEN;
+
+/// a Linux TUN interface, reads and writes are whole IP packets
+///
+/// creating one needs CAP_NET_ADMIN
+pub struct TunDevice {
+    file: File,
+    name: String,
+}
+
+/// an `ifreq` naming `name`, the rest zeroed
+fn ifreq(name: &str) -> io::Result<libc::ifreq> {
+    if name.len() >= libc::IFNAMSIZ || name.contains('\0') {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "interface name is too long"));
+    }
+
+    // all zero is a valid ifreq
+    let mut req: libc::ifreq = unsafe { mem::zeroed() };
+    for (dst, src) in req.ifr_name.iter_mut().zip(name.bytes()) {
+        *dst = src as libc::c_char;
+    }
+
+    return Ok(req);
+}
+
+fn ioctl(fd: libc::c_int, request: libc::Ioctl, req: &mut libc::ifreq) -> io::Result<()> {
+    match unsafe { libc::ioctl(fd, request, req as *mut libc::ifreq) } {
+        n if n < 0 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// a socket to configure interfaces through
+fn control_socket() -> io::Result<OwnedFd> {
+    match unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) } {
+        n if n < 0 => Err(io::Error::last_os_error()),
+        n => Ok(unsafe { OwnedFd::from_raw_fd(n) }),
+    }
+}
+
+fn sockaddr(addr: Ipv4Addr) -> libc::sockaddr {
+    let sin = libc::sockaddr_in {
+        sin_family: libc::AF_INET as libc::sa_family_t,
+        sin_port: 0,
+        sin_addr: libc::in_addr { s_addr: u32::from(addr).to_be() },
+        sin_zero: [0; 8],
+    };
+
+    // sockaddr_in and sockaddr are the same size
+    return unsafe { mem::transmute::<libc::sockaddr_in, libc::sockaddr>(sin) };
+}
+
+impl TunDevice {
+    /// creates the interface `name`, a name like `arm%d` lets the kernel number it
+    ///
+    /// the interface starts down with no address, see `set_address` and `up`
+    pub fn create(name: &str) -> io::Result<TunDevice> {
+        let file = OpenOptions::new().read(true).write(true).open("/dev/net/tun")?;
+
+        let mut req = ifreq(name)?;
+        req.ifr_ifru.ifru_flags = (libc::IFF_TUN | libc::IFF_NO_PI) as libc::c_short;
+        ioctl(file.as_raw_fd(), libc::TUNSETIFF, &mut req)?;
+
+        let name = req.ifr_name.iter()
+            .take_while(|c| **c != 0)
+            .map(|c| *c as u8 as char)
+            .collect();
+
+        return Ok(TunDevice { file, name });
+    }
+
+    /// the interface name given by the kernel
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// a second handle to the same interface, to read and write from different threads
+    pub fn try_clone(&self) -> io::Result<TunDevice> {
+        Ok(TunDevice { file: self.file.try_clone()?, name: self.name.clone() })
+    }
+
+    pub fn set_mtu(&self, mtu: usize) -> io::Result<()> {
+        let mut req = ifreq(&self.name)?;
+        req.ifr_ifru.ifru_mtu = mtu as libc::c_int;
+
+        return ioctl(control_socket()?.as_raw_fd(), libc::SIOCSIFMTU as libc::Ioctl, &mut req);
+    }
+
+    /// assigns an IPv4 address, `prefix` is the netmask length e.g. 24
+    pub fn set_address(&self, addr: Ipv4Addr, prefix: u8) -> io::Result<()> {
+        if prefix > 32 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "prefix is longer than 32 bits"));
+        }
+
+        let socket = control_socket()?;
+        let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+
+        let mut req = ifreq(&self.name)?;
+        req.ifr_ifru.ifru_addr = sockaddr(addr);
+        ioctl(socket.as_raw_fd(), libc::SIOCSIFADDR as libc::Ioctl, &mut req)?;
+
+        let mut req = ifreq(&self.name)?;
+        req.ifr_ifru.ifru_netmask = sockaddr(Ipv4Addr::from(mask));
+        return ioctl(socket.as_raw_fd(), libc::SIOCSIFNETMASK as libc::Ioctl, &mut req);
+    }
+
+    /// brings the interface up
+    pub fn up(&self) -> io::Result<()> {
+        let socket = control_socket()?;
+
+        let mut req = ifreq(&self.name)?;
+        ioctl(socket.as_raw_fd(), libc::SIOCGIFFLAGS as libc::Ioctl, &mut req)?;
+
+        unsafe {
+            req.ifr_ifru.ifru_flags |= (libc::IFF_UP | libc::IFF_RUNNING) as libc::c_short;
+        }
+        return ioctl(socket.as_raw_fd(), libc::SIOCSIFFLAGS as libc::Ioctl, &mut req);
+    }
+}
+
+impl Read for TunDevice {
+    /// reads one IP packet, `buf` should be at least the MTU
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for TunDevice {
+    /// writes one IP packet
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// counters for a running `TunBridge`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TunStats {
+    /// IP packets transmitted by the Radio
+    pub sent: usize,
+    /// IP packets recieved by the Radio and written to the interface
+    pub received: usize,
+    /// Radio packets transmitted, one per fragment
+    pub fragments_sent: usize,
+    /// packets that couldn't be transmitted, too long or a Radio error
+    pub tx_errors: usize,
+    /// failed polls of the Radio, or writes to the interface
+    pub rx_errors: usize,
+    pub reassembly: ReassemblyStats,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(n) => n,
+        Err(n) => n.into_inner(),
+    }
+}
+
+/// what a single `TunBridge::poll` recieved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TunPoll {
+    /// nothing was waiting on the Radio, or polling it failed
+    Idle,
+    /// a fragment of a packet that is still missing others
+    Fragment,
+    /// a whole IP packet
+    Packet(Vec<u8>),
+}
+
+/// carries IP packets over a Radio, fragmenting those longer than one packet
+///
+/// both ends of a link run a bridge, each in front of its own interface,
+/// so the link behaves like a point to point network
+pub struct TunBridge<T> {
+    radio: SharedRadio<T>,
+    fragmenter: Arc<Mutex<Fragmenter>>,
+    reassembler: Arc<Mutex<Reassembler>>,
+    stats: Arc<Mutex<TunStats>>,
+    poll_interval: Duration,
+}
+
+impl<T> Clone for TunBridge<T> {
+    fn clone(&self) -> Self {
+        TunBridge {
+            radio: self.radio.clone(),
+            fragmenter: self.fragmenter.clone(),
+            reassembler: self.reassembler.clone(),
+            stats: self.stats.clone(),
+            poll_interval: self.poll_interval,
+        }
+    }
+}
+
+impl<T: Transceiver + Send + 'static> TunBridge<T> {
+    pub fn new(radio: SharedRadio<T>) -> TunBridge<T> {
+        TunBridge {
+            radio,
+            fragmenter: Arc::new(Mutex::new(Fragmenter::new())),
+            reassembler: Arc::new(Mutex::new(Reassembler::new(Duration::from_secs(5)))),
+            stats: Arc::new(Mutex::new(TunStats::default())),
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+
+    /// sets the time between polls of the Radio
+    ///
+    /// ## Default
+    /// 10 ms
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// sets how long a partly recieved packet waits for its missing fragments
+    ///
+    /// ## Default
+    /// 5 s
+    pub fn set_reassembly_timeout(&mut self, timeout: Duration) {
+        self.reassembler = Arc::new(Mutex::new(Reassembler::new(timeout)));
+    }
+
+    pub fn stats(&self) -> TunStats {
+        let mut stats = *lock(&self.stats);
+        stats.reassembly = lock(&self.reassembler).stats();
+        return stats;
+    }
+
+    /// transmits an IP packet
+    ///
+    /// the fragments are sent back to back while holding the Radio, so
+    /// they aren't interleaved with other users of the `SharedRadio`
+    ///
+    /// ## Returns
+    /// wheather or not every fragment was transmitted
+    pub fn send(&self, packet: &[u8]) -> bool {
+        let fragments = match lock(&self.fragmenter).split(packet) {
+            Ok(n) => n,
+            Err(_) => {
+                lock(&self.stats).tx_errors += 1;
+                return false;
+            },
+        };
+
+        let res = self.radio.with(|r| {
+            for fragment in &fragments {
+                r.transmit(fragment)?;
+            }
+            Ok::<(), T::Error>(())
+        });
+
+        let mut stats = lock(&self.stats);
+        match res {
+            Ok(_) => {
+                stats.sent += 1;
+                stats.fragments_sent += fragments.len();
+            },
+            Err(_) => stats.tx_errors += 1,
+        };
+
+        return res.is_ok();
+    }
+
+    /// polls the Radio once
+    ///
+    /// ## Returns
+    /// the packet once all of its fragments have arrived, `TunPoll::Fragment`
+    /// for the ones before, the rest are likely already waiting
+    pub fn poll(&self) -> TunPoll {
+        let packet = match self.radio.with(|r| r.get_packet()) {
+            Ok(n) => n,
+            Err(_) => {
+                lock(&self.stats).rx_errors += 1;
+                return TunPoll::Idle;
+            },
+        };
+
+        if packet.is_empty() {
+            return TunPoll::Idle;
+        }
+
+        return match lock(&self.reassembler).push(&packet) {
+            Some(n) => TunPoll::Packet(n),
+            None => TunPoll::Fragment,
+        };
+    }
+
+    /// forwards packets between `device` and the Radio until reading the device fails
+    ///
+    /// packets from the interface are sent on a new thread, the calling
+    /// thread polls the Radio
+    pub fn run(&self, device: TunDevice) -> io::Result<()> {
+        let mut reader = device.try_clone()?;
+        let mut writer = device;
+
+        let bridge = self.clone();
+        let sender = thread::spawn(move || {
+            let mut buf = vec![0u8; MAX_MTU];
+
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(n) => {bridge.send(&buf[..n]);},
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                    Err(e) => {return e;},
+                };
+            }
+        });
+
+        while !sender.is_finished() {
+            // only sleep once the Radio is empty, not between the fragments of a packet
+            let packet = match self.poll() {
+                TunPoll::Packet(n) => n,
+                TunPoll::Fragment => {continue;},
+                TunPoll::Idle => {
+                    thread::sleep(self.poll_interval);
+                    continue;
+                },
+            };
+
+            let res = writer.write_all(&packet);
+
+            let mut stats = lock(&self.stats);
+            match res {
+                Ok(_) => stats.received += 1,
+                Err(_) => stats.rx_errors += 1,
+            };
+        }
+
+        return match sender.join() {
+            Ok(e) => Err(e),
+            Err(_) => Err(io::Error::other("interface reader panicked")),
+        };
+    }
+}
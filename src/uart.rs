@@ -0,0 +1,359 @@
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(all(feature = "serial", unix))]
+use serialport::{SerialPort, TTYPort};
+
+use crate::protocol::MAX_PACKET_LEN;
+use crate::shared::SharedRadio;
+use crate::transceiver::Transceiver;
+
+/// when bytes written to a `WirelessUart` are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UartConfig {
+    /// longest a byte waits to be sent, a packet goes out this long after its first byte
+    pub flush_interval: Duration,
+    /// packet size, a packet goes out as soon as this many bytes are waiting
+    pub max_packet: usize,
+}
+
+impl Default for UartConfig {
+    /// ## Default
+    /// 20 ms flush interval, `MAX_PACKET_LEN` byte packets
+    fn default() -> Self {
+        UartConfig {
+            flush_interval: Duration::from_millis(20),
+            max_packet: MAX_PACKET_LEN,
+        }
+    }
+}
+
+/// groups a byte stream into packets, see `UartConfig`
+#[derive(Debug)]
+pub struct Chunker {
+    config: UartConfig,
+    buf: Vec<u8>,
+    /// when the oldest byte in `buf` arrived
+    since: Option<Instant>,
+}
+
+impl Chunker {
+    /// `config.max_packet` is clamped to 1..=`MAX_PACKET_LEN`
+    pub fn new(mut config: UartConfig) -> Chunker {
+        config.max_packet = config.max_packet.clamp(1, MAX_PACKET_LEN);
+
+        Chunker {
+            config,
+            buf: Vec::with_capacity(config.max_packet),
+            since: None,
+        }
+    }
+
+    /// the bytes waiting to be sent
+    pub fn pending(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// adds bytes recieved at `now`
+    ///
+    /// ## Returns
+    /// every packet that filled up
+    pub fn push(&mut self, data: &[u8], now: Instant) -> Vec<Vec<u8>> {
+        let mut packets = vec![];
+
+        for &byte in data {
+            if self.buf.is_empty() {
+                self.since = Some(now);
+            }
+
+            self.buf.push(byte);
+
+            if self.buf.len() == self.config.max_packet {
+                packets.push(self.take());
+            }
+        }
+
+        return packets;
+    }
+
+    /// ## Returns
+    /// the waiting bytes once the oldest has waited the flush interval
+    pub fn poll(&mut self, now: Instant) -> Option<Vec<u8>> {
+        match self.since {
+            Some(n) if now.saturating_duration_since(n) >= self.config.flush_interval => Some(self.take()),
+            _ => None,
+        }
+    }
+
+    /// the waiting bytes, regardless of the flush interval
+    pub fn take(&mut self) -> Vec<u8> {
+        self.since = None;
+        return std::mem::replace(&mut self.buf, Vec::with_capacity(self.config.max_packet));
+    }
+}
+
+/// counters for a running `WirelessUart`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UartStats {
+    /// bytes written by the serial side and transmitted
+    pub bytes_sent: usize,
+    /// bytes recieved by the Radio and written to the serial side
+    pub bytes_received: usize,
+    pub packets_sent: usize,
+    pub packets_received: usize,
+    /// packets that couldn't be transmitted
+    pub tx_errors: usize,
+    /// failed polls of the Radio, or writes to the serial side
+    pub rx_errors: usize,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(n) => n,
+        Err(n) => n.into_inner(),
+    }
+}
+
+type Output = Arc<Mutex<Option<Box<dyn Write + Send>>>>;
+
+/// a transparent serial link over a Radio, two of them act as a wireless serial cable
+///
+/// bytes written on the serial side are grouped into packets and
+/// transmitted, every recieved packet is written back out unchanged
+///
+/// there is no framing or retransmission, a lost packet loses its bytes
+/// just like noise on a real serial line would
+pub struct WirelessUart<T> {
+    radio: SharedRadio<T>,
+    config: UartConfig,
+    output: Output,
+    stats: Arc<Mutex<UartStats>>,
+    poll_interval: Duration,
+}
+
+impl<T> Clone for WirelessUart<T> {
+    fn clone(&self) -> Self {
+        WirelessUart {
+            radio: self.radio.clone(),
+            config: self.config,
+            output: self.output.clone(),
+            stats: self.stats.clone(),
+            poll_interval: self.poll_interval,
+        }
+    }
+}
+
+impl<T: Transceiver + Send + 'static> WirelessUart<T> {
+    pub fn new(radio: SharedRadio<T>, config: UartConfig) -> WirelessUart<T> {
+        WirelessUart {
+            radio,
+            config,
+            output: Arc::new(Mutex::new(None)),
+            stats: Arc::new(Mutex::new(UartStats::default())),
+            poll_interval: Duration::from_millis(5),
+        }
+    }
+
+    /// sets the time between polls of the Radio
+    ///
+    /// ## Default
+    /// 5 ms
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    pub fn config(&self) -> UartConfig {
+        self.config
+    }
+
+    pub fn stats(&self) -> UartStats {
+        *lock(&self.stats)
+    }
+
+    fn transmit(&self, packet: &[u8]) {
+        if packet.is_empty() {
+            return;
+        }
+
+        let res = self.radio.with(|r| r.transmit(packet));
+
+        let mut stats = lock(&self.stats);
+        match res {
+            Ok(_) => {
+                stats.packets_sent += 1;
+                stats.bytes_sent += packet.len();
+            },
+            Err(_) => stats.tx_errors += 1,
+        };
+    }
+
+    /// makes a pair of streams the serial side, replacing any previous one
+    ///
+    /// `reader` is read on a new thread until it closes, it should time out
+    /// (`io::ErrorKind::TimedOut` or `WouldBlock`) within the flush interval
+    /// while idle, otherwise a partial packet waits for the next byte
+    pub fn attach<R: Read + Send + 'static, W: Write + Send + 'static>(&self, mut reader: R, writer: W) {
+        *lock(&self.output) = Some(Box::new(writer));
+
+        let uart = self.clone();
+        thread::spawn(move || {
+            let mut chunker = Chunker::new(uart.config);
+            let mut buf = [0u8; 1024];
+
+            loop {
+                let res = reader.read(&mut buf);
+                let now = Instant::now();
+
+                match res {
+                    Ok(0) => {break;},
+                    Ok(n) => {
+                        for packet in chunker.push(&buf[..n], now) {
+                            uart.transmit(&packet);
+                        }
+                    },
+                    Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted) => {},
+                    Err(_) => {break;},
+                };
+
+                if let Some(packet) = chunker.poll(now) {
+                    uart.transmit(&packet);
+                }
+            }
+
+            // whatever was written before the serial side closed is still sent
+            uart.transmit(&chunker.take());
+        });
+    }
+
+    /// creates a pseudo terminal as the serial side, for software that writes to a serial port
+    ///
+    /// ## Returns
+    /// the path of the terminal to give that software, e.g. /dev/pts/3
+    #[cfg(all(feature = "serial", unix))]
+    pub fn open_pty(&self) -> io::Result<String> {
+        let (mut master, mut slave) = TTYPort::pair()?;
+
+        // reads wake up at least once per flush interval to send partial packets
+        master.set_timeout(self.config.flush_interval.max(Duration::from_millis(1)))?;
+        // other processes (and users) have to be able to open the terminal
+        slave.set_exclusive(false)?;
+
+        let path = match slave.name() {
+            Some(n) => n,
+            None => {return Err(io::Error::other("pseudo terminal has no name"));},
+        };
+
+        let writer = master.try_clone_native()?;
+        self.attach(master, writer);
+
+        // the slave end is kept open for as long as the program runs, otherwise
+        // reads on the master fail whenever no program has the terminal open
+        thread::spawn(move || {
+            let _slave = slave;
+            loop {
+                thread::park();
+            }
+        });
+
+        return Ok(path);
+    }
+
+    /// polls the Radio forever, writing every packet to the serial side
+    pub fn run(&self) {
+        loop {
+            match self.poll() {
+                true => {},
+                false => thread::sleep(self.poll_interval),
+            };
+        }
+    }
+
+    /// polls the Radio once
+    ///
+    /// ## Returns
+    /// wheather or not a packet was recieved
+    pub fn poll(&self) -> bool {
+        let packet = match self.radio.with(|r| r.get_packet()) {
+            Ok(n) => n,
+            Err(_) => {
+                lock(&self.stats).rx_errors += 1;
+                return false;
+            },
+        };
+
+        if packet.is_empty() {
+            return false;
+        }
+
+        let res = match lock(&self.output).as_mut() {
+            Some(out) => out.write_all(&packet).and_then(|_| out.flush()),
+            // nothing attached yet, like a serial line with no one listening
+            None => Ok(()),
+        };
+
+        let mut stats = lock(&self.stats);
+        match res {
+            Ok(_) => {
+                stats.packets_received += 1;
+                stats.bytes_received += packet.len();
+            },
+            Err(_) => stats.rx_errors += 1,
+        };
+
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunker(flush_ms: u64, max_packet: usize) -> Chunker {
+        Chunker::new(UartConfig { flush_interval: Duration::from_millis(flush_ms), max_packet })
+    }
+
+    #[test]
+    fn full_packets() {
+        let mut chunker = chunker(20, 4);
+        let now = Instant::now();
+
+        assert_eq!(chunker.push(b"abcdefghij", now), vec![b"abcd".to_vec(), b"efgh".to_vec()]);
+        assert_eq!(chunker.pending(), 2);
+        assert_eq!(chunker.push(b"kl", now), vec![b"ijkl".to_vec()]);
+        assert_eq!(chunker.pending(), 0);
+        assert_eq!(chunker.poll(now + Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn flush_interval() {
+        let mut chunker = chunker(20, 255);
+        let start = Instant::now();
+
+        assert!(chunker.push(b"ab", start).is_empty());
+        // a later byte doesn't restart the clock, the oldest byte decides
+        assert!(chunker.push(b"c", start + Duration::from_millis(15)).is_empty());
+
+        assert_eq!(chunker.poll(start + Duration::from_millis(19)), None);
+        assert_eq!(chunker.poll(start + Duration::from_millis(20)), Some(b"abc".to_vec()));
+        assert_eq!(chunker.poll(start + Duration::from_millis(60)), None);
+
+        // a time before the first byte, e.g. from another thread's clock read
+        assert!(chunker.push(b"d", start + Duration::from_millis(30)).is_empty());
+        assert_eq!(chunker.poll(start), None);
+
+        assert_eq!(chunker.take(), b"d".to_vec());
+        assert!(chunker.take().is_empty());
+    }
+
+    #[test]
+    fn packet_size_is_clamped() {
+        let now = Instant::now();
+
+        assert_eq!(chunker(20, 0).push(b"ab", now), vec![b"a".to_vec(), b"b".to_vec()]);
+
+        let packets = chunker(20, 10_000).push(&[0; 600], now);
+        assert_eq!(packets.iter().map(|p| p.len()).collect::<Vec<usize>>(), vec![MAX_PACKET_LEN, MAX_PACKET_LEN]);
+    }
+}
@@ -0,0 +1,333 @@
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::capture::{CaptureRecord, Direction};
+use crate::encoding::base64_encode;
+use crate::protocol::MAX_PACKET_LEN;
+use crate::shared::SharedRadio;
+use crate::transceiver::{RadioConfig, Transceiver};
+
+/// length of the metadata in front of each payload with `Framing::Header`
+///
+/// | offset | size | field |
+/// |--------|------|-------|
+/// | 0 | 8 | timestamp, u64 LE (microseconds since the unix epoch) |
+/// | 8 | 12 | the `capture` packet header, see `capture::CAPTURE_HEADER_LEN` |
+pub const UDP_HEADER_LEN: usize = 20;
+
+/// how recieved packets are put into datagrams
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    /// the payload only
+    Raw,
+    /// `UDP_HEADER_LEN` bytes of binary metadata, then the payload
+    Header,
+    /// one JSON object per datagram, the payload base64 encoded
+    Json,
+}
+
+impl Framing {
+    /// the datagram for a recieved packet
+    pub fn encode(&self, record: &CaptureRecord) -> Vec<u8> {
+        match self {
+            Framing::Raw => record.payload.clone(),
+            Framing::Header => {
+                let mut out = (record.timestamp.as_micros() as u64).to_le_bytes().to_vec();
+                out.extend_from_slice(&record.encode());
+                out
+            },
+            Framing::Json => {
+                let opt = |v: Option<String>| v.unwrap_or("null".to_string());
+
+                format!(
+                    "{{\"timestamp\":{:.6},\"rssi\":{},\"lqi\":{},\"frequency\":{},\"modulation\":{},\"payload\":\"{}\"}}",
+                    record.timestamp.as_secs_f64(),
+                    opt(record.rssi.map(|n| n.to_string())),
+                    opt(record.lqi.map(|n| n.to_string())),
+                    opt(record.frequency.map(|n| n.to_string())),
+                    opt(record.modulation.map(|n| format!("\"{:?}\"", n))),
+                    base64_encode(&record.payload),
+                ).into_bytes()
+            },
+        }
+    }
+}
+
+/// counters for a running `UdpGateway`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UdpStats {
+    /// packets recieved by the Radio and sent to the destinations
+    pub forwarded: usize,
+    /// datagrams transmitted by the Radio
+    pub transmitted: usize,
+    /// datagrams that couldn't be transmitted, too long or a Radio error
+    pub tx_errors: usize,
+    /// failed polls of the Radio, or datagrams that couldn't be sent
+    pub rx_errors: usize,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(n) => n,
+        Err(n) => n.into_inner(),
+    }
+}
+
+/// bridges a Radio to UDP
+///
+/// every packet the Radio recieves is sent to each destination, every
+/// datagram arriving on the local port is transmitted as is
+pub struct UdpGateway<T> {
+    radio: SharedRadio<T>,
+    socket: Arc<UdpSocket>,
+    destinations: Arc<Mutex<Vec<SocketAddr>>>,
+    framing: Framing,
+    /// settings reported in the metadata
+    config: Arc<Mutex<RadioConfig>>,
+    stats: Arc<Mutex<UdpStats>>,
+    poll_interval: Duration,
+}
+
+impl<T> Clone for UdpGateway<T> {
+    fn clone(&self) -> Self {
+        UdpGateway {
+            radio: self.radio.clone(),
+            socket: self.socket.clone(),
+            destinations: self.destinations.clone(),
+            framing: self.framing,
+            config: self.config.clone(),
+            stats: self.stats.clone(),
+            poll_interval: self.poll_interval,
+        }
+    }
+}
+
+impl<T: Transceiver + Send + 'static> UdpGateway<T> {
+    /// listens for datagrams to transmit on `local`, port 0 picks a free port
+    ///
+    /// recieved packets are sent from the same socket
+    pub fn bind<A: ToSocketAddrs>(radio: SharedRadio<T>, local: A) -> io::Result<UdpGateway<T>> {
+        let socket = UdpSocket::bind(local)?;
+
+        return Ok(UdpGateway {
+            radio,
+            socket: Arc::new(socket),
+            destinations: Arc::new(Mutex::new(vec![])),
+            framing: Framing::Raw,
+            config: Arc::new(Mutex::new(RadioConfig::default())),
+            stats: Arc::new(Mutex::new(UdpStats::default())),
+            poll_interval: Duration::from_millis(10),
+        });
+    }
+
+    /// the address datagrams to transmit are accepted on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// sends recieved packets to `addr` as well
+    pub fn add_destination(&self, addr: SocketAddr) {
+        lock(&self.destinations).push(addr);
+    }
+
+    /// sets how recieved packets are framed
+    ///
+    /// ## Default
+    /// `Framing::Raw`
+    pub fn set_framing(&mut self, framing: Framing) {
+        self.framing = framing;
+    }
+
+    /// the Radio settings reported in the metadata, the gateway doesn't change them
+    pub fn set_config(&self, config: RadioConfig) {
+        *lock(&self.config) = config;
+    }
+
+    /// sets the time between polls of the Radio
+    ///
+    /// ## Default
+    /// 10 ms
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    pub fn stats(&self) -> UdpStats {
+        *lock(&self.stats)
+    }
+
+    /// transmits a datagram recieved on the local port
+    ///
+    /// ## Returns
+    /// wheather or not it was transmitted
+    pub fn handle_datagram(&self, data: &[u8]) -> bool {
+        let res = match !data.is_empty() && data.len() <= MAX_PACKET_LEN {
+            true => self.radio.with(|r| r.transmit(data)).is_ok(),
+            false => false,
+        };
+
+        let mut stats = lock(&self.stats);
+        match res {
+            true => stats.transmitted += 1,
+            false => stats.tx_errors += 1,
+        };
+
+        return res;
+    }
+
+    /// polls the Radio forever, forwarding every packet, while datagrams
+    /// on the local port are transmitted from another thread
+    pub fn run(&self) {
+        let gateway = self.clone();
+        thread::spawn(move || {
+            let mut buf = [0u8; 65536];
+
+            loop {
+                // errors are e.g. ICMP port unreachable from an earlier send, not fatal
+                if let Ok((n, _)) = gateway.socket.recv_from(&mut buf) {
+                    gateway.handle_datagram(&buf[..n]);
+                }
+            }
+        });
+
+        loop {
+            match self.poll() {
+                true => {},
+                false => thread::sleep(self.poll_interval),
+            };
+        }
+    }
+
+    /// polls the Radio once
+    ///
+    /// ## Returns
+    /// wheather or not a packet was recieved
+    pub fn poll(&self) -> bool {
+        let packet = match self.radio.with(|r| r.get_packet()) {
+            Ok(n) => n,
+            Err(_) => {
+                lock(&self.stats).rx_errors += 1;
+                return false;
+            },
+        };
+
+        if packet.is_empty() {
+            return false;
+        }
+
+        let record = CaptureRecord::now(Direction::Rx, &packet, &lock(&self.config));
+        let datagram = self.framing.encode(&record);
+
+        let mut ok = true;
+        for addr in lock(&self.destinations).iter() {
+            ok &= self.socket.send_to(&datagram, addr).is_ok();
+        }
+
+        let mut stats = lock(&self.stats);
+        match ok {
+            true => stats.forwarded += 1,
+            false => stats.rx_errors += 1,
+        };
+
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::CAPTURE_HEADER_LEN;
+    use crate::mock::MockRadio;
+    use crate::protocol::ModulationFormat;
+
+    fn record(payload: &[u8]) -> CaptureRecord {
+        CaptureRecord {
+            direction: Direction::Rx,
+            timestamp: Duration::from_micros(1_500_000),
+            payload: payload.to_vec(),
+            rssi: Some(-90),
+            lqi: Some(40),
+            frequency: Some(915e6),
+            modulation: Some(ModulationFormat::FSK2),
+        }
+    }
+
+    #[test]
+    fn header_layout() {
+        assert_eq!(UDP_HEADER_LEN, 8 + CAPTURE_HEADER_LEN);
+
+        let full = record(b"hi");
+        let datagram = Framing::Header.encode(&full);
+
+        assert_eq!(datagram.len(), UDP_HEADER_LEN + 2);
+        assert_eq!(datagram[..8], 1_500_000u64.to_le_bytes());
+        assert_eq!(datagram[8..], full.encode());
+        assert_eq!(CaptureRecord::decode(&datagram[8..], full.timestamp), Some(full));
+
+        assert_eq!(Framing::Raw.encode(&record(b"hi")), b"hi");
+    }
+
+    #[test]
+    fn json() {
+        let full = Framing::Json.encode(&record(b"hi"));
+        assert_eq!(
+            String::from_utf8(full).unwrap(),
+            "{\"timestamp\":1.500000,\"rssi\":-90,\"lqi\":40,\"frequency\":915000000,\"modulation\":\"FSK2\",\"payload\":\"aGk=\"}",
+        );
+
+        let bare = CaptureRecord { rssi: None, lqi: None, frequency: None, modulation: None, ..record(&[]) };
+        assert_eq!(
+            String::from_utf8(Framing::Json.encode(&bare)).unwrap(),
+            "{\"timestamp\":1.500000,\"rssi\":null,\"lqi\":null,\"frequency\":null,\"modulation\":null,\"payload\":\"\"}",
+        );
+    }
+
+    #[test]
+    fn loopback() {
+        let mut radio = MockRadio::new();
+        radio.push_rx(b"from air");
+
+        let radio = SharedRadio::new(radio);
+        let mut gateway = UdpGateway::bind(radio.clone(), "127.0.0.1:0").unwrap();
+        gateway.set_framing(Framing::Header);
+        gateway.set_poll_interval(Duration::from_millis(1));
+        gateway.set_config(RadioConfig { frequency: Some(433e6), ..RadioConfig::default() });
+
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        gateway.add_destination(peer.local_addr().unwrap());
+
+        let running = gateway.clone();
+        thread::spawn(move || running.run());
+
+        // the recieved packet is forwarded with its metadata
+        let mut buf = [0u8; 256];
+        let (len, from) = peer.recv_from(&mut buf).unwrap();
+        assert_eq!(from, gateway.local_addr().unwrap());
+
+        let forwarded = CaptureRecord::decode(&buf[8..len], Duration::ZERO).unwrap();
+        assert_eq!(forwarded.payload, b"from air");
+        assert_eq!(forwarded.frequency, Some(433e6));
+
+        // a datagram to the gateway is transmitted
+        peer.send_to(b"to air", from).unwrap();
+
+        let expected = UdpStats { forwarded: 1, transmitted: 1, tx_errors: 0, rx_errors: 0 };
+        for _ in 0..500 {
+            if gateway.stats() == expected {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(gateway.stats(), expected);
+        assert_eq!(radio.with(|r| r.take_transmitted()), vec![b"to air".to_vec()]);
+
+        // empty and oversized datagrams are refused
+        assert!(!gateway.handle_datagram(&[]));
+        assert!(!gateway.handle_datagram(&[0; MAX_PACKET_LEN + 1]));
+        assert_eq!(gateway.stats().tx_errors, 2);
+    }
+}
@@ -0,0 +1,66 @@
+#![cfg(all(feature = "i2c", feature = "std"))]
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{Error as _, ErrorKind, I2c, NoAcknowledgeSource};
+
+use ArmlabRadio::linklog::{read_log, Link, LinkError, LinkEvent, LinkReplay, ReplayI2c};
+use ArmlabRadio::radio_i2c::Radio;
+
+/// recorded through `RecordingI2c`, a board that NACKs its primary address,
+/// answers the ident on the backup address (twice), then NACKs the first read
+/// of a `get_packet` before returning a 3 byte packet
+const CHECK_FOR_DEVICE: &[u8] = include_bytes!("fixtures/check_for_device.log");
+
+/// replay doesn't depend on timing
+struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _: u32) {}
+}
+
+#[test]
+fn replays_check_for_device() {
+    let (link, events) = read_log(CHECK_FOR_DEVICE).unwrap();
+    assert_eq!(link, Link::I2c);
+    assert_eq!(events[1], LinkEvent::Error(LinkError::NoAcknowledgeAddress));
+
+    let replay = LinkReplay::new(events);
+    let mut radio = Radio::from_bus(ReplayI2c::new(replay.clone()), NoDelay).unwrap();
+
+    assert_eq!(radio.address(), 0x35);
+    // nothing but the ident was sent, the delays are the defaults
+    assert_eq!(radio.timing().packet_gather_delay, 10_000);
+    assert!(radio.is_device_available());
+    // only retried because the replayed error is still a NACK
+    assert_eq!(radio.get_packet().unwrap(), vec![1, 2, 3]);
+    // raised by the one retry backoff it took
+    assert_eq!(radio.timing().packet_gather_delay, 10_050);
+
+    replay.finish().unwrap();
+}
+
+#[test]
+fn replayed_errors_keep_their_kind() {
+    let (_, events) = read_log(CHECK_FOR_DEVICE).unwrap();
+    let mut bus = ReplayI2c::new(LinkReplay::new(events));
+
+    let err = bus.write(0x34, &[]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+
+    // anything the log doesn't have is a mismatch
+    let err = bus.write(0x34, &[]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+}
+
+#[test]
+fn version_1_errors_have_no_kind() {
+    let mut log = b"ARMLINK\x01\x01".to_vec();
+    log.extend_from_slice(&[3, 1, 0, 0x34, 2, 0, 0]);
+
+    let (_, events) = read_log(log.as_slice()).unwrap();
+    assert_eq!(events, vec![LinkEvent::Transaction(0x34), LinkEvent::Error(LinkError::Other)]);
+
+    // an error without a kind is only valid in version 1
+    log[7] = 2;
+    assert!(read_log(log.as_slice()).is_err());
+}